@@ -15,31 +15,61 @@ use winit::event::{Event, StartCause};
 use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
 
 use crate::config::{
-    get_config_path, load_and_validate_config, load_or_create_config, save_config,
+    HookStage, get_config_path, load_and_validate_config, load_or_create_config, save_config,
 };
+use crate::control::{
+    ControlRequest, ControlResponse, ControlServer, handle_request, socket_path,
+    spawn_control_server,
+};
+use crate::events::{AppEvent, EventBus, listener_changes};
+use crate::history::{HistoryStore, history_path};
+use crate::hooks::{HookResult, append_hook_results, hook_env, terminate_with_hooks};
 use crate::integrations::brew::{query_brew_services_map, run_brew_stop};
-use crate::integrations::docker::{query_docker_port_map, run_docker_stop};
+use crate::integrations::docker::{
+    ComposeAction, ContainerAction, query_restart_policy, run_compose, run_container_action,
+};
+use crate::integrations::docker_api::{docker_socket_path, spawn_event_watcher};
+use crate::integrations::kubectl::forward_restart_config;
+use crate::integrations::runtime::{ContainerRuntime, enabled_runtimes, query_container_port_map};
+use crate::integrations::ssh::{
+    SshConnection, cancel_forward, read_ssh_connection, ssh_tunnel_for,
+};
+use crate::metrics::{Metrics, spawn_metrics_server};
 use crate::model::*;
 use crate::notify::{maybe_notify_changes, notify_update_available};
-use crate::process::kill::terminate_pid;
-use crate::process::ports::scan_ports;
+use crate::process::details::{describe_command, query_process_details, unix_now};
+use crate::process::identity::capture_identity;
+use crate::process::kill::{ChildSummary, terminate_supervisor};
+use crate::process::monitor::{ListenerMonitor, MonitorPoll};
+use crate::process::scanner::{PortScanner, ScanOptions, default_scanner};
+use crate::process::tree::query_process_table;
+use crate::respawn::{
+    PortOwner, Rebind, RespawnTracker, RespawnWarning, Supervisor, identify_supervisor,
+};
+use crate::restart::{LaunchContext, RestartStore, relaunch, restart_path};
 use crate::ui::icon::{IconVariant, create_template_icon};
 use crate::ui::menu::{
-    build_menu_with_context, build_tooltip, collect_targets_for_all, format_command_label,
-    parse_menu_action,
+    build_menu_with_context, build_tooltip, collect_targets_for_all, format_children_label,
+    format_command_label, parse_menu_action,
 };
+use crate::undo::StoppedService;
 use crate::update::check_for_update;
 
-const IDLE_THRESHOLD: Duration = Duration::from_secs(30);
 const IDLE_MULTIPLIER: u64 = 2; // Idle poll interval = base * IDLE_MULTIPLIER
 const INTEGRATION_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
 const MENU_POLL_INTERVAL: Duration = Duration::from_millis(100);
 const UPDATE_CHECK_DELAY: Duration = Duration::from_secs(5);
+const CONTROL_REPLY_TIMEOUT: Duration = Duration::from_secs(2);
 const DOWNLOAD_URL: &str =
     "https://github.com/gupsammy/PortKiller/releases/latest/download/PortKiller.dmg";
 // menu constants moved under ui::menu
 
 pub fn run() -> Result<()> {
+    run_with_scanner(default_scanner())
+}
+
+/// Run the tray app against an explicit scanner backend.
+pub fn run_with_scanner(scanner: Arc<dyn PortScanner>) -> Result<()> {
     let config = load_or_create_config().context("failed to load configuration")?;
     let shared_config = Arc::new(RwLock::new(config.clone()));
 
@@ -48,9 +78,17 @@ pub fn run() -> Result<()> {
         last_feedback: None,
         config: config.clone(),
         project_cache: HashMap::new(),
+        process_details: HashMap::new(),
+        process_children: HashMap::new(),
+        ssh_connections: HashMap::new(),
         docker_port_map: HashMap::new(),
         brew_services_map: HashMap::new(),
         available_update: None,
+        recent_history: Vec::new(),
+        respawn_warning: None,
+        pending_respawns: Vec::new(),
+        restartable: Vec::new(),
+        undo: Default::default(),
     };
 
     let event_loop = EventLoop::<UserEvent>::with_user_event()
@@ -58,11 +96,45 @@ pub fn run() -> Result<()> {
         .context("failed to create event loop")?;
     let proxy = event_loop.create_proxy();
     let (worker_tx, worker_rx) = crossbeam_channel::unbounded();
+    let events = Arc::new(create_event_bus(&config));
+    let mut history = open_history(&config);
+    if let Some(store) = &history {
+        state.recent_history = store.recent().to_vec();
+    }
+    let mut restarts = open_restarts(&config);
+    if let Some(store) = &restarts {
+        state.restartable = store.specs().to_vec();
+    }
+    let metrics = Arc::new(Metrics::new());
+    if config.metrics.enabled
+        && let Err(err) = spawn_metrics_server(config.metrics.port, metrics.clone())
+    {
+        warn!("Metrics endpoint disabled: {:#}", err);
+    }
 
-    let _monitor_thread = spawn_monitor_thread(proxy.clone(), shared_config.clone());
+    let _monitor_thread = spawn_monitor_thread(
+        proxy.clone(),
+        shared_config.clone(),
+        scanner.clone(),
+        metrics.clone(),
+    );
     let _config_watcher = spawn_config_watcher(proxy.clone(), shared_config.clone());
-    let _worker = spawn_worker(worker_rx, proxy.clone());
+    let _worker = spawn_worker(
+        worker_rx,
+        proxy.clone(),
+        scanner.clone(),
+        events.clone(),
+        metrics.clone(),
+    );
     let _update_checker = spawn_update_checker(proxy.clone(), shared_config.clone());
+    let docker_socket = docker_socket_path().filter(|_| config.integrations.docker_enabled);
+    let _docker_events = docker_socket.map(|socket| {
+        let proxy = proxy.clone();
+        spawn_event_watcher(socket, move || {
+            proxy.send_event(UserEvent::DockerChanged).is_ok()
+        })
+    });
+    let _control_server = start_control_server(proxy.clone(), events.clone(), &config);
     let menu_receiver = MenuEvent::receiver().clone();
 
     let icon =
@@ -83,6 +155,7 @@ pub fn run() -> Result<()> {
     let mut worker_sender: Option<Sender<WorkerCommand>> = Some(worker_tx);
     // Initialize to past time to force first integration refresh
     let mut last_integration_refresh = Instant::now() - INTEGRATION_REFRESH_INTERVAL;
+    let mut respawns = RespawnTracker::default();
     // Clone shared_config for use in event loop (for manual reload)
     let shared_config_for_loop = shared_config.clone();
 
@@ -100,45 +173,83 @@ pub fn run() -> Result<()> {
                     let _ = proxy.send_event(UserEvent::MenuAction(action));
                 }
             }
+            let undo_window = Duration::from_secs(state.config.integrations.undo_window_secs);
+            if state.undo.expire(Instant::now(), undo_window) {
+                sync_menu_with_context(&tray_icon, &state);
+            }
             event_loop
                 .set_control_flow(ControlFlow::WaitUntil(Instant::now() + MENU_POLL_INTERVAL));
         }
         Event::UserEvent(user_event) => match user_event {
-            UserEvent::ProcessesUpdated(processes) => {
+            UserEvent::ProcessesUpdated(processes, details) => {
                 let prev = std::mem::take(&mut state.processes);
                 state.processes = processes;
                 // Detect if ports changed (not just process list) to trigger integration refresh
-                let prev_ports: HashSet<u16> = prev.iter().map(|p| p.port).collect();
-                let curr_ports: HashSet<u16> = state.processes.iter().map(|p| p.port).collect();
+                let prev_ports: HashSet<(u16, Protocol)> =
+                    prev.iter().map(|p| (p.port, p.protocol)).collect();
+                let curr_ports: HashSet<(u16, Protocol)> = state
+                    .processes
+                    .iter()
+                    .map(|p| (p.port, p.protocol))
+                    .collect();
                 let ports_changed = prev_ports != curr_ports;
                 // Refresh integrations when ports change OR on timer (to catch external changes)
                 let timer_refresh =
                     last_integration_refresh.elapsed() >= INTEGRATION_REFRESH_INTERVAL;
                 if ports_changed || timer_refresh {
                     last_integration_refresh = Instant::now();
-                    if state.config.integrations.docker_enabled {
-                        state.docker_port_map = query_docker_port_map().unwrap_or_default();
-                    }
+                    state.docker_port_map =
+                        query_containers(&enabled_runtimes(&state.config.integrations), &metrics);
                     if state.config.integrations.brew_enabled {
-                        state.brew_services_map = query_brew_services_map().unwrap_or_default();
+                        let result = query_brew_services_map();
+                        metrics.set_integration_up("brew", Some(result.is_ok()));
+                        state.brew_services_map = result.unwrap_or_default();
                     }
                 }
                 // Clear maps if integrations disabled (check every time)
-                if !state.config.integrations.docker_enabled {
+                if enabled_runtimes(&state.config.integrations).is_empty() {
                     state.docker_port_map.clear();
                 }
                 if !state.config.integrations.brew_enabled {
                     state.brew_services_map.clear();
+                    metrics.set_integration_up("brew", None);
                 }
                 // Derive project info in best-effort mode
-                refresh_projects_for(&mut state);
+                refresh_projects_for(&mut state, scanner.as_ref());
+                state.apply_details(details);
+                metrics.set_listeners(&state.processes, &state.project_cache);
                 // Notifications on change (before cache cleanup so stopped ports still have project info)
                 maybe_notify_changes(&state, &prev);
+                for event in listener_changes(&prev, &state.processes) {
+                    events.publish(event);
+                }
+                if let Some(store) = history.as_mut() {
+                    store.record(&state, unix_now());
+                    state.recent_history = store.recent().to_vec();
+                }
+                // A killed listener that is already back was respawned by something
+                for rebind in respawns.detect(&state.processes, Instant::now()) {
+                    if let Some(sender) = worker_sender.as_ref() {
+                        let owner = PortOwner::of(&rebind, &state);
+                        let _ = sender.send(WorkerCommand::IdentifySupervisor { rebind, owner });
+                    }
+                }
+                if let Some(warning) = &state.respawn_warning
+                    && !state
+                        .processes
+                        .iter()
+                        .any(|p| p.pid == warning.rebind.new_pid)
+                {
+                    state.next_respawn_warning();
+                }
                 // Clean up stale cache entries for terminated processes
                 let active_pids: HashSet<i32> = state.processes.iter().map(|p| p.pid).collect();
                 state
                     .project_cache
                     .retain(|pid, _| active_pids.contains(pid));
+                state
+                    .process_details
+                    .retain(|pid, _| active_pids.contains(pid));
                 sync_menu_with_context(&tray_icon, &state);
                 update_tray_display(&tray_icon, &state);
             }
@@ -227,10 +338,18 @@ pub fn run() -> Result<()> {
                     sync_menu_with_context(&tray_icon, &state);
                     update_tray_display(&tray_icon, &state);
                 }
-                MenuAction::KillPid { pid, .. } => {
-                    if let Some(target) = describe_pid(pid, &state.processes) {
+                MenuAction::KillPid { pid, force } | MenuAction::KillTree { pid, force } => {
+                    let force_tree = matches!(action, MenuAction::KillTree { .. });
+                    if !force && state.is_pid_protected(pid) {
+                        refuse_protected(&mut state, &format!("PID {}", pid));
+                        update_tray_display(&tray_icon, &state);
+                    } else if let Some(mut target) = describe_pid(pid, &state.processes) {
+                        prepare_target(&mut target, &state, force_tree);
+                        respawns.watch(pid, &state.processes, Instant::now());
+                        let launch = LaunchContext::new(&state.processes, &state.config.restart);
                         if let Some(sender) = worker_sender.as_ref() {
-                            if let Err(err) = sender.send(WorkerCommand::KillPid(target)) {
+                            if let Err(err) = sender.send(WorkerCommand::KillPid { target, launch })
+                            {
                                 let feedback = KillFeedback::error(format!(
                                     "Unable to dispatch kill command: {}",
                                     err
@@ -254,38 +373,21 @@ pub fn run() -> Result<()> {
                     }
                 }
                 MenuAction::KillAll => {
-                    // Filter to only regular processes (exclude Docker and Brew)
-                    let regular_processes: Vec<ProcessInfo> = state
-                        .processes
-                        .iter()
-                        .filter(|p| {
-                            // Exclude Docker containers
-                            if state.docker_port_map.contains_key(&p.port) {
-                                return false;
-                            }
-                            // Exclude Brew services
-                            if crate::integrations::brew::get_brew_managed_service(
-                                &p.command,
-                                p.port,
-                                &state.brew_services_map,
-                            )
-                            .is_some()
-                            {
-                                return false;
-                            }
-                            true
-                        })
-                        .cloned()
-                        .collect();
-
-                    let targets = collect_targets_for_all(&regular_processes);
+                    let mut targets = collect_targets_for_all(&regular_processes(&state), |p| {
+                        state.is_protected(p)
+                    });
+                    for target in &mut targets {
+                        prepare_target(target, &state, false);
+                        respawns.watch(target.pid, &state.processes, Instant::now());
+                    }
                     if targets.is_empty() {
                         state.last_feedback = Some(KillFeedback::info(
                             "No dev port listeners to terminate.".to_string(),
                         ));
                         update_tray_display(&tray_icon, &state);
                     } else if let Some(sender) = worker_sender.as_ref() {
-                        if let Err(err) = sender.send(WorkerCommand::KillAll(targets)) {
+                        let launch = LaunchContext::new(&state.processes, &state.config.restart);
+                        if let Err(err) = sender.send(WorkerCommand::KillAll { targets, launch }) {
                             let feedback = KillFeedback::error(format!(
                                 "Unable to dispatch kill-all command: {}",
                                 err
@@ -302,34 +404,228 @@ pub fn run() -> Result<()> {
                         update_tray_display(&tray_icon, &state);
                     }
                 }
+                MenuAction::KillSupervisor { pid } => {
+                    let supervisor = match state
+                        .respawn_warning
+                        .as_ref()
+                        .and_then(|w| w.supervisor.as_ref())
+                    {
+                        Some(Supervisor::Process {
+                            pid: known,
+                            name,
+                            identity,
+                        }) if *known == pid => Some((name.clone(), identity.clone())),
+                        _ => None,
+                    };
+                    match (supervisor, worker_sender.as_ref()) {
+                        (Some((label, identity)), Some(sender)) => {
+                            let mut target = KillTarget {
+                                pid,
+                                label,
+                                identity: ProcessIdentity::default(),
+                                ladder: Vec::new(),
+                                children: Vec::new(),
+                                tree: false,
+                                hooks: KillHooks::default(),
+                            };
+                            prepare_target(&mut target, &state, false);
+                            // The process seen respawning the listener, not
+                            // whatever holds the PID by the time of the click
+                            target.identity = identity;
+                            let _ = sender.send(WorkerCommand::KillSupervisor(target));
+                        }
+                        (None, _) => {
+                            state.last_feedback = Some(KillFeedback::info(format!(
+                                "The respawn warning for PID {} has expired.",
+                                pid
+                            )));
+                        }
+                        (Some(_), None) => {
+                            state.last_feedback = Some(KillFeedback::error(format!(
+                                "Worker unavailable for PID {}.",
+                                pid
+                            )));
+                        }
+                    }
+                    state.next_respawn_warning();
+                    sync_menu_with_context(&tray_icon, &state);
+                    update_tray_display(&tray_icon, &state);
+                }
+                MenuAction::Restart { port, protocol } => {
+                    let spec = state
+                        .restartable
+                        .iter()
+                        .find(|s| s.port == port && s.protocol == protocol)
+                        .cloned();
+                    match (spec, worker_sender.as_ref()) {
+                        (Some(spec), Some(sender)) => {
+                            state.last_feedback = Some(KillFeedback::info(format!(
+                                "Restarting {}…",
+                                spec.command_line()
+                            )));
+                            let timeout =
+                                Duration::from_secs(state.config.restart.verify_timeout_secs);
+                            let _ = sender.send(WorkerCommand::Restart { spec, timeout });
+                        }
+                        (None, _) => {
+                            state.last_feedback = Some(KillFeedback::info(format!(
+                                "Nothing recorded to restart on port {}.",
+                                protocol.port_label(port)
+                            )));
+                        }
+                        (Some(_), None) => {
+                            state.last_feedback = Some(KillFeedback::error(
+                                "Worker unavailable for restart.".to_string(),
+                            ));
+                        }
+                    }
+                    update_tray_display(&tray_icon, &state);
+                }
+                MenuAction::ReestablishForward { pid, force } => {
+                    let target = describe_pid(pid, &state.processes);
+                    if !force && state.is_pid_protected(pid) {
+                        refuse_protected(&mut state, &format!("PID {}", pid));
+                    } else if let (Some(mut target), Some(sender)) =
+                        (target, worker_sender.as_ref())
+                    {
+                        prepare_target(&mut target, &state, false);
+                        state.last_feedback = Some(KillFeedback::info(format!(
+                            "Re-establishing {}…",
+                            target.label
+                        )));
+                        let restart = forward_restart_config(&state.config.restart);
+                        let timeout = Duration::from_secs(restart.verify_timeout_secs);
+                        let launch = LaunchContext::new(&state.processes, &restart);
+                        let _ = sender.send(WorkerCommand::ReestablishForward {
+                            target,
+                            launch,
+                            timeout,
+                        });
+                    } else {
+                        state.last_feedback = Some(KillFeedback::info(format!(
+                            "PID {} is no longer active.",
+                            pid
+                        )));
+                    }
+                    update_tray_display(&tray_icon, &state);
+                }
+                MenuAction::CancelSshTunnel { pid, port, force } => {
+                    let tunnel = state
+                        .processes
+                        .iter()
+                        .find(|p| p.pid == pid && p.port == port)
+                        .and_then(|process| ssh_tunnel_for(&state, process))
+                        .filter(|connection| connection.multiplexed)
+                        .and_then(|connection| {
+                            let tunnel = connection.tunnel_on(port)?;
+                            Some((connection.cancel_args(tunnel), connection.describe(tunnel)))
+                        });
+                    if !force && state.is_pid_protected(pid) {
+                        refuse_protected(&mut state, &format!("PID {}", pid));
+                    } else if let (Some((args, label)), Some(sender)) =
+                        (tunnel, worker_sender.as_ref())
+                    {
+                        let _ = sender.send(WorkerCommand::CancelSshTunnel { args, label });
+                    } else {
+                        state.last_feedback = Some(KillFeedback::error(format!(
+                            "No ControlMaster tunnel on port {} for PID {}.",
+                            port, pid
+                        )));
+                    }
+                    update_tray_display(&tray_icon, &state);
+                }
+                MenuAction::UndoStop(service) => {
+                    if state.undo.take(&service).is_none() {
+                        state.last_feedback = Some(KillFeedback::info(format!(
+                            "Undo for {} has expired.",
+                            service.name()
+                        )));
+                    } else if let Some(sender) = worker_sender.as_ref() {
+                        let _ = sender.send(WorkerCommand::UndoStop(service));
+                    }
+                    sync_menu_with_context(&tray_icon, &state);
+                    update_tray_display(&tray_icon, &state);
+                }
+                MenuAction::DismissRespawn => {
+                    state.next_respawn_warning();
+                    sync_menu_with_context(&tray_icon, &state);
+                }
                 MenuAction::Quit => {
                     event_loop.exit();
                 }
-                MenuAction::DockerStop { container } => {
-                    if let Some(sender) = worker_sender.as_ref() {
-                        let _ = sender.send(WorkerCommand::DockerStop { container });
+                MenuAction::DockerStop {
+                    container,
+                    runtime,
+                    force,
+                } => {
+                    if !force && state.is_container_protected(&container) {
+                        refuse_protected(&mut state, &format!("Container {}", container));
+                        update_tray_display(&tray_icon, &state);
+                    } else if let Some(sender) = worker_sender.as_ref() {
+                        let _ = sender.send(WorkerCommand::DockerAction {
+                            container,
+                            runtime,
+                            action: ContainerAction::Stop,
+                        });
+                    }
+                }
+                MenuAction::DockerAction {
+                    container,
+                    runtime,
+                    action,
+                } => {
+                    if state.is_container_protected(&container) {
+                        refuse_protected(&mut state, &format!("Container {}", container));
+                        update_tray_display(&tray_icon, &state);
+                    } else if let Some(sender) = worker_sender.as_ref() {
+                        let _ = sender.send(WorkerCommand::DockerAction {
+                            container,
+                            runtime,
+                            action,
+                        });
                     }
                 }
                 MenuAction::DockerStopAll => {
                     if let Some(sender) = worker_sender.as_ref() {
-                        // Collect all unique Docker containers from current processes
-                        let containers: Vec<String> = state
+                        // Every mapped container once; the same name under two
+                        // runtimes is two containers
+                        let containers: HashSet<(ContainerRuntime, String)> = state
                             .docker_port_map
                             .values()
-                            .map(|dc| dc.name.clone())
-                            .collect::<HashSet<_>>()
-                            .into_iter()
+                            .filter(|dc| !state.is_container_protected(&dc.name))
+                            .map(|dc| (dc.runtime, dc.name.clone()))
                             .collect();
 
-                        for container in containers {
-                            let _ = sender.send(WorkerCommand::DockerStop {
-                                container: container.clone(),
+                        for (runtime, container) in containers {
+                            let _ = sender.send(WorkerCommand::DockerAction {
+                                runtime,
+                                container,
+                                action: ContainerAction::Stop,
                             });
                         }
                     }
                 }
-                MenuAction::BrewStop { service } => {
-                    if let Some(sender) = worker_sender.as_ref() {
+                MenuAction::Compose(action) => {
+                    let service = match &action {
+                        ComposeAction::RestartService { service, .. } => Some(service.as_str()),
+                        _ => None,
+                    };
+                    if state.is_compose_protected(action.project(), service) {
+                        state.last_feedback = Some(KillFeedback::warning(format!(
+                            "Compose project {} has protected containers; stop the others individually.",
+                            action.project()
+                        )));
+                        update_tray_display(&tray_icon, &state);
+                    } else if let Some(sender) = worker_sender.as_ref() {
+                        let runtime = state.compose_runtime(action.project());
+                        let _ = sender.send(WorkerCommand::Compose { action, runtime });
+                    }
+                }
+                MenuAction::BrewStop { service, force } => {
+                    if !force && state.is_brew_service_protected(&service) {
+                        refuse_protected(&mut state, &format!("Service {}", service));
+                        update_tray_display(&tray_icon, &state);
+                    } else if let Some(sender) = worker_sender.as_ref() {
                         let _ = sender.send(WorkerCommand::BrewStop { service });
                     }
                 }
@@ -346,6 +642,7 @@ pub fn run() -> Result<()> {
                                     &state.brew_services_map,
                                 )
                             })
+                            .filter(|service| !state.is_brew_service_protected(service))
                             .collect::<HashSet<_>>()
                             .into_iter()
                             .collect();
@@ -367,6 +664,7 @@ pub fn run() -> Result<()> {
                             state.config = new_config;
                             state.last_feedback =
                                 Some(KillFeedback::info("Configuration reloaded".to_string()));
+                            events.publish(AppEvent::ConfigReloaded);
                         }
                         Err(e) => {
                             state.last_feedback =
@@ -433,21 +731,82 @@ pub fn run() -> Result<()> {
                 }
             },
             UserEvent::KillFeedback(feedback) => {
+                respawns.confirm(&feedback.stopped_pids, Instant::now());
+                let mut menu_changed = false;
+                for spec in &feedback.launches {
+                    if let Some(store) = restarts.as_mut()
+                        && let Err(err) = store.remember(spec.clone())
+                    {
+                        warn!("Failed to save restart info: {:#}", err);
+                    }
+                    state
+                        .restartable
+                        .retain(|s| (s.port, s.protocol) != (spec.port, spec.protocol));
+                    state.restartable.insert(0, spec.clone());
+                    menu_changed = true;
+                }
+                let undo_window = Duration::from_secs(state.config.integrations.undo_window_secs);
+                if let Some(service) = feedback.undoable.clone()
+                    && !undo_window.is_zero()
+                {
+                    state.undo.push(service, Instant::now());
+                    menu_changed = true;
+                }
                 state.last_feedback = Some(feedback);
+                if menu_changed {
+                    sync_menu_with_context(&tray_icon, &state);
+                }
                 update_tray_display(&tray_icon, &state);
             }
+            UserEvent::DockerChanged => {
+                if state.config.integrations.docker_enabled
+                    && let Some(sender) = worker_sender.as_ref()
+                {
+                    let runtimes = enabled_runtimes(&state.config.integrations);
+                    let _ = sender.send(WorkerCommand::RefreshContainers(runtimes));
+                }
+            }
+            UserEvent::ContainersUpdated(map) => {
+                if map != state.docker_port_map {
+                    state.docker_port_map = map;
+                    sync_menu_with_context(&tray_icon, &state);
+                    update_tray_display(&tray_icon, &state);
+                }
+            }
+            UserEvent::RespawnIdentified(warning) => {
+                // The respawned listener may be gone again by now
+                if state
+                    .processes
+                    .iter()
+                    .any(|p| p.pid == warning.rebind.new_pid)
+                {
+                    warn!("{}", warning.message());
+                    state.last_feedback = Some(KillFeedback::warning(warning.message()));
+                    state.push_respawn_warning(warning);
+                    sync_menu_with_context(&tray_icon, &state);
+                    update_tray_display(&tray_icon, &state);
+                }
+            }
             UserEvent::MonitorError(message) => {
                 warn!("Monitor error: {}", message);
                 state.last_feedback = Some(KillFeedback::error(message));
                 update_tray_display(&tray_icon, &state);
             }
             UserEvent::ConfigReloaded(new_config) => {
-                state.config = new_config;
+                state.config = *new_config;
+                events.publish(AppEvent::ConfigReloaded);
                 state.last_feedback =
                     Some(KillFeedback::info("Configuration reloaded".to_string()));
                 sync_menu_with_context(&tray_icon, &state);
                 update_tray_display(&tray_icon, &state);
             }
+            UserEvent::ControlRequest(request, reply) => {
+                let (response, actions) = handle_request(&state, request);
+                for action in actions {
+                    let _ = proxy.send_event(UserEvent::MenuAction(action));
+                }
+                let _ = reply.send(response);
+            }
             UserEvent::ConfigReloadFailed(message) => {
                 state.last_feedback = Some(KillFeedback::error(message));
                 update_tray_display(&tray_icon, &state);
@@ -491,16 +850,18 @@ pub fn run() -> Result<()> {
 fn spawn_monitor_thread(
     proxy: EventLoopProxy<UserEvent>,
     shared_config: Arc<RwLock<crate::config::Config>>,
+    scanner: Arc<dyn PortScanner>,
+    metrics: Arc<Metrics>,
 ) -> thread::JoinHandle<()> {
     thread::spawn(move || {
-        let mut previous: Vec<ProcessInfo> = Vec::new();
-        let mut last_change = Instant::now();
+        let mut monitor = ListenerMonitor::new(scanner);
+        let mut ssh_connections = HashMap::new();
         loop {
             // Read current config at each iteration to pick up hot-reloaded changes
-            let (port_ranges, poll_interval_secs) = {
+            let (scan_options, poll_interval_secs) = {
                 let cfg = shared_config.read().unwrap();
                 (
-                    cfg.monitoring.port_ranges.clone(),
+                    ScanOptions::from(&cfg.monitoring),
                     cfg.monitoring.poll_interval_secs,
                 )
             };
@@ -508,40 +869,42 @@ fn spawn_monitor_thread(
             let poll_interval_idle = Duration::from_secs(poll_interval_secs * IDLE_MULTIPLIER);
 
             let scan_start = Instant::now();
-            match scan_ports(&port_ranges) {
-                Ok(mut processes) => {
-                    let scan_duration = scan_start.elapsed();
-                    processes.sort();
-                    if processes != previous {
-                        log::debug!(
-                            "Change detected (scan took {:?}). Polling immediately for rapid changes.",
-                            scan_duration
-                        );
-                        last_change = Instant::now();
-                        previous = processes.clone();
-                        if proxy
-                            .send_event(UserEvent::ProcessesUpdated(processes))
-                            .is_err()
-                        {
-                            break;
-                        }
-                        continue;
-                    } else {
-                        // Adaptive polling: use longer interval when idle
-                        let poll_interval = if last_change.elapsed() > IDLE_THRESHOLD {
-                            poll_interval_idle
-                        } else {
-                            poll_interval_active
-                        };
-                        log::trace!(
-                            "No change (scan took {:?}). Sleeping {}s (idle: {}).",
-                            scan_duration,
-                            poll_interval.as_secs(),
-                            last_change.elapsed() > IDLE_THRESHOLD
-                        );
-                        thread::sleep(poll_interval);
+            let poll = monitor.poll(&scan_options);
+            let scan_time = scan_start.elapsed();
+            if poll.is_ok() {
+                metrics.observe_scan(scan_time);
+            }
+            match poll {
+                Ok(MonitorPoll::Changed(processes)) => {
+                    log::debug!(
+                        "Change detected (scan took {:?}). Polling immediately for rapid changes.",
+                        scan_time
+                    );
+                    let details = query_listener_details(&processes, &ssh_connections);
+                    ssh_connections = details.ssh_connections.clone();
+                    if proxy
+                        .send_event(UserEvent::ProcessesUpdated(processes, details))
+                        .is_err()
+                    {
+                        break;
                     }
                 }
+                Ok(MonitorPoll::Unchanged) => {
+                    // Adaptive polling: use longer interval when idle
+                    let idle = monitor.is_idle();
+                    let poll_interval = if idle {
+                        poll_interval_idle
+                    } else {
+                        poll_interval_active
+                    };
+                    log::trace!(
+                        "No change (scan took {:?}). Sleeping {}s (idle: {}).",
+                        scan_time,
+                        poll_interval.as_secs(),
+                        idle
+                    );
+                    thread::sleep(poll_interval);
+                }
                 Err(err) => {
                     let message = format!("{}", err);
                     if proxy.send_event(UserEvent::MonitorError(message)).is_err() {
@@ -556,6 +919,65 @@ fn spawn_monitor_thread(
 
 const CONFIG_DEBOUNCE_DURATION: Duration = Duration::from_millis(500);
 
+fn create_event_bus(config: &crate::config::Config) -> EventBus {
+    let Some(path) = &config.events.ndjson_path else {
+        return EventBus::new();
+    };
+    EventBus::with_file(path).unwrap_or_else(|err| {
+        warn!("Event log disabled: {:#}", err);
+        EventBus::new()
+    })
+}
+
+fn open_history(config: &crate::config::Config) -> Option<HistoryStore> {
+    if !config.history.enabled {
+        return None;
+    }
+    let path = history_path(&config.history);
+    HistoryStore::open(&path, config.history.retention_days, unix_now())
+        .map_err(|err| warn!("History disabled: {:#}", err))
+        .ok()
+}
+
+fn open_restarts(config: &crate::config::Config) -> Option<RestartStore> {
+    if !config.restart.enabled {
+        return None;
+    }
+    RestartStore::open(&restart_path(&config.restart))
+        .map_err(|err| warn!("Restart disabled: {:#}", err))
+        .ok()
+}
+
+fn start_control_server(
+    proxy: EventLoopProxy<UserEvent>,
+    events: Arc<EventBus>,
+    config: &crate::config::Config,
+) -> Option<ControlServer> {
+    if !config.control.enabled {
+        return None;
+    }
+    let path = socket_path(&config.control);
+    let dispatch = move |request: ControlRequest| {
+        let (reply_tx, reply_rx) = crossbeam_channel::bounded(1);
+        if proxy
+            .send_event(UserEvent::ControlRequest(request, reply_tx))
+            .is_err()
+        {
+            return ControlResponse::error("app is shutting down");
+        }
+        reply_rx
+            .recv_timeout(CONTROL_REPLY_TIMEOUT)
+            .unwrap_or_else(|_| ControlResponse::error("timed out waiting for the app"))
+    };
+    match spawn_control_server(&path, events, dispatch) {
+        Ok(server) => Some(server),
+        Err(err) => {
+            warn!("Control socket disabled: {:#}", err);
+            None
+        }
+    }
+}
+
 fn spawn_update_checker(
     proxy: EventLoopProxy<UserEvent>,
     shared_config: Arc<RwLock<crate::config::Config>>,
@@ -686,7 +1108,8 @@ fn spawn_config_watcher(
                                 if let Ok(mut cfg) = shared_config.write() {
                                     *cfg = new_config.clone();
                                 }
-                                let _ = proxy.send_event(UserEvent::ConfigReloaded(new_config));
+                                let _ = proxy
+                                    .send_event(UserEvent::ConfigReloaded(Box::new(new_config)));
                             }
                             Err(e) => {
                                 let msg = format!("Config reload failed: {}", e);
@@ -707,18 +1130,129 @@ fn spawn_config_watcher(
 fn spawn_worker(
     rx: Receiver<WorkerCommand>,
     proxy: EventLoopProxy<UserEvent>,
+    scanner: Arc<dyn PortScanner>,
+    events: Arc<EventBus>,
+    metrics: Arc<Metrics>,
 ) -> thread::JoinHandle<()> {
     thread::spawn(move || {
         for command in rx.iter() {
             let should_continue = match command {
-                WorkerCommand::KillPid(target) => handle_single_kill(&proxy, target),
-                WorkerCommand::KillAll(targets) => handle_batch_kill(&proxy, targets),
-                WorkerCommand::DockerStop { container } => {
-                    let feedback = run_docker_stop(&container);
+                WorkerCommand::KillPid { target, launch } => {
+                    handle_single_kill(&proxy, scanner.as_ref(), &events, &metrics, target, &launch)
+                }
+                WorkerCommand::KillSupervisor(mut target) => {
+                    collect_children(&mut target);
+                    let (outcome, children) = terminate_supervisor(&target);
+                    metrics.record_kill(&outcome);
+                    let feedback = kill_feedback(&target, outcome, children);
+                    publish_kill_event(
+                        &events,
+                        Some(target.pid),
+                        target.label.clone(),
+                        kill_succeeded(&outcome),
+                        &feedback,
+                    );
+                    proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
+                }
+                WorkerCommand::Restart { spec, timeout } => {
+                    let feedback = match relaunch(&spec, scanner.as_ref(), timeout) {
+                        Ok(pid) => KillFeedback::info(format!(
+                            "Restarted {} on port {} (PID {}).",
+                            spec.command_line(),
+                            spec.protocol.port_label(spec.port),
+                            pid
+                        )),
+                        Err(err) => KillFeedback::error(format!("Restart failed: {:#}.", err)),
+                    };
+                    proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
+                }
+                WorkerCommand::ReestablishForward {
+                    target,
+                    launch,
+                    timeout,
+                } => handle_reestablish(
+                    &proxy,
+                    scanner.as_ref(),
+                    &events,
+                    &metrics,
+                    target,
+                    &launch,
+                    timeout,
+                ),
+                WorkerCommand::IdentifySupervisor { rebind, owner } => proxy
+                    .send_event(UserEvent::RespawnIdentified(respawn_warning(
+                        rebind, &owner,
+                    )))
+                    .is_ok(),
+                WorkerCommand::RefreshContainers(runtimes) => proxy
+                    .send_event(UserEvent::ContainersUpdated(query_containers(
+                        &runtimes, &metrics,
+                    )))
+                    .is_ok(),
+                WorkerCommand::CancelSshTunnel { args, label } => {
+                    let feedback = cancel_forward(&args, &label);
+                    let succeeded = !matches!(feedback.severity, FeedbackSeverity::Error);
+                    publish_kill_event(&events, None, label, succeeded, &feedback);
+                    proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
+                }
+                WorkerCommand::UndoStop(service) => proxy
+                    .send_event(UserEvent::KillFeedback(service.start()))
+                    .is_ok(),
+                WorkerCommand::KillAll { targets, launch } => handle_batch_kill(
+                    &proxy,
+                    scanner.as_ref(),
+                    &events,
+                    &metrics,
+                    targets,
+                    &launch,
+                ),
+                WorkerCommand::DockerAction {
+                    container,
+                    runtime,
+                    action,
+                } => {
+                    let mut feedback = run_container_action(runtime, &container, action);
+                    let succeeded = !matches!(feedback.severity, FeedbackSeverity::Error);
+                    publish_kill_event(
+                        &events,
+                        None,
+                        format!("container {}", container),
+                        succeeded,
+                        &feedback,
+                    );
+                    // A warning means the container was already gone
+                    if action.undoable() && matches!(feedback.severity, FeedbackSeverity::Info) {
+                        feedback.undoable = Some(StoppedService::Container {
+                            name: container,
+                            runtime,
+                        });
+                    }
+                    proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
+                }
+                WorkerCommand::Compose { action, runtime } => {
+                    let feedback = run_compose(runtime, &action);
+                    publish_kill_event(
+                        &events,
+                        None,
+                        format!("compose project {}", action.project()),
+                        !matches!(feedback.severity, FeedbackSeverity::Error),
+                        &feedback,
+                    );
                     proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
                 }
                 WorkerCommand::BrewStop { service } => {
-                    let feedback = run_brew_stop(&service);
+                    let mut feedback = run_brew_stop(&service);
+                    let succeeded = !matches!(feedback.severity, FeedbackSeverity::Error);
+                    publish_kill_event(
+                        &events,
+                        None,
+                        format!("brew service {}", service),
+                        succeeded,
+                        &feedback,
+                    );
+                    if succeeded {
+                        feedback.undoable = Some(StoppedService::BrewService(service));
+                    }
                     proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
                 }
             };
@@ -729,12 +1263,137 @@ fn spawn_worker(
     })
 }
 
-fn handle_single_kill(proxy: &EventLoopProxy<UserEvent>, target: KillTarget) -> bool {
-    let outcome = terminate_pid(target.pid);
-    let feedback = match outcome {
-        KillOutcome::Success => {
-            KillFeedback::info(format!("Terminated {} (PID {}).", target.label, target.pid))
+fn handle_single_kill(
+    proxy: &EventLoopProxy<UserEvent>,
+    scanner: &dyn PortScanner,
+    events: &EventBus,
+    metrics: &Metrics,
+    mut target: KillTarget,
+    launch: &LaunchContext,
+) -> bool {
+    // Read before the kill, while the process is still there to read
+    let spec = launch.capture(target.pid, scanner);
+    collect_children(&mut target);
+    let (outcome, children, hooks) = terminate_with_hooks(&target, scanner);
+    metrics.record_kill(&outcome);
+    let mut feedback = kill_feedback(&target, outcome, children);
+    append_hook_results(&mut feedback, &hooks);
+    publish_kill_event(
+        events,
+        Some(target.pid),
+        target.label.clone(),
+        kill_succeeded(&outcome),
+        &feedback,
+    );
+    if kill_succeeded(&outcome) {
+        feedback.stopped_pids.push(target.pid);
+        feedback.launches.extend(spec);
+    }
+    proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
+}
+
+/// Stop a port-forward and start it again with the command line read just
+/// before the kill.
+fn handle_reestablish(
+    proxy: &EventLoopProxy<UserEvent>,
+    scanner: &dyn PortScanner,
+    events: &EventBus,
+    metrics: &Metrics,
+    mut target: KillTarget,
+    launch: &LaunchContext,
+    timeout: Duration,
+) -> bool {
+    let Some(spec) = launch.capture(target.pid, scanner) else {
+        let feedback = KillFeedback::error(format!(
+            "Could not read the port-forward command of PID {}.",
+            target.pid
+        ));
+        return proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok();
+    };
+    collect_children(&mut target);
+    let (outcome, _, hooks) = terminate_with_hooks(&target, scanner);
+    metrics.record_kill(&outcome);
+    let mut feedback = if !kill_succeeded(&outcome) {
+        kill_feedback(&target, outcome, ChildSummary::default())
+    } else {
+        match relaunch(&spec, scanner, timeout) {
+            Ok(pid) => KillFeedback::info(format!(
+                "Re-established {} (PID {}).",
+                spec.command_line(),
+                pid
+            )),
+            Err(err) => KillFeedback::error(format!(
+                "Stopped the forward but could not start it again: {:#}.",
+                err
+            )),
         }
+    };
+    append_hook_results(&mut feedback, &hooks);
+    publish_kill_event(
+        events,
+        Some(target.pid),
+        target.label.clone(),
+        kill_succeeded(&outcome),
+        &feedback,
+    );
+    proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
+}
+
+/// Published ports from every enabled container runtime; each runtime's
+/// health goes to the metrics.
+fn query_containers(
+    runtimes: &[ContainerRuntime],
+    metrics: &Metrics,
+) -> HashMap<PublishedPort, DockerContainerInfo> {
+    let query = query_container_port_map(runtimes);
+    for runtime in ContainerRuntime::ALL {
+        metrics.set_integration_up(runtime.binary(), query.status(runtime));
+    }
+    query.ports
+}
+
+/// A protected target was clicked without the explicit override.
+fn refuse_protected(state: &mut AppState, what: &str) {
+    state.last_feedback = Some(KillFeedback::warning(format!(
+        "{} is protected; use the \"Anyway\" item to stop it.",
+        what
+    )));
+}
+
+/// Name what most likely respawned a killed listener.
+fn respawn_warning(rebind: Rebind, owner: &PortOwner) -> RespawnWarning {
+    let table = query_process_table();
+    let mut supervisor = identify_supervisor(&rebind, owner, &table, |container| {
+        query_restart_policy(container.runtime, &container.name).ok()
+    });
+    // `ps -o comm` says "node" for nodemon and pm2; the arguments say more
+    if let Some(Supervisor::Process { pid, name, .. }) = &mut supervisor {
+        *name = describe_command(name, query_process_details(&[*pid]).get(pid));
+    }
+    RespawnWarning { rebind, supervisor }
+}
+
+/// User-facing summary of a single kill.
+pub(crate) fn kill_feedback(
+    target: &KillTarget,
+    outcome: KillOutcome,
+    children: ChildSummary,
+) -> KillFeedback {
+    let mut feedback = match outcome {
+        KillOutcome::Success { step: 0, signal } => KillFeedback::info(format!(
+            "Terminated {} (PID {}) with {}.",
+            target.label,
+            target.pid,
+            signal.as_str()
+        )),
+        KillOutcome::Success { step, signal } => KillFeedback::info(format!(
+            "Terminated {} (PID {}) after escalating to {} (step {} of {}).",
+            target.label,
+            target.pid,
+            signal.as_str(),
+            step + 1,
+            target.ladder.len()
+        )),
         KillOutcome::AlreadyExited => KillFeedback::warning(format!(
             "{} (PID {}) was already stopped.",
             target.label, target.pid
@@ -747,16 +1406,76 @@ fn handle_single_kill(proxy: &EventLoopProxy<UserEvent>, target: KillTarget) ->
             "Timed out terminating {} (PID {}).",
             target.label, target.pid
         )),
+        KillOutcome::Replaced => KillFeedback::warning(format!(
+            "PID {} now belongs to a different process; {} was not killed.",
+            target.pid, target.label
+        )),
         KillOutcome::Failed(err) => KillFeedback::error(format!(
             "Failed to terminate {} (PID {}): {}.",
             target.label, target.pid, err
         )),
     };
+    append_child_summary(&mut feedback, children);
+    feedback
+}
 
-    proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
+fn publish_kill_event(
+    events: &EventBus,
+    pid: Option<i32>,
+    target: String,
+    succeeded: bool,
+    feedback: &KillFeedback,
+) {
+    let message = feedback.message.clone();
+    events.publish(if succeeded {
+        AppEvent::KillSucceeded {
+            pid,
+            target,
+            message,
+        }
+    } else {
+        AppEvent::KillFailed {
+            pid,
+            target,
+            message,
+        }
+    });
 }
 
-fn handle_batch_kill(proxy: &EventLoopProxy<UserEvent>, targets: Vec<KillTarget>) -> bool {
+// The port is free afterwards, whether or not we were the ones to stop it.
+pub(crate) fn kill_succeeded(outcome: &KillOutcome) -> bool {
+    matches!(
+        outcome,
+        KillOutcome::Success { .. } | KillOutcome::AlreadyExited
+    )
+}
+
+fn append_child_summary(feedback: &mut KillFeedback, children: ChildSummary) {
+    if children.stopped > 0 {
+        feedback.message.push_str(&format!(
+            " Also stopped {} child process(es).",
+            children.stopped
+        ));
+    }
+    if children.survived > 0 {
+        feedback.message.push_str(&format!(
+            " {} child process(es) still running.",
+            children.survived
+        ));
+        if matches!(feedback.severity, FeedbackSeverity::Info) {
+            feedback.severity = FeedbackSeverity::Warning;
+        }
+    }
+}
+
+fn handle_batch_kill(
+    proxy: &EventLoopProxy<UserEvent>,
+    scanner: &dyn PortScanner,
+    events: &EventBus,
+    metrics: &Metrics,
+    targets: Vec<KillTarget>,
+    launch: &LaunchContext,
+) -> bool {
     if targets.is_empty() {
         return proxy
             .send_event(UserEvent::KillFeedback(KillFeedback::info(
@@ -766,14 +1485,45 @@ fn handle_batch_kill(proxy: &EventLoopProxy<UserEvent>, targets: Vec<KillTarget>
     }
 
     let mut successes = 0usize;
+    let mut escalated = 0usize;
     let mut already = 0usize;
     let mut denied = 0usize;
     let mut timed_out = 0usize;
+    let mut replaced = 0usize;
     let mut failures: Vec<(KillTarget, Errno)> = Vec::new();
-
-    for target in targets {
-        match terminate_pid(target.pid) {
-            KillOutcome::Success => successes += 1,
+    let mut children = ChildSummary::default();
+    let mut stopped_pids = Vec::new();
+    let mut launches = Vec::new();
+    let mut hook_results: Vec<HookResult> = Vec::new();
+
+    for mut target in targets {
+        let spec = launch.capture(target.pid, scanner);
+        collect_children(&mut target);
+        let (outcome, summary, hooks) = terminate_with_hooks(&target, scanner);
+        metrics.record_kill(&outcome);
+        let mut feedback = kill_feedback(&target, outcome, summary);
+        append_hook_results(&mut feedback, &hooks);
+        hook_results.extend(hooks);
+        publish_kill_event(
+            events,
+            Some(target.pid),
+            target.label.clone(),
+            kill_succeeded(&outcome),
+            &feedback,
+        );
+        children.stopped += summary.stopped;
+        children.survived += summary.survived;
+        if kill_succeeded(&outcome) {
+            stopped_pids.push(target.pid);
+            launches.extend(spec);
+        }
+        match outcome {
+            KillOutcome::Success { step, .. } => {
+                successes += 1;
+                if step > 0 {
+                    escalated += 1;
+                }
+            }
             KillOutcome::AlreadyExited => already += 1,
             KillOutcome::PermissionDenied => {
                 denied += 1;
@@ -783,23 +1533,28 @@ fn handle_batch_kill(proxy: &EventLoopProxy<UserEvent>, targets: Vec<KillTarget>
                 timed_out += 1;
                 failures.push((target, Errno::ETIMEDOUT));
             }
+            KillOutcome::Replaced => replaced += 1,
             KillOutcome::Failed(err) => failures.push((target, err)),
         }
     }
 
     let failure_count = failures.len();
-    let severity = if successes > 0 && failure_count == 0 && denied == 0 && timed_out == 0 {
-        FeedbackSeverity::Info
-    } else if successes > 0 {
-        FeedbackSeverity::Warning
-    } else {
-        FeedbackSeverity::Error
-    };
+    let severity =
+        if successes > 0 && failure_count == 0 && denied == 0 && timed_out == 0 && replaced == 0 {
+            FeedbackSeverity::Info
+        } else if successes > 0 {
+            FeedbackSeverity::Warning
+        } else {
+            FeedbackSeverity::Error
+        };
 
     let mut parts = Vec::new();
     if successes > 0 {
         parts.push(format!("terminated {}", successes));
     }
+    if escalated > 0 {
+        parts.push(format!("{} needed escalation", escalated));
+    }
     if already > 0 {
         parts.push(format!("{} already stopped", already));
     }
@@ -809,6 +1564,9 @@ fn handle_batch_kill(proxy: &EventLoopProxy<UserEvent>, targets: Vec<KillTarget>
     if timed_out > 0 {
         parts.push(format!("{} timed out", timed_out));
     }
+    if replaced > 0 {
+        parts.push(format!("{} replaced by another process", replaced));
+    }
     if failure_count > 0 {
         parts.push(format!("{} failed", failure_count));
     }
@@ -825,7 +1583,11 @@ fn handle_batch_kill(proxy: &EventLoopProxy<UserEvent>, targets: Vec<KillTarget>
         ));
     }
 
-    let feedback = KillFeedback::new(message, severity);
+    let mut feedback = KillFeedback::new(message, severity);
+    feedback.stopped_pids = stopped_pids;
+    feedback.launches = launches;
+    append_child_summary(&mut feedback, children);
+    append_hook_results(&mut feedback, &hook_results);
     proxy.send_event(UserEvent::KillFeedback(feedback)).is_ok()
 }
 
@@ -835,7 +1597,29 @@ fn handle_batch_kill(proxy: &EventLoopProxy<UserEvent>, targets: Vec<KillTarget>
 
 // collect_targets_for_all now in ui::menu
 
-fn describe_pid(pid: i32, processes: &[ProcessInfo]) -> Option<KillTarget> {
+/// Listeners that are plain processes, i.e. not owned by Docker or a brew service.
+pub(crate) fn regular_processes(state: &AppState) -> Vec<ProcessInfo> {
+    state
+        .processes
+        .iter()
+        .filter(|p| {
+            // Exclude Docker containers
+            if state.container_for(p).is_some() {
+                return false;
+            }
+            // Exclude Brew services
+            crate::integrations::brew::get_brew_managed_service(
+                &p.command,
+                p.port,
+                &state.brew_services_map,
+            )
+            .is_none()
+        })
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn describe_pid(pid: i32, processes: &[ProcessInfo]) -> Option<KillTarget> {
     let mut ports = Vec::new();
     let mut command: Option<String> = None;
     for process in processes.iter().filter(|p| p.pid == pid) {
@@ -853,7 +1637,83 @@ fn describe_pid(pid: i32, processes: &[ProcessInfo]) -> Option<KillTarget> {
 
     ports.sort();
     let label = format_command_label(command.as_deref().unwrap_or(""), &ports);
-    Some(KillTarget { pid, label })
+    Some(KillTarget {
+        pid,
+        label,
+        identity: ProcessIdentity::default(),
+        ladder: Vec::new(),
+        children: Vec::new(),
+        tree: false,
+        hooks: KillHooks::default(),
+    })
+}
+
+/// Fill in what a kill needs from live state: the identity of the process
+/// as listed, the signal ladder and hooks configured for it and whether to
+/// stop its descendants too. Those are listed later by `collect_children`.
+pub(crate) fn prepare_target(target: &mut KillTarget, state: &AppState, force_tree: bool) {
+    let listeners: Vec<&ProcessInfo> = state
+        .processes
+        .iter()
+        .filter(|p| p.pid == target.pid)
+        .collect();
+    let command = listeners.first().map(|p| p.command.as_str()).unwrap_or("");
+    let mut ports: Vec<u16> = listeners.iter().map(|p| p.port).collect();
+    ports.sort();
+    ports.dedup();
+    target.ladder = state.config.kill.ladder_for(command, &ports);
+    target.hooks = KillHooks {
+        pre: state.config.kill.hooks_for(HookStage::Pre, command, &ports),
+        post: state
+            .config
+            .kill
+            .hooks_for(HookStage::Post, command, &ports),
+        env: Vec::new(),
+    };
+    if !target.hooks.pre.is_empty() || !target.hooks.post.is_empty() {
+        let container = listeners
+            .iter()
+            .find_map(|p| state.container_for(p))
+            .map(|c| c.name.as_str());
+        target.hooks.env = hook_env(
+            target.pid,
+            command,
+            &ports,
+            state.project_cache.get(&target.pid),
+            container,
+        );
+    }
+    target.identity = capture_identity(
+        target.pid,
+        state.process_details.get(&target.pid).map(|d| d.start_time),
+    );
+
+    target.tree = force_tree || state.config.kill.tree_for(command, &ports);
+}
+
+/// List a tree kill's descendants. Called on the worker right before the
+/// kill: it runs `ps` for the whole table, and workers may have spawned
+/// since the menu was built.
+pub(crate) fn collect_children(target: &mut KillTarget) {
+    if !target.tree {
+        return;
+    }
+    let descendants = query_process_table().descendants(target.pid);
+    if !descendants.is_empty() {
+        target.label = format!("{} + {}", target.label, format_children_label(&descendants));
+    }
+    target.children = descendants
+        .into_iter()
+        .map(|child| KillTarget {
+            pid: child.pid,
+            identity: capture_identity(child.pid, None),
+            label: child.command,
+            ladder: target.ladder.clone(),
+            children: Vec::new(),
+            tree: false,
+            hooks: KillHooks::default(),
+        })
+        .collect();
 }
 
 // format_command_label now in ui::menu
@@ -882,13 +1742,17 @@ fn update_tray_display(tray_icon: &TrayIcon, state: &AppState) {
         tray_icon.set_icon_as_template(true);
     }
 
-    let tooltip = build_tooltip(&state.processes, state.last_feedback.as_ref());
+    let tooltip = build_tooltip(
+        &state.processes,
+        &state.process_details,
+        state.last_feedback.as_ref(),
+    );
     if let Err(err) = tray_icon.set_tooltip(Some(tooltip.as_str())) {
         error!("Failed to update tooltip: {}", err);
     }
 }
 
-fn refresh_projects_for(state: &mut AppState) {
+pub(crate) fn refresh_projects_for(state: &mut AppState, scanner: &dyn PortScanner) {
     let mut missing: HashSet<i32> = HashSet::new();
     for p in &state.processes {
         if !state.project_cache.contains_key(&p.pid) {
@@ -896,14 +1760,59 @@ fn refresh_projects_for(state: &mut AppState) {
         }
     }
     for pid in missing {
-        if let Some(info) = resolve_project_info(pid) {
+        if let Some(info) = resolve_project_info(pid, scanner) {
             state.project_cache.insert(pid, info);
         }
     }
 }
 
-fn resolve_project_info(pid: i32) -> Option<ProjectInfo> {
-    let path = get_process_cwd(pid)?;
+// The CLI's one-shot lookup; the tray gets these from the monitor thread.
+pub(crate) fn refresh_details_for(state: &mut AppState) {
+    let details = query_listener_details(&state.processes, &state.ssh_connections);
+    state.apply_details(details);
+}
+
+// Re-query every listed PID in one ps call so CPU and memory stay current.
+// ssh command lines are parsed once per PID, as that reads the config file;
+// `known_ssh` holds the earlier results.
+pub(crate) fn query_listener_details(
+    processes: &[ProcessInfo],
+    known_ssh: &HashMap<i32, Option<SshConnection>>,
+) -> ListenerDetails {
+    let pids: Vec<i32> = processes
+        .iter()
+        .map(|p| p.pid)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let details = query_process_details(&pids);
+    let ssh_connections = processes
+        .iter()
+        .filter(|p| p.command == "ssh")
+        .filter_map(|p| {
+            let connection = match known_ssh.get(&p.pid) {
+                Some(known) => known.clone(),
+                None => read_ssh_connection(&details.get(&p.pid)?.args),
+            };
+            Some((p.pid, connection))
+        })
+        .collect();
+
+    let table = query_process_table();
+    let children = pids
+        .iter()
+        .map(|pid| (*pid, table.descendants(*pid)))
+        .filter(|(_, children)| !children.is_empty())
+        .collect();
+    ListenerDetails {
+        details,
+        children,
+        ssh_connections,
+    }
+}
+
+fn resolve_project_info(pid: i32, scanner: &dyn PortScanner) -> Option<ProjectInfo> {
+    let path = scanner.process_cwd(pid)?;
     // Validate path is in safe location (home dir or /tmp)
     if !is_safe_path(&path) {
         log::debug!("Skipping project resolution for unsafe path: {:?}", path);
@@ -915,20 +1824,6 @@ fn resolve_project_info(pid: i32) -> Option<ProjectInfo> {
     Some(ProjectInfo { name, path })
 }
 
-fn get_process_cwd(pid: i32) -> Option<std::path::PathBuf> {
-    let out = Command::new("lsof")
-        .args(["-a", "-p", &pid.to_string(), "-d", "cwd", "-Fn"])
-        .output()
-        .ok()?;
-    if !out.status.success() {
-        return None;
-    }
-    String::from_utf8_lossy(&out.stdout)
-        .lines()
-        .find_map(|line| line.strip_prefix('n'))
-        .map(std::path::PathBuf::from)
-}
-
 fn is_safe_path(path: &std::path::Path) -> bool {
     // Resolve to canonical path to prevent traversal attacks
     let canonical = match path.canonicalize() {
@@ -0,0 +1,876 @@
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use nix::sys::signal::Signal;
+use serde::Serialize;
+
+use crate::app::{
+    collect_children, describe_pid, kill_feedback, kill_succeeded, prepare_target,
+    refresh_details_for, refresh_projects_for, regular_processes,
+};
+use crate::config::{Config, load_or_create_config, parse_signal};
+use crate::events::{AppEvent, listener_changes};
+use crate::history::{
+    HistoryQuery, ListenerSession, churn, format_time, history_path, load_records, parse_time,
+    sessions,
+};
+use crate::hooks::{append_hook_results, terminate_with_hooks};
+use crate::integrations::brew::{get_brew_managed_service, query_brew_services_map, run_brew_stop};
+use crate::integrations::docker::{ContainerAction, run_container_action, run_docker_stop};
+use crate::integrations::runtime::{enabled_runtimes, query_container_port_map};
+use crate::model::{
+    AppState, FeedbackSeverity, KillFeedback, KillOutcome, KillStep, KillTarget, ProcessDetails,
+    ProcessInfo,
+};
+use crate::process::details::{describe_command, unix_now};
+use crate::process::monitor::{ListenerMonitor, MonitorPoll};
+use crate::process::scanner::{PortScanner, ScanOptions, default_scanner};
+use crate::restart::{RestartStore, capture_launch, relaunch, restart_path};
+use crate::ui::menu::collect_targets_for_all;
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_PERMISSION_DENIED: i32 = 4;
+pub const EXIT_TIMED_OUT: i32 = 5;
+pub const EXIT_REPLACED: i32 = 6;
+pub const EXIT_PROTECTED: i32 = 7;
+
+// How long `--signal` waits for the process to exit before giving up
+const SIGNAL_OVERRIDE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const USAGE: &str = "Usage:
+  portkiller                        Start the menu bar app
+  portkiller list [--json]          List listeners in the configured port ranges
+  portkiller kill <port> [options]  Stop whatever listens on <port>
+  portkiller kill --all [options]   Stop every unprotected listener except Docker and brew services
+  portkiller restart <port>         Relaunch the process last killed on <port>
+  portkiller container <action> <name|port> [options]
+                                    Stop, kill, restart or rm a container with published ports
+  portkiller watch [--json]         Print listeners as they start and stop
+  portkiller history [port] [options]
+                                    Show past listeners, or respawn counts with --summary
+
+Kill options:
+  --signal <SIG>  Send SIG once and wait instead of the configured ladder
+  --tree          Also stop the process's descendants
+  --force         Stop a listener even if it matches a protected rule; with
+                  --all, include protected listeners
+
+Container options:
+  --volumes       With rm, also remove the container's anonymous volumes
+  --prune         With rm, also prune networks no container uses
+  --force         Act on a container even if it matches a protected rule
+
+History options:
+  --since <T>, --until <T>  Limit to a window; T is an age (30m, 2h, 1d),
+                            [today|yesterday] HH:MM, or YYYY-MM-DD [HH:MM]
+  --summary                 Count starts per port instead of listing listeners
+  --json                    Print JSON
+
+Exit codes: 0 ok, 1 failed, 2 usage, 3 nothing listening / already exited,
+4 permission denied, 5 timed out, 6 PID reused by another process, 7 protected";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliCommand {
+    List {
+        json: bool,
+    },
+    Kill {
+        port: u16,
+        signal: Option<Signal>,
+        tree: bool,
+        force: bool,
+    },
+    KillAll {
+        signal: Option<Signal>,
+        tree: bool,
+        force: bool,
+    },
+    Restart {
+        port: u16,
+    },
+    Container {
+        target: String,
+        action: ContainerAction,
+        force: bool,
+    },
+    Watch {
+        json: bool,
+    },
+    History {
+        query: HistoryQuery,
+        summary: bool,
+        json: bool,
+    },
+    Help,
+}
+
+/// Run a CLI command if the arguments ask for one. Returns the process exit
+/// code, or `None` when the tray app should start instead.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = match parse_args(args) {
+        Ok(Some(command)) => command,
+        Ok(None) => return None,
+        Err(err) => {
+            eprintln!("portkiller: {}\n\n{}", err, USAGE);
+            return Some(EXIT_USAGE);
+        }
+    };
+    let result = match command {
+        CliCommand::Help => {
+            println!("{}", USAGE);
+            Ok(EXIT_OK)
+        }
+        CliCommand::List { json } => run_list(json),
+        CliCommand::Kill {
+            port,
+            signal,
+            tree,
+            force,
+        } => run_kill_port(port, signal, tree, force),
+        CliCommand::KillAll {
+            signal,
+            tree,
+            force,
+        } => run_kill_all(signal, tree, force),
+        CliCommand::Restart { port } => run_restart(port),
+        CliCommand::Container {
+            target,
+            action,
+            force,
+        } => run_container(&target, action, force),
+        CliCommand::Watch { json } => run_watch(json),
+        CliCommand::History {
+            query,
+            summary,
+            json,
+        } => run_history(&query, summary, json),
+    };
+    Some(result.unwrap_or_else(|err| {
+        eprintln!("portkiller: {:#}", err);
+        EXIT_FAILURE
+    }))
+}
+
+pub fn parse_args(args: &[String]) -> Result<Option<CliCommand>> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    // Finder passes a process serial number to apps launched from a bundle
+    if command.starts_with("-psn_") {
+        return Ok(None);
+    }
+
+    let mut json = false;
+    let mut all = false;
+    let mut tree = false;
+    let mut force = false;
+    let mut summary = false;
+    let mut volumes = false;
+    let mut prune = false;
+    let mut signal = None;
+    let mut since = None;
+    let mut until = None;
+    let mut positional = Vec::new();
+    // Options in the order given, checked against the subcommand below
+    let mut options = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        if arg.starts_with('-') {
+            options.push(arg.as_str());
+        }
+        match arg.as_str() {
+            "--json" => json = true,
+            "--all" => all = true,
+            "--tree" => tree = true,
+            "--force" | "-f" => force = true,
+            "--summary" => summary = true,
+            "--volumes" => volumes = true,
+            "--prune" => prune = true,
+            "--since" => {
+                let value = iter.next().context("--since needs a value")?;
+                since = Some(parse_time(value, unix_now())?);
+            }
+            "--until" => {
+                let value = iter.next().context("--until needs a value")?;
+                until = Some(parse_time(value, unix_now())?);
+            }
+            "--signal" | "-s" => {
+                let name = iter.next().context("--signal needs a value")?;
+                signal =
+                    Some(parse_signal(name).with_context(|| format!("unknown signal {}", name))?);
+            }
+            flag if flag.starts_with('-') => bail!("unknown option {}", flag),
+            value => positional.push(value),
+        }
+    }
+
+    let parsed = match command.as_str() {
+        "help" | "--help" | "-h" => CliCommand::Help,
+        "list" | "ls" => CliCommand::List { json },
+        "watch" => CliCommand::Watch { json },
+        "kill" => match (all, positional.as_slice()) {
+            (true, []) => CliCommand::KillAll {
+                signal,
+                tree,
+                force,
+            },
+            (false, [port]) => CliCommand::Kill {
+                port: port
+                    .parse()
+                    .with_context(|| format!("invalid port {}", port))?,
+                signal,
+                tree,
+                force,
+            },
+            (true, _) => bail!("kill --all takes no port"),
+            (false, _) => bail!("kill needs exactly one port or --all"),
+        },
+        "restart" => match positional.as_slice() {
+            [port] => CliCommand::Restart {
+                port: port
+                    .parse()
+                    .with_context(|| format!("invalid port {}", port))?,
+            },
+            _ => bail!("restart needs exactly one port"),
+        },
+        "container" => {
+            let [verb, target] = positional.as_slice() else {
+                bail!("container needs an action and a container name or port");
+            };
+            let action = match *verb {
+                "stop" => ContainerAction::Stop,
+                "kill" => ContainerAction::Kill,
+                "restart" => ContainerAction::Restart,
+                "rm" | "remove" => ContainerAction::Remove {
+                    volumes,
+                    prune_networks: prune,
+                },
+                other => bail!("unknown container action {}", other),
+            };
+            if (volumes || prune) && !matches!(action, ContainerAction::Remove { .. }) {
+                bail!("--volumes and --prune only apply to rm");
+            }
+            CliCommand::Container {
+                target: target.to_string(),
+                action,
+                force,
+            }
+        }
+        "history" => {
+            let port = match positional.as_slice() {
+                [] => None,
+                [port] => Some(
+                    port.parse()
+                        .with_context(|| format!("invalid port {}", port))?,
+                ),
+                _ => bail!("history takes at most one port"),
+            };
+            CliCommand::History {
+                query: HistoryQuery { port, since, until },
+                summary,
+                json,
+            }
+        }
+        other => bail!("unknown command {}", other),
+    };
+    if !positional.is_empty()
+        && !matches!(
+            parsed,
+            CliCommand::Kill { .. }
+                | CliCommand::Restart { .. }
+                | CliCommand::Container { .. }
+                | CliCommand::History { .. }
+        )
+    {
+        bail!("unexpected argument {}", positional[0]);
+    }
+    let allowed: &[&str] = match parsed {
+        CliCommand::List { .. } | CliCommand::Watch { .. } => &["--json"],
+        CliCommand::Kill { .. } | CliCommand::KillAll { .. } => {
+            &["--all", "--signal", "-s", "--tree", "--force", "-f"]
+        }
+        CliCommand::Container { .. } => &["--volumes", "--prune", "--force", "-f"],
+        CliCommand::History { .. } => &["--since", "--until", "--summary", "--json"],
+        CliCommand::Restart { .. } | CliCommand::Help => &[],
+    };
+    if let Some(option) = options.iter().find(|o| !allowed.contains(o)) {
+        bail!("{} does not apply to {}", option, command);
+    }
+    Ok(Some(parsed))
+}
+
+/// Map a kill outcome to the CLI exit code.
+pub fn exit_code(outcome: &KillOutcome) -> i32 {
+    match outcome {
+        KillOutcome::Success { .. } => EXIT_OK,
+        KillOutcome::AlreadyExited => EXIT_NOT_FOUND,
+        KillOutcome::PermissionDenied => EXIT_PERMISSION_DENIED,
+        KillOutcome::TimedOut => EXIT_TIMED_OUT,
+        KillOutcome::Replaced => EXIT_REPLACED,
+        KillOutcome::Failed(_) => EXIT_FAILURE,
+    }
+}
+
+#[derive(Serialize)]
+struct ListEntry<'a> {
+    #[serde(flatten)]
+    process: &'a ProcessInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<&'a ProcessDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    container: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    brew_service: Option<String>,
+}
+
+fn run_list(json: bool) -> Result<i32> {
+    let config = load_or_create_config().context("failed to load configuration")?;
+    let scanner = default_scanner();
+    let options = ScanOptions::from(&config.monitoring);
+    let state = snapshot_state(config, scanner.as_ref(), &options)?;
+
+    let entries: Vec<ListEntry> = state
+        .processes
+        .iter()
+        .map(|p| ListEntry {
+            process: p,
+            details: state.process_details.get(&p.pid),
+            project: state.project_cache.get(&p.pid).map(|i| i.name.as_str()),
+            container: state.container_for(p).map(|c| c.name.as_str()),
+            brew_service: get_brew_managed_service(&p.command, p.port, &state.brew_services_map),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(EXIT_OK);
+    }
+    if entries.is_empty() {
+        println!("No listeners in the configured port ranges.");
+        return Ok(EXIT_OK);
+    }
+    println!(
+        "{:<5} {:<24} {:>7}  {:<30} OWNER",
+        "PROTO", "ADDRESS", "PID", "COMMAND"
+    );
+    for entry in &entries {
+        let owner = if let Some(container) = entry.container {
+            format!("docker:{}", container)
+        } else if let Some(service) = &entry.brew_service {
+            format!("brew:{}", service)
+        } else {
+            entry.project.unwrap_or("").to_string()
+        };
+        println!(
+            "{:<5} {:<24} {:>7}  {:<30} {}",
+            entry.process.protocol.to_string(),
+            entry.process.endpoint(),
+            entry.process.pid,
+            describe_command(&entry.process.command, entry.details),
+            owner
+        );
+    }
+    Ok(EXIT_OK)
+}
+
+fn run_kill_port(port: u16, signal: Option<Signal>, tree: bool, force: bool) -> Result<i32> {
+    let config = load_or_create_config().context("failed to load configuration")?;
+    let scanner = default_scanner();
+    // The port may be outside the configured ranges; look at it regardless.
+    // The ranges are scanned too so a protection rule that matches one of the
+    // PID's other ports applies here, as in the menu and the control socket.
+    let mut options = ScanOptions::from(&config.monitoring);
+    options.port_ranges.push((port, port));
+    let state = snapshot_state(config, scanner.as_ref(), &options)?;
+    let on_port: Vec<&ProcessInfo> = state.processes.iter().filter(|p| p.port == port).collect();
+    if on_port.is_empty() {
+        eprintln!("Nothing is listening on port {}.", port);
+        return Ok(EXIT_NOT_FOUND);
+    }
+    if !force && let Some(process) = on_port.iter().find(|p| state.is_pid_protected(p.pid)) {
+        eprintln!(
+            "Port {} ({}) is protected; pass --force to stop it anyway.",
+            port, process.command
+        );
+        return Ok(EXIT_PROTECTED);
+    }
+
+    // Each listener goes to whatever owns it. Published container ports are
+    // owned by the runtime's proxy; stop the container instead
+    let owners = state.owners_on(port);
+    let owner = owners
+        .containers
+        .first()
+        .map(|c| format!("container {}", c.name))
+        .or_else(|| {
+            owners
+                .brew_services
+                .first()
+                .map(|s| format!("brew service {}", s))
+        });
+    if (signal.is_some() || tree)
+        && let Some(owner) = owner
+    {
+        return Ok(reject_process_flags(port, &owner));
+    }
+
+    let mut codes = Vec::new();
+    for container in &owners.containers {
+        codes.push(report_feedback(&run_docker_stop(
+            container.runtime,
+            &container.name,
+        )));
+    }
+    for service in &owners.brew_services {
+        codes.push(report_feedback(&run_brew_stop(service)));
+    }
+    let targets: Vec<KillTarget> = owners
+        .pids
+        .iter()
+        .filter_map(|pid| describe_pid(*pid, &state.processes))
+        .collect();
+    if !targets.is_empty() {
+        codes.push(kill_targets(
+            &state,
+            scanner.as_ref(),
+            targets,
+            signal,
+            tree,
+        ));
+    }
+    Ok(codes
+        .into_iter()
+        .find(|code| *code != EXIT_OK)
+        .unwrap_or(EXIT_OK))
+}
+
+/// `--signal` and `--tree` mean nothing to a container or brew service, which
+/// are stopped through their own tools.
+fn reject_process_flags(port: u16, owner: &str) -> i32 {
+    eprintln!(
+        "portkiller: port {} belongs to {}; --signal and --tree only apply to processes.",
+        port, owner
+    );
+    EXIT_USAGE
+}
+
+fn run_kill_all(signal: Option<Signal>, tree: bool, force: bool) -> Result<i32> {
+    let config = load_or_create_config().context("failed to load configuration")?;
+    let scanner = default_scanner();
+    let options = ScanOptions::from(&config.monitoring);
+    let state = snapshot_state(config, scanner.as_ref(), &options)?;
+    let targets = collect_targets_for_all(&regular_processes(&state), |p| {
+        !force && state.is_protected(p)
+    });
+    if targets.is_empty() {
+        println!("No dev port listeners to terminate.");
+        return Ok(EXIT_OK);
+    }
+    Ok(kill_targets(
+        &state,
+        scanner.as_ref(),
+        targets,
+        signal,
+        tree,
+    ))
+}
+
+fn run_container(target: &str, action: ContainerAction, force: bool) -> Result<i32> {
+    let config = load_or_create_config().context("failed to load configuration")?;
+    let containers = query_container_port_map(&enabled_runtimes(&config.integrations)).ports;
+    let by_port = target
+        .parse::<u16>()
+        .ok()
+        .and_then(|port| containers.iter().find(|(key, _)| key.port == port))
+        .map(|(_, c)| c);
+    let Some(container) = by_port.or_else(|| containers.values().find(|c| c.name == target)) else {
+        eprintln!("No running container {} with published ports.", target);
+        return Ok(EXIT_NOT_FOUND);
+    };
+
+    // Protection is decided by the listeners on the container's ports
+    let options = ScanOptions {
+        port_ranges: containers
+            .iter()
+            .filter(|(_, c)| c.name == container.name)
+            .map(|(key, _)| (key.port, key.port))
+            .collect(),
+        include_udp: true,
+    };
+    let state = snapshot_state(config, default_scanner().as_ref(), &options)?;
+    if !force && state.is_container_protected(&container.name) {
+        eprintln!(
+            "Container {} is protected; pass --force to act on it anyway.",
+            container.name
+        );
+        return Ok(EXIT_PROTECTED);
+    }
+    Ok(report_feedback(&run_container_action(
+        container.runtime,
+        &container.name,
+        action,
+    )))
+}
+
+fn run_restart(port: u16) -> Result<i32> {
+    let config = load_or_create_config().context("failed to load configuration")?;
+    let store = RestartStore::open(&restart_path(&config.restart))?;
+    let Some(spec) = store.find(port, None) else {
+        eprintln!(
+            "No recorded launch for port {}; kill it with portkiller first.",
+            port
+        );
+        return Ok(EXIT_NOT_FOUND);
+    };
+    println!("Starting {} in {}", spec.command_line(), spec.cwd.display());
+    let timeout = Duration::from_secs(config.restart.verify_timeout_secs);
+    match relaunch(spec, default_scanner().as_ref(), timeout) {
+        Ok(pid) => {
+            println!(
+                "Restarted on port {} (PID {}).",
+                spec.protocol.port_label(spec.port),
+                pid
+            );
+            Ok(EXIT_OK)
+        }
+        Err(err) => {
+            eprintln!("Restart failed: {:#}", err);
+            Ok(EXIT_FAILURE)
+        }
+    }
+}
+
+// Kill each target in turn; the exit code is that of the first failure.
+fn kill_targets(
+    state: &AppState,
+    scanner: &dyn PortScanner,
+    targets: Vec<KillTarget>,
+    signal: Option<Signal>,
+    tree: bool,
+) -> i32 {
+    let mut code = EXIT_OK;
+    for mut target in targets {
+        prepare_target(&mut target, state, tree);
+        collect_children(&mut target);
+        if let Some(signal) = signal {
+            let ladder = vec![KillStep {
+                signal,
+                timeout: SIGNAL_OVERRIDE_TIMEOUT,
+            }];
+            for child in &mut target.children {
+                child.ladder = ladder.clone();
+            }
+            target.ladder = ladder;
+        }
+        let launch = capture_launch(target.pid, &state.processes, &state.config.restart, scanner);
+        let (outcome, children, hooks) = terminate_with_hooks(&target, scanner);
+        if let Some(spec) = launch
+            && kill_succeeded(&outcome)
+            && let Err(err) = RestartStore::open(&restart_path(&state.config.restart))
+                .and_then(|mut store| store.remember(spec))
+        {
+            eprintln!("portkiller: could not save restart info: {:#}", err);
+        }
+        let mut feedback = kill_feedback(&target, outcome, children);
+        append_hook_results(&mut feedback, &hooks);
+        report_feedback(&feedback);
+        if code == EXIT_OK {
+            code = exit_code(&outcome);
+        }
+    }
+    code
+}
+
+fn report_feedback(feedback: &KillFeedback) -> i32 {
+    match feedback.severity {
+        FeedbackSeverity::Info => {
+            println!("{}", feedback.message);
+            EXIT_OK
+        }
+        FeedbackSeverity::Warning => {
+            eprintln!("{}", feedback.message);
+            EXIT_OK
+        }
+        FeedbackSeverity::Error => {
+            eprintln!("{}", feedback.message);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn run_watch(json: bool) -> Result<i32> {
+    let config = load_or_create_config().context("failed to load configuration")?;
+    let options = ScanOptions::from(&config.monitoring);
+    let interval = Duration::from_secs(config.monitoring.poll_interval_secs);
+    let mut monitor = ListenerMonitor::new(default_scanner());
+    let mut previous: Vec<ProcessInfo> = Vec::new();
+    loop {
+        if let MonitorPoll::Changed(current) = monitor.poll(&options)? {
+            for event in listener_changes(&previous, &current) {
+                if json {
+                    println!("{}", event.to_json_line());
+                    continue;
+                }
+                let (sign, process) = match &event {
+                    AppEvent::ListenerStarted { process } => ('+', process),
+                    AppEvent::ListenerStopped { process } => ('-', process),
+                    _ => continue,
+                };
+                println!(
+                    "{} {} {} {} (PID {})",
+                    sign,
+                    process.protocol,
+                    process.endpoint(),
+                    process.command,
+                    process.pid
+                );
+            }
+            previous = current;
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn run_history(query: &HistoryQuery, summary: bool, json: bool) -> Result<i32> {
+    let config = load_or_create_config().context("failed to load configuration")?;
+    let path = history_path(&config.history);
+    let records = if path.exists() {
+        load_records(&path)?
+    } else {
+        Vec::new()
+    };
+
+    if summary {
+        let ports = churn(&records, query);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&ports)?);
+        } else if ports.is_empty() {
+            println!("No listeners started in that window.");
+        } else {
+            println!(
+                "{:<10} {:>6} {:>5}  {:<16}  COMMANDS",
+                "PORT", "STARTS", "PIDS", "LAST START"
+            );
+            for port in &ports {
+                println!(
+                    "{:<10} {:>6} {:>5}  {:<16}  {}",
+                    port.protocol.port_label(port.port),
+                    port.starts,
+                    port.pids,
+                    format_time(port.last_start),
+                    port.commands.join(", ")
+                );
+            }
+        }
+        return Ok(EXIT_OK);
+    }
+
+    let matching: Vec<ListenerSession> = sessions(&records)
+        .into_iter()
+        .filter(|s| query.matches(s))
+        .collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&matching)?);
+        return Ok(EXIT_OK);
+    }
+    if matching.is_empty() {
+        println!("No listeners recorded in that window.");
+        return Ok(EXIT_OK);
+    }
+    println!(
+        "{:<16}  {:<16}  {:<10} {:>7}  {:<24} OWNER",
+        "STARTED", "STOPPED", "PORT", "PID", "COMMAND"
+    );
+    for session in &matching {
+        let started = session.started.map(format_time);
+        let stopped = session.stopped.map(format_time);
+        let owner = match (&session.container, &session.project) {
+            (Some(container), _) => format!("docker:{}", container),
+            (None, Some(project)) => project.clone(),
+            (None, None) => String::new(),
+        };
+        println!(
+            "{:<16}  {:<16}  {:<10} {:>7}  {:<24} {}",
+            started.as_deref().unwrap_or("?"),
+            stopped.as_deref().unwrap_or("running"),
+            session.protocol.port_label(session.port),
+            session.pid,
+            session.command,
+            owner
+        );
+    }
+    Ok(EXIT_OK)
+}
+
+// Scan once and fill in the same state the tray works from.
+fn snapshot_state(
+    config: Config,
+    scanner: &dyn PortScanner,
+    options: &ScanOptions,
+) -> Result<AppState> {
+    let mut state = AppState {
+        processes: scanner.scan(options)?,
+        ..Default::default()
+    };
+    state.docker_port_map = query_container_port_map(&enabled_runtimes(&config.integrations)).ports;
+    if config.integrations.brew_enabled {
+        state.brew_services_map = query_brew_services_map().unwrap_or_default();
+    }
+    state.config = config;
+    refresh_projects_for(&mut state, scanner);
+    refresh_details_for(&mut state);
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn no_arguments_launch_the_tray() {
+        assert_eq!(parse_args(&[]).unwrap(), None);
+        assert_eq!(parse_args(&args(&["-psn_0_12345"])).unwrap(), None);
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            parse_args(&args(&["list", "--json"])).unwrap(),
+            Some(CliCommand::List { json: true })
+        );
+        assert_eq!(
+            parse_args(&args(&["kill", "3000", "--signal", "INT"])).unwrap(),
+            Some(CliCommand::Kill {
+                port: 3000,
+                signal: Some(Signal::SIGINT),
+                tree: false,
+                force: false,
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["kill", "5432", "--force"])).unwrap(),
+            Some(CliCommand::Kill {
+                port: 5432,
+                signal: None,
+                tree: false,
+                force: true,
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["kill", "--all", "--tree"])).unwrap(),
+            Some(CliCommand::KillAll {
+                signal: None,
+                tree: true,
+                force: false,
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["kill", "--all", "--force"])).unwrap(),
+            Some(CliCommand::KillAll {
+                signal: None,
+                tree: false,
+                force: true,
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["restart", "3000"])).unwrap(),
+            Some(CliCommand::Restart { port: 3000 })
+        );
+        assert_eq!(
+            parse_args(&args(&["container", "rm", "shop-db-1", "--volumes"])).unwrap(),
+            Some(CliCommand::Container {
+                target: "shop-db-1".into(),
+                action: ContainerAction::Remove {
+                    volumes: true,
+                    prune_networks: false,
+                },
+                force: false,
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["watch"])).unwrap(),
+            Some(CliCommand::Watch { json: false })
+        );
+    }
+
+    #[test]
+    fn parses_history_queries() {
+        let since = parse_time("2024-05-01 12:00", 0).unwrap();
+        let until = parse_time("2024-05-01 18:00", 0).unwrap();
+        assert_eq!(
+            parse_args(&args(&[
+                "history",
+                "8080",
+                "--since",
+                "2024-05-01 12:00",
+                "--until",
+                "2024-05-01 18:00",
+            ]))
+            .unwrap(),
+            Some(CliCommand::History {
+                query: HistoryQuery {
+                    port: Some(8080),
+                    since: Some(since),
+                    until: Some(until),
+                },
+                summary: false,
+                json: false,
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["history", "--summary", "--json"])).unwrap(),
+            Some(CliCommand::History {
+                query: HistoryQuery::default(),
+                summary: true,
+                json: true,
+            })
+        );
+        assert!(parse_args(&args(&["history", "--since", "whenever"])).is_err());
+        assert!(parse_args(&args(&["history", "80", "81"])).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_usage() {
+        assert!(parse_args(&args(&["kill"])).is_err());
+        assert!(parse_args(&args(&["kill", "http"])).is_err());
+        assert!(parse_args(&args(&["kill", "--all", "3000"])).is_err());
+        assert!(parse_args(&args(&["kill", "3000", "--signal", "NOPE"])).is_err());
+        assert!(parse_args(&args(&["list", "extra"])).is_err());
+        assert!(parse_args(&args(&["frobnicate"])).is_err());
+        assert!(parse_args(&args(&["container", "stop"])).is_err());
+        assert!(parse_args(&args(&["container", "pause", "db"])).is_err());
+        assert!(parse_args(&args(&["container", "stop", "db", "--prune"])).is_err());
+        assert!(parse_args(&args(&["list", "--tree"])).is_err());
+        assert!(parse_args(&args(&["watch", "--signal", "KILL"])).is_err());
+        assert!(parse_args(&args(&["restart", "3000", "--force"])).is_err());
+        assert!(parse_args(&args(&["kill", "3000", "--json"])).is_err());
+        assert!(parse_args(&args(&["container", "stop", "db", "--tree"])).is_err());
+        assert!(parse_args(&args(&["history", "--volumes"])).is_err());
+    }
+
+    #[test]
+    fn maps_outcomes_to_exit_codes() {
+        let success = KillOutcome::Success {
+            step: 1,
+            signal: Signal::SIGKILL,
+        };
+        assert_eq!(exit_code(&success), EXIT_OK);
+        assert_eq!(exit_code(&KillOutcome::AlreadyExited), EXIT_NOT_FOUND);
+        assert_eq!(
+            exit_code(&KillOutcome::PermissionDenied),
+            EXIT_PERMISSION_DENIED
+        );
+        assert_eq!(exit_code(&KillOutcome::TimedOut), EXIT_TIMED_OUT);
+        assert_eq!(exit_code(&KillOutcome::Replaced), EXIT_REPLACED);
+    }
+}
@@ -1,10 +1,20 @@
 use std::fs::{self, Permissions};
 use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
+use nix::sys::signal::Signal;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::model::{KillStep, ProcessInfo};
+
+// Upper bound for a single ladder step so a typo cannot hang the worker
+const MAX_STEP_TIMEOUT_MS: u64 = 120_000;
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Config {
     #[serde(default)]
@@ -17,6 +27,21 @@ pub struct Config {
     pub system: SystemConfig,
     #[serde(default)]
     pub updates: UpdateConfig,
+    #[serde(default)]
+    pub kill: KillConfig,
+    #[serde(default)]
+    pub control: ControlConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    #[serde(default)]
+    pub restart: RestartConfig,
+    /// Listeners that Kill All skips and single kills only stop on override.
+    #[serde(default)]
+    pub protected: Vec<ProtectedRule>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -25,6 +50,8 @@ pub struct MonitoringConfig {
     pub poll_interval_secs: u64,
     pub port_ranges: Vec<(u16, u16)>,
     pub show_project_names: bool,
+    /// Also report bound UDP sockets (DNS stubs, mDNS, QUIC dev servers).
+    pub include_udp: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -32,6 +59,12 @@ pub struct MonitoringConfig {
 pub struct IntegrationsConfig {
     pub brew_enabled: bool,
     pub docker_enabled: bool,
+    pub podman_enabled: bool,
+    /// containerd through nerdctl (e.g. Rancher Desktop).
+    pub nerdctl_enabled: bool,
+    /// How long "Undo stop" stays in the menu after stopping a container or
+    /// brew service; 0 turns it off.
+    pub undo_window_secs: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -55,6 +88,340 @@ pub struct UpdateConfig {
     pub dismissed_version: Option<String>,
 }
 
+/// Local control socket for scripts and editor plugins. Read at startup only.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ControlConfig {
+    pub enabled: bool,
+    /// Defaults to `$XDG_RUNTIME_DIR/portkiller.sock` or a per-user temp file.
+    pub socket_path: Option<PathBuf>,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            socket_path: None,
+        }
+    }
+}
+
+/// Structured event stream. Read at startup only.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct EventsConfig {
+    /// Append every event to this file as NDJSON, e.g. for `tail -f`.
+    pub ndjson_path: Option<PathBuf>,
+}
+
+/// Prometheus endpoint on 127.0.0.1. Read at startup only.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 9464,
+        }
+    }
+}
+
+/// Log of listener starts and stops. Read at startup only.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Defaults to `~/.portkiller-history.ndjson`.
+    pub path: Option<PathBuf>,
+    /// Records older than this are dropped at startup; 0 keeps everything.
+    pub retention_days: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+            retention_days: 30,
+        }
+    }
+}
+
+/// Relaunching killed processes from their captured command line.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RestartConfig {
+    pub enabled: bool,
+    /// Environment variables carried over to the relaunched process; a
+    /// trailing `*` matches a prefix.
+    pub env: Vec<String>,
+    /// How long to wait for the relaunched process to bind its port.
+    pub verify_timeout_secs: u64,
+    /// Defaults to `~/.portkiller-restart.json`.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            env: [
+                "PATH",
+                "HOME",
+                "USER",
+                "SHELL",
+                "LANG",
+                "LC_*",
+                "TMPDIR",
+                "NODE_ENV",
+                "NODE_OPTIONS",
+                "PORT",
+                "HOST",
+                "RAILS_ENV",
+                "RACK_ENV",
+                "FLASK_*",
+                "DJANGO_SETTINGS_MODULE",
+                "VIRTUAL_ENV",
+                "PYTHONPATH",
+                "JAVA_HOME",
+                "GOPATH",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            verify_timeout_secs: 15,
+            path: None,
+        }
+    }
+}
+
+/// How processes are terminated: an ordered signal ladder, with overrides
+/// for specific commands or ports.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct KillConfig {
+    pub ladder: Vec<SignalStep>,
+    /// Checked in order; the first matching rule's settings replace these.
+    pub rules: Vec<KillRule>,
+    /// Also stop descendants of the listening process.
+    pub tree: bool,
+    /// Shell commands run before and after matching kills.
+    pub hooks: Vec<KillHook>,
+}
+
+/// Send `signal`, then wait up to `timeout_ms` for the process to exit.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SignalStep {
+    pub signal: String,
+    pub timeout_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct KillRule {
+    /// Process name as shown in the menu, e.g. "postgres".
+    pub command: Option<String>,
+    /// Port ranges in the same form as `monitoring.port_ranges`.
+    pub ports: Vec<(u16, u16)>,
+    /// Replaces `kill.ladder`; unset or empty keeps it, so a rule can
+    /// override only `tree`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ladder: Option<Vec<SignalStep>>,
+    /// Overrides `kill.tree` for matching processes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tree: Option<bool>,
+}
+
+impl KillRule {
+    fn matches(&self, command: &str, ports: &[u16]) -> bool {
+        filter_matches(self.command.as_deref(), &self.ports, command, ports)
+    }
+}
+
+fn filter_matches(
+    wanted: Option<&str>,
+    ranges: &[(u16, u16)],
+    command: &str,
+    ports: &[u16],
+) -> bool {
+    let command_ok = wanted.is_none_or(|c| c == command);
+    let ports_ok = ranges.is_empty()
+        || ports
+            .iter()
+            .any(|p| ranges.iter().any(|(s, e)| p >= s && p <= e));
+    command_ok && ports_ok
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookStage {
+    #[default]
+    Pre,
+    Post,
+}
+
+/// `run` is passed to `sh -c` with the killed process described in
+/// `PORTKILLER_*` environment variables. Filters work like `KillRule`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct KillHook {
+    pub when: HookStage,
+    pub run: String,
+    pub command: Option<String>,
+    pub ports: Vec<(u16, u16)>,
+    /// The hook is killed if it runs longer than this.
+    pub timeout_ms: u64,
+}
+
+impl Default for KillHook {
+    fn default() -> Self {
+        Self {
+            when: HookStage::Pre,
+            run: String::new(),
+            command: None,
+            ports: Vec::new(),
+            timeout_ms: 5_000,
+        }
+    }
+}
+
+/// Every field that is set must match.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ProtectedRule {
+    /// Regex matched against the process name and its full command line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<(u16, u16)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    #[serde(skip)]
+    regex: OnceLock<Option<Regex>>,
+}
+
+impl ProtectedRule {
+    fn command_regex(&self) -> Option<&Regex> {
+        self.regex
+            .get_or_init(|| self.command.as_deref().and_then(|c| Regex::new(c).ok()))
+            .as_ref()
+    }
+
+    /// `args` is the full command line, `project` and `container` what the
+    /// listener was attributed to, if anything.
+    pub fn matches(
+        &self,
+        process: &ProcessInfo,
+        args: Option<&str>,
+        project: Option<&str>,
+        container: Option<&str>,
+    ) -> bool {
+        let command_ok = self.command.is_none()
+            || self.command_regex().is_some_and(|re| {
+                re.is_match(&process.command) || args.is_some_and(|a| re.is_match(a))
+            });
+        let ports_ok = self.ports.is_empty()
+            || self
+                .ports
+                .iter()
+                .any(|(s, e)| process.port >= *s && process.port <= *e);
+        let project_ok = self.project.is_none() || self.project.as_deref() == project;
+        let container_ok = self.container.is_none() || self.container.as_deref() == container;
+        command_ok && ports_ok && project_ok && container_ok
+    }
+}
+
+impl KillConfig {
+    fn rule_for(&self, command: &str, ports: &[u16]) -> Option<&KillRule> {
+        self.rules.iter().find(|r| r.matches(command, ports))
+    }
+
+    /// Resolve the ladder for a process by name and the ports it listens on.
+    pub fn ladder_for(&self, command: &str, ports: &[u16]) -> Vec<KillStep> {
+        let steps = self
+            .rule_for(command, ports)
+            .and_then(|r| r.ladder.as_ref())
+            .filter(|ladder| !ladder.is_empty())
+            .unwrap_or(&self.ladder);
+        let resolved: Vec<KillStep> = steps.iter().filter_map(SignalStep::resolve).collect();
+        if resolved.is_empty() {
+            Self::default()
+                .ladder
+                .iter()
+                .filter_map(SignalStep::resolve)
+                .collect()
+        } else {
+            resolved
+        }
+    }
+
+    /// Hooks to run at `stage` for a process, in configuration order.
+    pub fn hooks_for(&self, stage: HookStage, command: &str, ports: &[u16]) -> Vec<KillHook> {
+        self.hooks
+            .iter()
+            .filter(|h| {
+                h.when == stage && filter_matches(h.command.as_deref(), &h.ports, command, ports)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Whether killing this process should take its descendants along.
+    pub fn tree_for(&self, command: &str, ports: &[u16]) -> bool {
+        self.rule_for(command, ports)
+            .and_then(|r| r.tree)
+            .unwrap_or(self.tree)
+    }
+}
+
+impl SignalStep {
+    fn new(signal: &str, timeout_ms: u64) -> Self {
+        Self {
+            signal: signal.to_string(),
+            timeout_ms,
+        }
+    }
+
+    fn resolve(&self) -> Option<KillStep> {
+        Some(KillStep {
+            signal: parse_signal(&self.signal)?,
+            timeout: Duration::from_millis(self.timeout_ms),
+        })
+    }
+}
+
+/// Accept "SIGINT", "INT" or "sigint".
+pub fn parse_signal(name: &str) -> Option<Signal> {
+    let upper = name.trim().to_ascii_uppercase();
+    let full = if upper.starts_with("SIG") {
+        upper
+    } else {
+        format!("SIG{}", upper)
+    };
+    Signal::from_str(&full).ok()
+}
+
+impl Default for KillConfig {
+    fn default() -> Self {
+        Self {
+            ladder: vec![
+                SignalStep::new("SIGTERM", 2_000),
+                SignalStep::new("SIGKILL", 1_000),
+            ],
+            rules: Vec::new(),
+            tree: false,
+            hooks: Vec::new(),
+        }
+    }
+}
+
 impl Default for UpdateConfig {
     fn default() -> Self {
         Self {
@@ -84,6 +451,7 @@ impl Default for MonitoringConfig {
                 (27017, 27017), // MongoDB
             ],
             show_project_names: true,
+            include_udp: false,
         }
     }
 }
@@ -93,6 +461,9 @@ impl Default for IntegrationsConfig {
         Self {
             brew_enabled: true,
             docker_enabled: true,
+            podman_enabled: false,
+            nerdctl_enabled: false,
+            undo_window_secs: 30,
         }
     }
 }
@@ -179,5 +550,254 @@ fn validate_config(config: &Config) -> Result<()> {
             anyhow::bail!("invalid port range: start ({}) > end ({})", start, end);
         }
     }
+    validate_ladder("kill.ladder", &config.kill.ladder)?;
+    for (idx, rule) in config.kill.rules.iter().enumerate() {
+        let name = format!("kill.rules[{}]", idx);
+        if rule.command.is_none() && rule.ports.is_empty() {
+            anyhow::bail!("{} must set a command or ports", name);
+        }
+        for (start, end) in &rule.ports {
+            if start > end {
+                anyhow::bail!("{}: invalid port range {}-{}", name, start, end);
+            }
+        }
+        if let Some(ladder) = rule.ladder.as_ref().filter(|l| !l.is_empty()) {
+            validate_ladder(&name, ladder)?;
+        }
+    }
+    if config.restart.verify_timeout_secs == 0 || config.restart.verify_timeout_secs > 300 {
+        anyhow::bail!(
+            "restart.verify_timeout_secs must be between 1 and 300, got {}",
+            config.restart.verify_timeout_secs
+        );
+    }
+    for (idx, hook) in config.kill.hooks.iter().enumerate() {
+        let name = format!("kill.hooks[{}]", idx);
+        if hook.run.trim().is_empty() {
+            anyhow::bail!("{} must set run", name);
+        }
+        if hook.timeout_ms == 0 || hook.timeout_ms > 300_000 {
+            anyhow::bail!("{}: timeout_ms must be between 1 and 300000", name);
+        }
+        for (start, end) in &hook.ports {
+            if start > end {
+                anyhow::bail!("{}: invalid port range {}-{}", name, start, end);
+            }
+        }
+    }
+    for (idx, rule) in config.protected.iter().enumerate() {
+        let name = format!("protected[{}]", idx);
+        if rule.command.is_none()
+            && rule.ports.is_empty()
+            && rule.project.is_none()
+            && rule.container.is_none()
+        {
+            anyhow::bail!("{} must set a command, ports, project or container", name);
+        }
+        if let Some(pattern) = &rule.command {
+            Regex::new(pattern).with_context(|| format!("{}: invalid command regex", name))?;
+        }
+        for (start, end) in &rule.ports {
+            if start > end {
+                anyhow::bail!("{}: invalid port range {}-{}", name, start, end);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_ladder(name: &str, ladder: &[SignalStep]) -> Result<()> {
+    if ladder.is_empty() {
+        anyhow::bail!("{} must contain at least one step", name);
+    }
+    for step in ladder {
+        if parse_signal(&step.signal).is_none() {
+            anyhow::bail!("{}: unknown signal {:?}", name, step.signal);
+        }
+        if step.timeout_ms == 0 || step.timeout_ms > MAX_STEP_TIMEOUT_MS {
+            anyhow::bail!(
+                "{}: timeout_ms must be between 1 and {}, got {}",
+                name,
+                MAX_STEP_TIMEOUT_MS,
+                step.timeout_ms
+            );
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(signal: &str, timeout_ms: u64) -> SignalStep {
+        SignalStep::new(signal, timeout_ms)
+    }
+
+    #[test]
+    fn parses_signal_names_loosely() {
+        assert_eq!(parse_signal("SIGINT"), Some(Signal::SIGINT));
+        assert_eq!(parse_signal("int"), Some(Signal::SIGINT));
+        assert_eq!(parse_signal("SIGNOPE"), None);
+    }
+
+    #[test]
+    fn first_matching_rule_overrides_default_ladder() {
+        let kill = KillConfig {
+            rules: vec![
+                KillRule {
+                    command: Some("postgres".into()),
+                    ladder: Some(vec![step("SIGINT", 15_000), step("SIGKILL", 1_000)]),
+                    ..Default::default()
+                },
+                KillRule {
+                    ports: vec![(3000, 3010)],
+                    ladder: Some(vec![step("INT", 3_000)]),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let pg = kill.ladder_for("postgres", &[5432]);
+        assert_eq!(pg[0].signal, Signal::SIGINT);
+        assert_eq!(pg[0].timeout, Duration::from_secs(15));
+        assert_eq!(pg.len(), 2);
+
+        let web = kill.ladder_for("node", &[8080, 3001]);
+        assert_eq!(web.len(), 1);
+        assert_eq!(web[0].signal, Signal::SIGINT);
+
+        let other = kill.ladder_for("python", &[8000]);
+        assert_eq!(other[0].signal, Signal::SIGTERM);
+        assert_eq!(other[1].signal, Signal::SIGKILL);
+    }
+
+    #[test]
+    fn tree_mode_follows_rules() {
+        let kill = KillConfig {
+            rules: vec![KillRule {
+                command: Some("gunicorn".into()),
+                ladder: Some(vec![step("SIGTERM", 5_000)]),
+                tree: Some(true),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(kill.tree_for("gunicorn", &[8000]));
+        assert!(!kill.tree_for("node", &[3000]));
+    }
+
+    #[test]
+    fn hooks_filter_by_stage_command_and_port() {
+        let kill: KillConfig = serde_json::from_str(
+            r#"{"hooks": [
+                {"when": "pre", "run": "pg_dump app > /tmp/app.sql", "command": "postgres"},
+                {"when": "post", "run": "rm -f tmp/pids/server.pid", "ports": [[3000, 3010]]}
+            ]}"#,
+        )
+        .unwrap();
+        let pre = kill.hooks_for(HookStage::Pre, "postgres", &[5432]);
+        assert_eq!(pre.len(), 1);
+        assert_eq!(pre[0].timeout_ms, 5_000);
+        assert!(
+            kill.hooks_for(HookStage::Post, "postgres", &[5432])
+                .is_empty()
+        );
+        assert_eq!(kill.hooks_for(HookStage::Post, "puma", &[3000]).len(), 1);
+
+        let mut config = Config::default();
+        config.kill.hooks = vec![KillHook::default()];
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_ladders() {
+        let mut config = Config::default();
+        assert!(validate_config(&config).is_ok());
+
+        config.kill.ladder = vec![step("SIGWHAT", 1_000)];
+        assert!(validate_config(&config).is_err());
+
+        config.kill.ladder = vec![step("SIGTERM", 0)];
+        assert!(validate_config(&config).is_err());
+
+        config.kill = KillConfig {
+            rules: vec![KillRule {
+                ladder: Some(vec![step("SIGTERM", 1_000)]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(validate_config(&config).is_err());
+
+        config.kill.rules[0].command = Some("node".into());
+        config.kill.rules[0].ladder = Some(vec![step("SIGNOPE", 1_000)]);
+        assert!(validate_config(&config).is_err());
+    }
+
+    #[test]
+    fn rules_without_a_ladder_inherit_the_configured_one() {
+        let config: Config = serde_json::from_str(
+            r#"{"kill": {
+                "ladder": [{"signal": "SIGINT", "timeout_ms": 4000}],
+                "rules": [
+                    {"command": "gunicorn", "tree": true},
+                    {"command": "puma", "ladder": []}
+                ]
+            }}"#,
+        )
+        .unwrap();
+        assert!(validate_config(&config).is_ok());
+        let kill = &config.kill;
+        assert!(kill.tree_for("gunicorn", &[8000]));
+        for command in ["gunicorn", "puma"] {
+            let ladder = kill.ladder_for(command, &[8000]);
+            assert_eq!(ladder.len(), 1);
+            assert_eq!(ladder[0].signal, Signal::SIGINT);
+            assert_eq!(ladder[0].timeout, Duration::from_secs(4));
+        }
+    }
+
+    #[test]
+    fn protected_rules_match_every_set_field() {
+        let rules: Vec<ProtectedRule> = serde_json::from_str(
+            r#"[{"command": "^postgres$"}, {"ports": [[22, 22]], "command": "ssh"}, {"container": "db"}]"#,
+        )
+        .unwrap();
+        let process = |port, command: &str| ProcessInfo {
+            port,
+            protocol: crate::model::Protocol::Tcp,
+            address: std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST),
+            pid: 1,
+            command: command.into(),
+        };
+        assert!(rules[0].matches(&process(5432, "postgres"), None, None, None));
+        assert!(!rules[0].matches(&process(5432, "postgres-exporter"), None, None, None));
+        assert!(rules[1].matches(&process(22, "ssh"), None, None, None));
+        assert!(!rules[1].matches(&process(2222, "ssh"), None, None, None));
+        // The full command line counts too
+        assert!(rules[1].matches(
+            &process(22, "python3"),
+            Some("python3 /usr/local/bin/sshuttle -r bastion"),
+            None,
+            None
+        ));
+        assert!(rules[2].matches(&process(5432, "docker-proxy"), None, None, Some("db")));
+        assert!(!rules[2].matches(&process(5432, "docker-proxy"), None, None, None));
+    }
+
+    #[test]
+    fn rejects_invalid_protected_rules() {
+        let mut config = Config {
+            protected: vec![ProtectedRule::default()],
+            ..Default::default()
+        };
+        assert!(validate_config(&config).is_err());
+        config.protected = vec![ProtectedRule {
+            command: Some("(".into()),
+            ..Default::default()
+        }];
+        assert!(validate_config(&config).is_err());
+    }
+}
@@ -0,0 +1,625 @@
+use std::fs::{self, Permissions};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::config::ControlConfig;
+use crate::events::EventBus;
+use crate::integrations::runtime::ContainerRuntime;
+use crate::model::{AppState, MenuAction};
+
+/// A command received on the control socket, one JSON object per line,
+/// e.g. `{"id": 1, "method": "kill_port", "port": 3000}`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum ControlRequest {
+    State,
+    /// `force` is required to stop a listener matching a `protected` entry.
+    KillPid {
+        pid: i32,
+        #[serde(default)]
+        tree: bool,
+        #[serde(default)]
+        force: bool,
+    },
+    KillPort {
+        port: u16,
+        #[serde(default)]
+        tree: bool,
+        #[serde(default)]
+        force: bool,
+    },
+    /// `runtime` picks between containers of the same name under Docker,
+    /// Podman or nerdctl.
+    StopContainer {
+        container: String,
+        #[serde(default)]
+        runtime: Option<ContainerRuntime>,
+        #[serde(default)]
+        force: bool,
+    },
+    ReloadConfig,
+    /// Switch the connection to a stream of NDJSON events.
+    Subscribe,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ControlResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ControlResponse {
+    pub fn ok(result: Value) -> Self {
+        Self {
+            id: None,
+            ok: true,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            ok: false,
+            result: None,
+            error: Some(message.into()),
+        }
+    }
+}
+
+/// Removes the socket file when the app shuts down.
+pub struct ControlServer {
+    path: PathBuf,
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Where the socket lives: the configured path, else `$XDG_RUNTIME_DIR`,
+/// else a per-user file in the temp dir (macOS `TMPDIR` is already per-user).
+pub fn socket_path(config: &ControlConfig) -> PathBuf {
+    if let Some(path) = &config.socket_path {
+        return path.clone();
+    }
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("portkiller.sock"),
+        _ => {
+            // SAFETY: getuid has no preconditions and cannot fail.
+            let uid = unsafe { libc::getuid() };
+            std::env::temp_dir().join(format!("portkiller-{}.sock", uid))
+        }
+    }
+}
+
+/// Listen on `path` and answer each request line with `dispatch`.
+/// `subscribe` requests are served straight from `events`.
+pub fn spawn_control_server<F>(
+    path: &Path,
+    events: Arc<EventBus>,
+    dispatch: F,
+) -> Result<ControlServer>
+where
+    F: Fn(ControlRequest) -> ControlResponse + Send + Sync + 'static,
+{
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            bail!("another instance is already serving {}", path.display());
+        }
+        // Left behind by a crashed instance
+        fs::remove_file(path).context("failed to remove stale control socket")?;
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("failed to bind control socket {}", path.display()))?;
+    fs::set_permissions(path, Permissions::from_mode(0o600))
+        .context("failed to set control socket permissions")?;
+    log::info!("Control socket listening on {}", path.display());
+
+    let dispatch = Arc::new(dispatch);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let dispatch = dispatch.clone();
+                    let events = events.clone();
+                    thread::spawn(move || serve_connection(stream, &events, dispatch.as_ref()));
+                }
+                Err(err) => log::warn!("Control socket accept failed: {}", err),
+            }
+        }
+    });
+    Ok(ControlServer {
+        path: path.to_path_buf(),
+    })
+}
+
+fn serve_connection(
+    stream: UnixStream,
+    events: &EventBus,
+    dispatch: &dyn Fn(ControlRequest) -> ControlResponse,
+) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, request) = parse_line(&line);
+        let subscribe = matches!(request, Ok(ControlRequest::Subscribe));
+        let mut response = match request {
+            Ok(ControlRequest::Subscribe) => ControlResponse::ok(json!({ "subscribed": true })),
+            Ok(request) => dispatch(request),
+            Err(response) => response,
+        };
+        response.id = id;
+        if !write_line(&mut writer, &response) {
+            break;
+        }
+        if subscribe {
+            // The connection now only carries events until the client hangs up
+            for event in events.subscribe() {
+                if writer.write_all(format!("{}\n", event).as_bytes()).is_err() {
+                    break;
+                }
+            }
+            break;
+        }
+    }
+}
+
+fn parse_line(line: &str) -> (Option<Value>, Result<ControlRequest, ControlResponse>) {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(err) => {
+            return (
+                None,
+                Err(ControlResponse::error(format!("invalid JSON: {}", err))),
+            );
+        }
+    };
+    let id = value.get("id").cloned();
+    let request = serde_json::from_value::<ControlRequest>(value)
+        .map_err(|err| ControlResponse::error(format!("invalid request: {}", err)));
+    (id, request)
+}
+
+fn write_line(writer: &mut UnixStream, response: &ControlResponse) -> bool {
+    match serde_json::to_string(response) {
+        Ok(encoded) => writer
+            .write_all(format!("{}\n", encoded).as_bytes())
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Answer a request from the app state. Commands are not executed here; the
+/// returned menu actions are fed through the same path as menu clicks.
+pub fn handle_request(
+    state: &AppState,
+    request: ControlRequest,
+) -> (ControlResponse, Vec<MenuAction>) {
+    match request {
+        ControlRequest::State => (ControlResponse::ok(snapshot(state)), Vec::new()),
+        ControlRequest::KillPid { pid, tree, force } => {
+            if !state.processes.iter().any(|p| p.pid == pid) {
+                return (
+                    ControlResponse::error(format!("PID {} is not a known listener", pid)),
+                    Vec::new(),
+                );
+            }
+            if !force && state.is_pid_protected(pid) {
+                return protected(format!("PID {}", pid));
+            }
+            queued(vec![kill_action(pid, tree, force)])
+        }
+        ControlRequest::KillPort { port, tree, force } => {
+            let listeners: Vec<_> = state.processes.iter().filter(|p| p.port == port).collect();
+            if listeners.is_empty() {
+                return (
+                    ControlResponse::error(format!("nothing is listening on port {}", port)),
+                    Vec::new(),
+                );
+            }
+            // Every PID on the port goes, so each of their other ports counts too
+            if !force && listeners.iter().any(|p| state.is_pid_protected(p.pid)) {
+                return protected(format!("port {}", port));
+            }
+            // Each listener goes to whatever owns it; published container
+            // ports belong to the runtime's proxy, so the container is stopped
+            let owners = state.owners_on(port);
+            let containers = owners.containers.iter().map(|c| MenuAction::DockerStop {
+                container: c.name.clone(),
+                runtime: c.runtime,
+                force,
+            });
+            let services = owners
+                .brew_services
+                .into_iter()
+                .map(|service| MenuAction::BrewStop { service, force });
+            let kills = owners.pids.iter().map(|pid| kill_action(*pid, tree, force));
+            queued(containers.chain(services).chain(kills).collect())
+        }
+        ControlRequest::StopContainer {
+            container,
+            runtime,
+            force,
+        } => {
+            // Only containers we map to a listener, not arbitrary names
+            let runtimes = state.container_runtimes(&container);
+            let runtime = match (runtime, &runtimes[..]) {
+                (Some(runtime), _) if runtimes.contains(&runtime) => runtime,
+                (None, [runtime]) => *runtime,
+                (None, [_, _, ..]) => {
+                    return (
+                        ControlResponse::error(format!(
+                            "container {} runs under several runtimes; pass runtime",
+                            container
+                        )),
+                        Vec::new(),
+                    );
+                }
+                _ => {
+                    return (
+                        ControlResponse::error(format!("unknown container {}", container)),
+                        Vec::new(),
+                    );
+                }
+            };
+            if !force && state.is_container_protected(&container) {
+                return protected(format!("container {}", container));
+            }
+            queued(vec![MenuAction::DockerStop {
+                container,
+                runtime,
+                force,
+            }])
+        }
+        ControlRequest::ReloadConfig => queued(vec![MenuAction::ReloadConfig]),
+        // Handled by the connection itself; never reaches the app
+        ControlRequest::Subscribe => (
+            ControlResponse::error("subscribe is only available on the socket"),
+            Vec::new(),
+        ),
+    }
+}
+
+fn kill_action(pid: i32, tree: bool, force: bool) -> MenuAction {
+    if tree {
+        MenuAction::KillTree { pid, force }
+    } else {
+        MenuAction::KillPid { pid, force }
+    }
+}
+
+fn protected(target: String) -> (ControlResponse, Vec<MenuAction>) {
+    (
+        ControlResponse::error(format!(
+            "{} is protected; send \"force\": true to stop it anyway",
+            target
+        )),
+        Vec::new(),
+    )
+}
+
+fn queued(actions: Vec<MenuAction>) -> (ControlResponse, Vec<MenuAction>) {
+    let described: Vec<String> = actions.iter().map(|a| format!("{:?}", a)).collect();
+    (ControlResponse::ok(json!({ "queued": described })), actions)
+}
+
+/// The state a client can see: listeners, their metadata and integrations.
+pub fn snapshot(state: &AppState) -> Value {
+    json!({
+        "processes": state.processes,
+        "projects": state.project_cache,
+        "details": state.process_details,
+        "docker": published_containers(state),
+        "brew": state.brew_services_map,
+        "last_feedback": state.last_feedback,
+    })
+}
+
+/// Container bindings as a list, since a binding is not a valid JSON key.
+fn published_containers(state: &AppState) -> Vec<Value> {
+    let mut bindings: Vec<_> = state.docker_port_map.iter().collect();
+    bindings.sort_by_key(|(key, _)| **key);
+    bindings
+        .into_iter()
+        .map(|(key, container)| {
+            json!({
+                "ip": key.ip,
+                "port": key.port,
+                "protocol": key.protocol,
+                "container": container,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+    use crate::model::{DockerContainerInfo, ProcessInfo, Protocol, PublishedPort};
+    use crate::test_util::temp_path;
+
+    fn state() -> AppState {
+        let listener = |port, pid, command: &str| ProcessInfo {
+            port,
+            protocol: Protocol::Tcp,
+            address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            pid,
+            command: command.into(),
+        };
+        let mut state = AppState {
+            processes: vec![listener(3000, 10, "node"), listener(5432, 20, "com.docker")],
+            ..Default::default()
+        };
+        state.docker_port_map.insert(
+            PublishedPort {
+                ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                port: 5432,
+                protocol: Protocol::Tcp,
+            },
+            DockerContainerInfo {
+                name: "db".into(),
+                id: "abc".into(),
+                project: None,
+                service: None,
+                runtime: Default::default(),
+            },
+        );
+        state
+    }
+
+    #[test]
+    fn parses_requests() {
+        let request: ControlRequest =
+            serde_json::from_str(r#"{"id": 7, "method": "kill_port", "port": 3000}"#).unwrap();
+        assert_eq!(
+            request,
+            ControlRequest::KillPort {
+                port: 3000,
+                tree: false,
+                force: false,
+            }
+        );
+        assert!(serde_json::from_str::<ControlRequest>(r#"{"method": "format_disk"}"#).is_err());
+    }
+
+    #[test]
+    fn routes_ports_to_the_owner() {
+        let state = state();
+        let (response, actions) = handle_request(
+            &state,
+            ControlRequest::KillPort {
+                port: 3000,
+                tree: true,
+                force: false,
+            },
+        );
+        assert!(response.ok);
+        assert!(matches!(
+            actions[..],
+            [MenuAction::KillTree {
+                pid: 10,
+                force: false
+            }]
+        ));
+
+        let (_, actions) = handle_request(
+            &state,
+            ControlRequest::KillPort {
+                port: 5432,
+                tree: false,
+                force: false,
+            },
+        );
+        assert!(
+            matches!(&actions[..], [MenuAction::DockerStop { container, .. }] if container == "db")
+        );
+
+        // Containers on another address or protocol don't own node's listener
+        let mut state = state;
+        let container = state.docker_port_map.values().next().unwrap().clone();
+        for (ip, protocol) in [
+            (IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), Protocol::Tcp),
+            (IpAddr::V4(Ipv4Addr::UNSPECIFIED), Protocol::Udp),
+        ] {
+            state.docker_port_map.insert(
+                PublishedPort {
+                    ip,
+                    port: 3000,
+                    protocol,
+                },
+                container.clone(),
+            );
+        }
+        let (_, actions) = handle_request(
+            &state,
+            ControlRequest::KillPort {
+                port: 3000,
+                tree: false,
+                force: false,
+            },
+        );
+        assert!(matches!(
+            actions[..],
+            [MenuAction::KillPid {
+                pid: 10,
+                force: false
+            }]
+        ));
+    }
+
+    #[test]
+    fn protected_targets_need_force() {
+        let mut state = state();
+        state.config.protected = serde_json::from_str(r#"[{"container": "db"}]"#).unwrap();
+        let (response, actions) = handle_request(
+            &state,
+            ControlRequest::StopContainer {
+                container: "db".into(),
+                runtime: None,
+                force: false,
+            },
+        );
+        assert!(!response.ok);
+        assert!(response.error.unwrap().contains("protected"));
+        assert!(actions.is_empty());
+
+        let (response, actions) = handle_request(
+            &state,
+            ControlRequest::KillPort {
+                port: 5432,
+                tree: false,
+                force: true,
+            },
+        );
+        assert!(response.ok);
+        assert!(matches!(
+            &actions[..],
+            [MenuAction::DockerStop { force: true, .. }]
+        ));
+    }
+
+    #[test]
+    fn refuses_unknown_targets() {
+        let state = state();
+        let (response, actions) = handle_request(
+            &state,
+            ControlRequest::KillPid {
+                pid: 1,
+                tree: false,
+                force: false,
+            },
+        );
+        assert!(!response.ok);
+        assert!(actions.is_empty());
+
+        let (response, _) = handle_request(
+            &state,
+            ControlRequest::StopContainer {
+                container: "web".into(),
+                runtime: None,
+                force: false,
+            },
+        );
+        assert!(!response.ok);
+    }
+
+    #[test]
+    fn picks_the_runtime_of_a_shared_container_name() {
+        let mut state = state();
+        let mut podman = state.docker_port_map.values().next().unwrap().clone();
+        podman.runtime = ContainerRuntime::Podman;
+        state.docker_port_map.insert(
+            PublishedPort {
+                ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                port: 5433,
+                protocol: Protocol::Tcp,
+            },
+            podman,
+        );
+        let stop = |runtime| ControlRequest::StopContainer {
+            container: "db".into(),
+            runtime,
+            force: false,
+        };
+        let (response, actions) = handle_request(&state, stop(None));
+        assert!(response.error.unwrap().contains("several runtimes"));
+        assert!(actions.is_empty());
+
+        let (response, actions) = handle_request(&state, stop(Some(ContainerRuntime::Podman)));
+        assert!(response.ok);
+        assert!(matches!(
+            &actions[..],
+            [MenuAction::DockerStop {
+                runtime: ContainerRuntime::Podman,
+                ..
+            }]
+        ));
+        let request: ControlRequest = serde_json::from_str(
+            r#"{"method": "stop_container", "container": "db", "runtime": "podman"}"#,
+        )
+        .unwrap();
+        assert_eq!(request, stop(Some(ContainerRuntime::Podman)));
+    }
+
+    #[test]
+    fn serves_requests_over_the_socket() {
+        let path = temp_path("test.sock");
+        let shared = state();
+        let events = Arc::new(EventBus::new());
+        let _server = spawn_control_server(&path, events.clone(), move |request| {
+            handle_request(&shared, request).0
+        })
+        .unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(b"{\"id\": 1, \"method\": \"state\"}\nnot json\n")
+            .unwrap();
+        let mut lines = BufReader::new(stream).lines();
+
+        let first: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(first["id"], 1);
+        assert_eq!(first["result"]["processes"][0]["port"], 3000);
+        let docker = &first["result"]["docker"][0];
+        assert_eq!(docker["ip"], "0.0.0.0");
+        assert_eq!(docker["port"], 5432);
+        assert_eq!(docker["protocol"], "tcp");
+        assert_eq!(docker["container"]["name"], "db");
+
+        let second: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(second["ok"], false);
+    }
+
+    #[test]
+    fn streams_events_to_subscribers() {
+        let path = temp_path("sub.sock");
+        let events = Arc::new(EventBus::new());
+        let _server =
+            spawn_control_server(&path, events.clone(), |_| ControlResponse::error("unused"))
+                .unwrap();
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream.write_all(b"{\"method\": \"subscribe\"}\n").unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        let ack: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(ack["ok"], true);
+
+        // The subscription is registered right after the ack is written
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while events.subscriber_count() == 0 && std::time::Instant::now() < deadline {
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        events.publish(crate::events::AppEvent::ConfigReloaded);
+        let event: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(event["event"], "config_reloaded");
+    }
+}
@@ -0,0 +1,178 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, Sender};
+use serde::Serialize;
+
+use crate::model::ProcessInfo;
+use crate::process::details::unix_now;
+
+/// Something that happened in the app that outside tools may care about.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AppEvent {
+    ListenerStarted {
+        #[serde(flatten)]
+        process: ProcessInfo,
+    },
+    ListenerStopped {
+        #[serde(flatten)]
+        process: ProcessInfo,
+    },
+    KillSucceeded {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pid: Option<i32>,
+        target: String,
+        message: String,
+    },
+    KillFailed {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pid: Option<i32>,
+        target: String,
+        message: String,
+    },
+    ConfigReloaded,
+}
+
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    ts: u64,
+    #[serde(flatten)]
+    event: &'a AppEvent,
+}
+
+impl AppEvent {
+    /// One NDJSON line (without the newline), stamped with the current time.
+    pub fn to_json_line(&self) -> String {
+        let record = EventRecord {
+            ts: unix_now(),
+            event: self,
+        };
+        serde_json::to_string(&record).unwrap_or_default()
+    }
+}
+
+/// Fans events out to live subscribers and an optional append-only file.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<String>>>,
+    sink: Mutex<Option<File>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also append every event to `path` as NDJSON.
+    pub fn with_file(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(path)
+            .with_context(|| format!("failed to open event log {}", path.display()))?;
+        Ok(Self {
+            subscribers: Mutex::new(Vec::new()),
+            sink: Mutex::new(Some(file)),
+        })
+    }
+
+    /// Receive every event published from now on, as NDJSON lines.
+    /// Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<String> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    pub fn publish(&self, event: AppEvent) {
+        let line = event.to_json_line();
+        log::debug!("event: {}", line);
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(line.clone()).is_ok());
+        if let Some(file) = self.sink.lock().unwrap().as_mut()
+            && let Err(err) = writeln!(file, "{}", line)
+        {
+            log::warn!("Failed to write event log: {}", err);
+        }
+    }
+}
+
+/// Listener start/stop events between two scans. A listener whose PID or
+/// address changed shows up as stopped and started again.
+pub fn listener_changes(prev: &[ProcessInfo], curr: &[ProcessInfo]) -> Vec<AppEvent> {
+    let stopped = prev
+        .iter()
+        .filter(|p| !curr.contains(p))
+        .map(|p| AppEvent::ListenerStopped { process: p.clone() });
+    let started = curr
+        .iter()
+        .filter(|p| !prev.contains(p))
+        .map(|p| AppEvent::ListenerStarted { process: p.clone() });
+    stopped.chain(started).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{listener, temp_path};
+
+    #[test]
+    fn diffs_listeners() {
+        let prev = vec![listener(3000, 1), listener(4000, 2)];
+        let curr = vec![listener(3000, 1), listener(4000, 3)];
+        let events = listener_changes(&prev, &curr);
+        assert_eq!(
+            events,
+            vec![
+                AppEvent::ListenerStopped {
+                    process: listener(4000, 2)
+                },
+                AppEvent::ListenerStarted {
+                    process: listener(4000, 3)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn serializes_flat_records() {
+        let line = AppEvent::ListenerStarted {
+            process: listener(3000, 1),
+        }
+        .to_json_line();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "listener_started");
+        assert_eq!(value["port"], 3000);
+        assert_eq!(value["protocol"], "tcp");
+        assert!(value["ts"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn fans_out_to_subscribers_and_file() {
+        let path = temp_path("events");
+        let bus = EventBus::with_file(&path).unwrap();
+        let rx = bus.subscribe();
+        let dropped = bus.subscribe();
+        drop(dropped);
+
+        bus.publish(AppEvent::ConfigReloaded);
+        assert!(rx.try_recv().unwrap().contains("\"config_reloaded\""));
+        assert_eq!(bus.subscriber_count(), 1);
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(written.lines().count(), 1);
+    }
+}
@@ -0,0 +1,733 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::IpAddr;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::HistoryConfig;
+use crate::model::{AppState, ProcessInfo, Protocol};
+
+// Records kept in memory for the History submenu
+pub const RECENT_CAPACITY: usize = 200;
+
+type ListenerKey = (u16, Protocol, IpAddr, i32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryKind {
+    Started,
+    Stopped,
+}
+
+/// One line of the history log.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub ts: u64,
+    pub kind: HistoryKind,
+    pub port: u16,
+    pub protocol: Protocol,
+    pub address: IpAddr,
+    pub pid: i32,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Seconds the listener was up; only set on stop records.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+}
+
+impl HistoryRecord {
+    fn key(&self) -> ListenerKey {
+        (self.port, self.protocol, self.address, self.pid)
+    }
+}
+
+fn key_of(process: &ProcessInfo) -> ListenerKey {
+    (process.port, process.protocol, process.address, process.pid)
+}
+
+/// Default log location, next to the config file.
+pub fn history_path(config: &HistoryConfig) -> PathBuf {
+    config.path.clone().unwrap_or_else(|| {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(home).join(".portkiller-history.ndjson")
+    })
+}
+
+/// Append-only NDJSON log of listener starts and stops.
+pub struct HistoryStore {
+    file: File,
+    path: PathBuf,
+    retention_days: u64,
+    last_pruned: u64,
+    // Start record of every listener believed to be running
+    open: HashMap<ListenerKey, HistoryRecord>,
+    // Last timestamp in the log when listeners were still open at startup;
+    // the ones gone by the first scan stopped while the app was not running
+    carried_until: Option<u64>,
+    recent: Vec<HistoryRecord>,
+}
+
+impl HistoryStore {
+    /// Open (or create) the log, drop records older than `retention_days`
+    /// and pick up listeners that were still running when the app last quit.
+    pub fn open(path: &Path, retention_days: u64, now: u64) -> Result<Self> {
+        let mut records = if path.exists() {
+            load_records(path)?
+        } else {
+            Vec::new()
+        };
+        prune(path, &mut records, retention_days, now)?;
+
+        let mut open = HashMap::new();
+        for record in &records {
+            match record.kind {
+                HistoryKind::Started => open.insert(record.key(), record.clone()),
+                HistoryKind::Stopped => open.remove(&record.key()),
+            };
+        }
+        let carried_until = if open.is_empty() {
+            None
+        } else {
+            records.iter().map(|r| r.ts).max()
+        };
+        let skip = records.len().saturating_sub(RECENT_CAPACITY);
+        Ok(Self {
+            file: open_append(path)?,
+            path: path.to_path_buf(),
+            retention_days,
+            last_pruned: now,
+            open,
+            carried_until,
+            recent: records.split_off(skip),
+        })
+    }
+
+    /// Most recent records, oldest first.
+    pub fn recent(&self) -> &[HistoryRecord] {
+        &self.recent
+    }
+
+    /// Log every listener that appeared or disappeared since the last call.
+    /// Stop times are when the scan noticed, so they can lag by one poll.
+    pub fn record(&mut self, state: &AppState, now: u64) -> Vec<HistoryRecord> {
+        let current: BTreeSet<ListenerKey> = state.processes.iter().map(key_of).collect();
+        let stopped_at = self.carried_until.take().unwrap_or(now);
+        let mut gone: Vec<ListenerKey> = self
+            .open
+            .keys()
+            .filter(|key| !current.contains(key))
+            .copied()
+            .collect();
+        gone.sort();
+
+        let mut changes = Vec::new();
+        for key in gone {
+            if let Some(start) = self.open.remove(&key) {
+                changes.push(HistoryRecord {
+                    ts: stopped_at,
+                    kind: HistoryKind::Stopped,
+                    duration_secs: Some(stopped_at.saturating_sub(start.ts)),
+                    ..start
+                });
+            }
+        }
+        for process in &state.processes {
+            let key = key_of(process);
+            if self.open.contains_key(&key) {
+                continue;
+            }
+            let record = HistoryRecord {
+                ts: now,
+                kind: HistoryKind::Started,
+                port: process.port,
+                protocol: process.protocol,
+                address: process.address,
+                pid: process.pid,
+                command: process.command.clone(),
+                project: state
+                    .project_cache
+                    .get(&process.pid)
+                    .map(|p| p.name.clone()),
+                container: state.container_for(process).map(|c| c.name.clone()),
+                duration_secs: None,
+            };
+            self.open.insert(key, record.clone());
+            changes.push(record);
+        }
+
+        for record in &changes {
+            match serde_json::to_string(record) {
+                Ok(line) => {
+                    if let Err(err) = writeln!(self.file, "{}", line) {
+                        log::warn!("Failed to write history log: {}", err);
+                    }
+                }
+                Err(err) => log::warn!("Failed to serialize history record: {}", err),
+            }
+        }
+        self.recent.extend(changes.iter().cloned());
+        let excess = self.recent.len().saturating_sub(RECENT_CAPACITY);
+        self.recent.drain(..excess);
+        if now.saturating_sub(self.last_pruned) >= 86_400 {
+            self.prune(now);
+        }
+        changes
+    }
+
+    /// Apply retention to the log of a long-running app, once a day.
+    fn prune(&mut self, now: u64) {
+        self.last_pruned = now;
+        if self.retention_days == 0 {
+            return;
+        }
+        let result = load_records(&self.path).and_then(|mut records| {
+            if prune(&self.path, &mut records, self.retention_days, now)? {
+                // The rewrite replaced the file our handle appends to
+                self.file = open_append(&self.path)?;
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            log::warn!("Failed to prune history log: {:#}", err);
+        }
+        let cutoff = now.saturating_sub(self.retention_days * 86_400);
+        self.recent.retain(|r| r.ts >= cutoff);
+    }
+}
+
+/// Drop records older than `retention_days` (0 keeps everything) and rewrite
+/// the log if any went. Returns whether it was rewritten.
+fn prune(
+    path: &Path,
+    records: &mut Vec<HistoryRecord>,
+    retention_days: u64,
+    now: u64,
+) -> Result<bool> {
+    if retention_days == 0 {
+        return Ok(false);
+    }
+    let cutoff = now.saturating_sub(retention_days * 86_400);
+    let before = records.len();
+    records.retain(|r| r.ts >= cutoff);
+    if records.len() == before {
+        return Ok(false);
+    }
+    rewrite(path, records)?;
+    Ok(true)
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(path)
+        .with_context(|| format!("failed to open history log {}", path.display()))
+}
+
+/// Read every record in the log, skipping lines that do not parse.
+pub fn load_records(path: &Path) -> Result<Vec<HistoryRecord>> {
+    let file = File::open(path)
+        .with_context(|| format!("failed to open history log {}", path.display()))?;
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.context("failed to read history log")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(err) => log::debug!("Skipping malformed history line: {}", err),
+        }
+    }
+    Ok(records)
+}
+
+fn rewrite(path: &Path, records: &[HistoryRecord]) -> Result<()> {
+    let tmp = path.with_extension("ndjson.tmp");
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp)
+        .with_context(|| format!("failed to write {}", tmp.display()))?;
+    for record in records {
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+    }
+    fs::rename(&tmp, path).context("failed to replace history log")
+}
+
+/// A listener's lifetime, rebuilt from its start and stop records.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ListenerSession {
+    pub port: u16,
+    pub protocol: Protocol,
+    pub address: IpAddr,
+    pub pid: i32,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// `None` when the start record fell out of the retention window.
+    pub started: Option<u64>,
+    /// `None` while the listener is still up.
+    pub stopped: Option<u64>,
+}
+
+pub fn sessions(records: &[HistoryRecord]) -> Vec<ListenerSession> {
+    let mut sessions: Vec<ListenerSession> = Vec::new();
+    let mut open: HashMap<ListenerKey, usize> = HashMap::new();
+    for record in records {
+        match record.kind {
+            HistoryKind::Started => {
+                open.insert(record.key(), sessions.len());
+                sessions.push(ListenerSession {
+                    port: record.port,
+                    protocol: record.protocol,
+                    address: record.address,
+                    pid: record.pid,
+                    command: record.command.clone(),
+                    project: record.project.clone(),
+                    container: record.container.clone(),
+                    started: Some(record.ts),
+                    stopped: None,
+                });
+            }
+            HistoryKind::Stopped => match open.remove(&record.key()) {
+                Some(idx) => sessions[idx].stopped = Some(record.ts),
+                None => sessions.push(ListenerSession {
+                    port: record.port,
+                    protocol: record.protocol,
+                    address: record.address,
+                    pid: record.pid,
+                    command: record.command.clone(),
+                    project: record.project.clone(),
+                    container: record.container.clone(),
+                    started: record
+                        .duration_secs
+                        .map(|secs| record.ts.saturating_sub(secs)),
+                    stopped: Some(record.ts),
+                }),
+            },
+        }
+    }
+    sessions
+}
+
+/// Which port and time window to look at; every field is optional.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HistoryQuery {
+    pub port: Option<u16>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+impl HistoryQuery {
+    /// True if the session was up at any point inside the window.
+    pub fn matches(&self, session: &ListenerSession) -> bool {
+        if self.port.is_some_and(|port| port != session.port) {
+            return false;
+        }
+        if let (Some(until), Some(started)) = (self.until, session.started)
+            && started > until
+        {
+            return false;
+        }
+        if let (Some(since), Some(stopped)) = (self.since, session.stopped)
+            && stopped < since
+        {
+            return false;
+        }
+        true
+    }
+
+    fn contains(&self, ts: u64) -> bool {
+        self.since.is_none_or(|since| ts >= since) && self.until.is_none_or(|until| ts <= until)
+    }
+}
+
+/// How often a port came back up within a window.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PortChurn {
+    pub port: u16,
+    pub protocol: Protocol,
+    pub starts: usize,
+    pub pids: usize,
+    pub commands: Vec<String>,
+    pub last_start: u64,
+}
+
+#[derive(Default)]
+struct PortStarts {
+    starts: usize,
+    pids: BTreeSet<i32>,
+    commands: BTreeSet<String>,
+    last_start: u64,
+}
+
+/// Ports ordered by how many times a listener started on them, busiest first.
+pub fn churn(records: &[HistoryRecord], query: &HistoryQuery) -> Vec<PortChurn> {
+    let mut by_port: BTreeMap<(u16, Protocol), PortStarts> = BTreeMap::new();
+    for record in records {
+        if record.kind != HistoryKind::Started
+            || query.port.is_some_and(|port| port != record.port)
+            || !query.contains(record.ts)
+        {
+            continue;
+        }
+        let entry = by_port.entry((record.port, record.protocol)).or_default();
+        entry.starts += 1;
+        entry.pids.insert(record.pid);
+        entry.commands.insert(record.command.clone());
+        entry.last_start = entry.last_start.max(record.ts);
+    }
+    let mut churn: Vec<PortChurn> = by_port
+        .into_iter()
+        .map(|((port, protocol), starts)| PortChurn {
+            port,
+            protocol,
+            starts: starts.starts,
+            pids: starts.pids.len(),
+            commands: starts.commands.into_iter().collect(),
+            last_start: starts.last_start,
+        })
+        .collect();
+    churn.sort_by(|a, b| b.starts.cmp(&a.starts).then(a.port.cmp(&b.port)));
+    churn
+}
+
+fn local_tm(ts: u64) -> libc::tm {
+    let time = ts as libc::time_t;
+    // SAFETY: localtime_r only writes into the provided tm
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&time, &mut tm);
+        tm
+    }
+}
+
+fn from_local(year: i32, month: u32, day: u32, hour: u32, min: u32) -> Result<u64> {
+    // SAFETY: mktime reads and normalizes the tm we own
+    let ts = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        tm.tm_year = year - 1900;
+        tm.tm_mon = month as i32 - 1;
+        tm.tm_mday = day as i32;
+        tm.tm_hour = hour as i32;
+        tm.tm_min = min as i32;
+        tm.tm_isdst = -1;
+        libc::mktime(&mut tm)
+    };
+    if ts < 0 {
+        bail!("time out of range");
+    }
+    Ok(ts as u64)
+}
+
+/// Local time as "2024-05-01 14:05".
+pub fn format_time(ts: u64) -> String {
+    let tm = local_tm(ts);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min
+    )
+}
+
+/// Local wall-clock time as "14:05".
+pub fn format_clock(ts: u64) -> String {
+    let tm = local_tm(ts);
+    format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)
+}
+
+/// Parse a point in time: a relative age ("90s", "30m", "2h", "1d"),
+/// "today"/"yesterday" with an optional "HH:MM", a bare "HH:MM" for today,
+/// or "YYYY-MM-DD" with an optional "HH:MM".
+pub fn parse_time(value: &str, now: u64) -> Result<u64> {
+    let value = value.trim();
+    if let Some(age) = parse_age(value) {
+        return Ok(now.saturating_sub(age));
+    }
+    let mut parts = value.split_whitespace();
+    let day = parts.next().context("empty time")?;
+    let clock = parts.next();
+    if parts.next().is_some() {
+        bail!("invalid time {}", value);
+    }
+
+    let (date, clock) = match (day, clock) {
+        ("today", clock) => (local_date(now), clock),
+        ("yesterday", clock) => (local_date(now.saturating_sub(86_400)), clock),
+        (clock, None) if clock.contains(':') => (local_date(now), Some(clock)),
+        (date, clock) => (parse_date(date)?, clock),
+    };
+    let (hour, min) = match clock {
+        Some(clock) => parse_clock(clock)?,
+        None => (0, 0),
+    };
+    from_local(date.0, date.1, date.2, hour, min)
+}
+
+fn parse_age(value: &str) -> Option<u64> {
+    let unit = value.chars().last()?;
+    let scale = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        _ => return None,
+    };
+    let amount: u64 = value[..value.len() - 1].parse().ok()?;
+    amount.checked_mul(scale)
+}
+
+fn local_date(ts: u64) -> (i32, u32, u32) {
+    let tm = local_tm(ts);
+    (tm.tm_year + 1900, tm.tm_mon as u32 + 1, tm.tm_mday as u32)
+}
+
+fn parse_date(value: &str) -> Result<(i32, u32, u32)> {
+    let invalid = || format!("invalid date {} (expected YYYY-MM-DD)", value);
+    let mut parts = value.split('-');
+    let (Some(year), Some(month), Some(day), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        bail!(invalid());
+    };
+    let year: i32 = year.parse().with_context(invalid)?;
+    let month: u32 = month.parse().with_context(invalid)?;
+    let day: u32 = day.parse().with_context(invalid)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        bail!(invalid());
+    }
+    Ok((year, month, day))
+}
+
+fn parse_clock(value: &str) -> Result<(u32, u32)> {
+    let invalid = || format!("invalid time of day {} (expected HH:MM)", value);
+    let (hour, min) = value.split_once(':').with_context(invalid)?;
+    let hour: u32 = hour.parse().with_context(invalid)?;
+    let min: u32 = min.parse().with_context(invalid)?;
+    if hour > 23 || min > 59 {
+        bail!(invalid());
+    }
+    Ok((hour, min))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+    use crate::model::{DockerContainerInfo, ProjectInfo, Protocol, PublishedPort};
+    use crate::test_util::{listener, temp_path};
+
+    #[test]
+    fn records_starts_and_stops_with_context() {
+        let path = temp_path("history-record");
+        let mut store = HistoryStore::open(&path, 0, 1_000).unwrap();
+        let mut state = AppState {
+            processes: vec![listener(3000, 10), listener(5432, 20)],
+            ..Default::default()
+        };
+        state.project_cache.insert(
+            10,
+            ProjectInfo {
+                name: "web".into(),
+                path: PathBuf::from("/src/web"),
+            },
+        );
+        state.docker_port_map.insert(
+            PublishedPort {
+                ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                port: 5432,
+                protocol: Protocol::Tcp,
+            },
+            DockerContainerInfo {
+                name: "db".into(),
+                id: "abc".into(),
+                project: None,
+                service: None,
+                runtime: Default::default(),
+            },
+        );
+        assert_eq!(store.record(&state, 1_000).len(), 2);
+        assert!(store.record(&state, 1_010).is_empty());
+
+        state.processes = vec![listener(5432, 20)];
+        let changes = store.record(&state, 1_100);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, HistoryKind::Stopped);
+        assert_eq!(changes[0].project.as_deref(), Some("web"));
+        assert_eq!(changes[0].duration_secs, Some(100));
+
+        let records = load_records(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].container.as_deref(), Some("db"));
+
+        // A restart finds 5432 still open and does not log it again
+        let mut reopened = HistoryStore::open(&path, 0, 2_000).unwrap();
+        assert_eq!(reopened.recent().len(), 3);
+        assert!(reopened.record(&state, 2_000).is_empty());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn prunes_records_past_retention() {
+        let path = temp_path("history-retention");
+        let mut store = HistoryStore::open(&path, 0, 0).unwrap();
+        let state = AppState {
+            processes: vec![listener(3000, 10)],
+            ..Default::default()
+        };
+        store.record(&state, 100);
+        store.record(&AppState::default(), 200);
+        store.record(&state, 3 * 86_400);
+        drop(store);
+
+        let mut store = HistoryStore::open(&path, 1, 3 * 86_400 + 60).unwrap();
+        assert_eq!(store.recent().len(), 1);
+        assert_eq!(load_records(&path).unwrap().len(), 1);
+
+        // A tray left running prunes once a day, not only at startup
+        store.record(&AppState::default(), 4 * 86_400);
+        store.record(&state, 5 * 86_400 + 60);
+        assert_eq!(load_records(&path).unwrap().len(), 1);
+        assert_eq!(store.recent().len(), 1);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn closes_listeners_left_open_at_the_last_recorded_time() {
+        let path = temp_path("history-carried");
+        let mut store = HistoryStore::open(&path, 0, 0).unwrap();
+        store.record(
+            &AppState {
+                processes: vec![listener(3000, 10)],
+                ..Default::default()
+            },
+            100,
+        );
+        store.record(
+            &AppState {
+                processes: vec![listener(3000, 10), listener(8080, 11)],
+                ..Default::default()
+            },
+            500,
+        );
+        drop(store);
+
+        // Neither is up when the app starts again a day later
+        let mut store = HistoryStore::open(&path, 0, 86_400).unwrap();
+        let changes = store.record(&AppState::default(), 86_400);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|r| r.ts == 500));
+        assert_eq!(changes[0].duration_secs, Some(400));
+        assert_eq!(changes[1].duration_secs, Some(0));
+        let _ = fs::remove_file(&path);
+    }
+
+    fn record(ts: u64, kind: HistoryKind, port: u16, pid: i32) -> HistoryRecord {
+        HistoryRecord {
+            ts,
+            kind,
+            port,
+            protocol: Protocol::Tcp,
+            address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            pid,
+            command: "node".into(),
+            project: None,
+            container: None,
+            duration_secs: None,
+        }
+    }
+
+    #[test]
+    fn answers_what_was_on_a_port_in_a_window() {
+        use HistoryKind::*;
+        let records = vec![
+            record(100, Started, 8080, 1),
+            record(200, Stopped, 8080, 1),
+            record(300, Started, 8080, 2),
+            record(400, Stopped, 8080, 2),
+            record(500, Started, 8080, 3),
+            record(150, Started, 3000, 4),
+        ];
+        let all = sessions(&records);
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0].stopped, Some(200));
+        assert_eq!(all[2].stopped, None);
+
+        let query = HistoryQuery {
+            port: Some(8080),
+            since: Some(250),
+            until: Some(450),
+        };
+        let pids: Vec<i32> = all
+            .iter()
+            .filter(|s| query.matches(s))
+            .map(|s| s.pid)
+            .collect();
+        assert_eq!(pids, vec![2]);
+    }
+
+    #[test]
+    fn ranks_ports_by_respawns() {
+        use HistoryKind::*;
+        let records = vec![
+            record(100, Started, 3000, 1),
+            record(110, Stopped, 3000, 1),
+            record(120, Started, 3000, 2),
+            record(130, Stopped, 3000, 2),
+            record(140, Started, 3000, 3),
+            record(150, Started, 8080, 9),
+        ];
+        let ranked = churn(&records, &HistoryQuery::default());
+        assert_eq!(ranked[0].port, 3000);
+        assert_eq!(ranked[0].starts, 3);
+        assert_eq!(ranked[0].pids, 3);
+        assert_eq!(ranked[0].last_start, 140);
+
+        let recent = HistoryQuery {
+            since: Some(125),
+            ..Default::default()
+        };
+        assert_eq!(churn(&records, &recent)[0].starts, 1);
+    }
+
+    #[test]
+    fn parses_times() {
+        let now = 1_700_000_000;
+        assert_eq!(parse_time("2h", now).unwrap(), now - 7200);
+        assert_eq!(parse_time("1d", now).unwrap(), now - 86_400);
+
+        let ts = parse_time("2024-05-01 14:05", now).unwrap();
+        assert_eq!(format_time(ts), "2024-05-01 14:05");
+        assert_eq!(format_clock(ts), "14:05");
+        assert_eq!(
+            format_time(parse_time("2024-05-01", now).unwrap()),
+            "2024-05-01 00:00"
+        );
+
+        let yesterday = parse_time("yesterday 12:00", now).unwrap();
+        assert_eq!(format_clock(yesterday), "12:00");
+        assert!(yesterday < now && now - yesterday < 2 * 86_400);
+
+        assert!(parse_time("25:00", now).is_err());
+        assert!(parse_time("2024-13-01", now).is_err());
+        assert!(parse_time("soon", now).is_err());
+        assert!(parse_time("999999999999999999d", now).is_err());
+    }
+}
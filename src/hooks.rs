@@ -0,0 +1,330 @@
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::{Signal, killpg};
+use nix::unistd::Pid;
+
+use crate::config::{HookStage, KillHook};
+use crate::model::{FeedbackSeverity, KillFeedback, KillOutcome, KillTarget, ProjectInfo};
+use crate::process::identity::{IdentityCheck, check_identity};
+use crate::process::kill::{ChildSummary, terminate_tree};
+use crate::process::scanner::PortScanner;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(25);
+// Feedback is a notification; keep a hook's output to its gist
+const MAX_OUTPUT_CHARS: usize = 200;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HookStatus {
+    Succeeded,
+    Exited(Option<i32>),
+    TimedOut,
+    SpawnFailed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct HookResult {
+    pub stage: HookStage,
+    pub run: String,
+    pub status: HookStatus,
+    /// Combined stdout and stderr, trimmed.
+    pub output: String,
+}
+
+impl HookResult {
+    pub fn failed(&self) -> bool {
+        self.status != HookStatus::Succeeded
+    }
+
+    /// One sentence for the kill feedback; `None` for a quiet success.
+    pub fn describe(&self) -> Option<String> {
+        let stage = match self.stage {
+            HookStage::Pre => "Pre-kill",
+            HookStage::Post => "Post-kill",
+        };
+        let mut text = match &self.status {
+            HookStatus::Succeeded if self.output.is_empty() => return None,
+            HookStatus::Succeeded => format!("{} hook `{}`", stage, self.run),
+            HookStatus::Exited(Some(code)) => {
+                format!("{} hook `{}` exited with {}", stage, self.run, code)
+            }
+            HookStatus::Exited(None) => {
+                format!("{} hook `{}` was killed by a signal", stage, self.run)
+            }
+            HookStatus::TimedOut => format!("{} hook `{}` timed out", stage, self.run),
+            HookStatus::SpawnFailed(err) => {
+                format!("{} hook `{}` could not start: {}", stage, self.run, err)
+            }
+        };
+        if !self.output.is_empty() {
+            text.push_str(&format!(": {}", truncate(&self.output)));
+        }
+        text.push('.');
+        Some(text)
+    }
+}
+
+/// Run one hook through `sh -c`, killing it once `timeout_ms` passes.
+pub fn run_hook(hook: &KillHook, env: &[(String, String)]) -> HookResult {
+    let result = |status, output| HookResult {
+        stage: hook.when,
+        run: hook.run.clone(),
+        status,
+        output,
+    };
+    let mut child = match Command::new("sh")
+        .arg("-c")
+        .arg(&hook.run)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Own process group, so a timeout takes down whatever the hook started
+        .process_group(0)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => return result(HookStatus::SpawnFailed(err.to_string()), String::new()),
+    };
+
+    // Drain both pipes so a chatty hook cannot block on a full buffer. The
+    // readers stream chunks so output is not lost when something the hook
+    // left running keeps a pipe open
+    let (tx, rx) = mpsc::channel::<(usize, Option<Vec<u8>>)>();
+    let pipes: Vec<Box<dyn Read + Send>> = [
+        child
+            .stdout
+            .take()
+            .map(|r| Box::new(r) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|r| Box::new(r) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let mut open = pipes.len();
+    for (index, mut pipe) in pipes.into_iter().enumerate() {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match pipe.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if tx.send((index, Some(buf[..n].to_vec()))).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            let _ = tx.send((index, None));
+        });
+    }
+    drop(tx);
+
+    let deadline = Instant::now() + Duration::from_millis(hook.timeout_ms);
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(exit)) if exit.success() => break HookStatus::Succeeded,
+            Ok(Some(exit)) => break HookStatus::Exited(exit.code()),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+                let _ = child.wait();
+                break HookStatus::TimedOut;
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(err) => break HookStatus::SpawnFailed(err.to_string()),
+        }
+    };
+
+    // A background job (`notify.sh &`) or a straggler outside the group can
+    // hold the pipes long after the hook exits; stop collecting at the
+    // deadline so the worker is not stalled behind it
+    let mut collected = vec![Vec::new(); open];
+    while open > 0 {
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok((index, Some(chunk))) => collected[index].extend(chunk),
+            Ok((_, None)) => open -= 1,
+            Err(_) => break,
+        }
+    }
+    let output = collected
+        .iter()
+        .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    result(status, output)
+}
+
+/// Environment describing the process a hook runs for. Unknown values are
+/// empty rather than unset so scripts can use `set -u`.
+pub fn hook_env(
+    pid: i32,
+    command: &str,
+    ports: &[u16],
+    project: Option<&ProjectInfo>,
+    container: Option<&str>,
+) -> Vec<(String, String)> {
+    let ports_list: Vec<String> = ports.iter().map(u16::to_string).collect();
+    [
+        ("PORTKILLER_PID", pid.to_string()),
+        (
+            "PORTKILLER_PORT",
+            ports_list.first().cloned().unwrap_or_default(),
+        ),
+        ("PORTKILLER_PORTS", ports_list.join(",")),
+        ("PORTKILLER_COMMAND", command.to_string()),
+        (
+            "PORTKILLER_PROJECT",
+            project.map(|p| p.name.clone()).unwrap_or_default(),
+        ),
+        (
+            "PORTKILLER_PROJECT_PATH",
+            project
+                .map(|p| p.path.display().to_string())
+                .unwrap_or_default(),
+        ),
+        ("PORTKILLER_CONTAINER", container.unwrap_or("").to_string()),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect()
+}
+
+pub fn run_hooks(hooks: &[KillHook], env: &[(String, String)]) -> Vec<HookResult> {
+    hooks.iter().map(|hook| run_hook(hook, env)).collect()
+}
+
+/// `terminate_tree` wrapped in the target's pre and post hooks. Post hooks
+/// see the outcome in `PORTKILLER_OUTCOME`. Neither runs when the PID no
+/// longer belongs to the process the hooks were prepared for.
+pub fn terminate_with_hooks(
+    target: &KillTarget,
+    scanner: &dyn PortScanner,
+) -> (KillOutcome, ChildSummary, Vec<HookResult>) {
+    if check_identity(target.pid, &target.identity) != IdentityCheck::Same {
+        let (outcome, children) = terminate_tree(target, scanner);
+        return (outcome, children, Vec::new());
+    }
+    let mut results = run_hooks(&target.hooks.pre, &target.hooks.env);
+    let (outcome, children) = terminate_tree(target, scanner);
+    if !target.hooks.post.is_empty() {
+        let mut env = target.hooks.env.clone();
+        env.push(("PORTKILLER_OUTCOME".into(), outcome.label().into()));
+        results.extend(run_hooks(&target.hooks.post, &env));
+    }
+    (outcome, children, results)
+}
+
+/// Add hook output to the feedback; a failed hook makes it a warning.
+pub fn append_hook_results(feedback: &mut KillFeedback, results: &[HookResult]) {
+    for line in results.iter().filter_map(HookResult::describe) {
+        feedback.message.push(' ');
+        feedback.message.push_str(&line);
+    }
+    if results.iter().any(HookResult::failed) && matches!(feedback.severity, FeedbackSeverity::Info)
+    {
+        feedback.severity = FeedbackSeverity::Warning;
+    }
+}
+
+fn truncate(output: &str) -> String {
+    let flat = output.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= MAX_OUTPUT_CHARS {
+        return flat;
+    }
+    let mut cut: String = flat.chars().take(MAX_OUTPUT_CHARS).collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{KillHooks, ProcessIdentity};
+    use crate::process::scanner::FixtureScanner;
+    use crate::test_util::temp_path;
+
+    fn hook(run: &str, timeout_ms: u64) -> KillHook {
+        KillHook {
+            run: run.into(),
+            timeout_ms,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn passes_environment_and_captures_output() {
+        let env = vec![("PORTKILLER_PORT".to_string(), "3000".to_string())];
+        let result = run_hook(
+            &hook("echo port=$PORTKILLER_PORT; echo oops >&2", 5_000),
+            &env,
+        );
+        assert_eq!(result.status, HookStatus::Succeeded);
+        assert!(result.output.contains("port=3000"));
+        assert!(result.output.contains("oops"));
+        assert_eq!(
+            result.describe().as_deref(),
+            Some("Pre-kill hook `echo port=$PORTKILLER_PORT; echo oops >&2`: port=3000 oops.")
+        );
+    }
+
+    #[test]
+    fn reports_failures_and_timeouts() {
+        let failed = run_hook(&hook("echo no pidfile; exit 3", 5_000), &[]);
+        assert_eq!(failed.status, HookStatus::Exited(Some(3)));
+
+        let started = Instant::now();
+        let slow = run_hook(&hook("sleep 5", 100), &[]);
+        assert_eq!(slow.status, HookStatus::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        let mut feedback = KillFeedback::info("Terminated node (PID 1).".into());
+        append_hook_results(&mut feedback, &[failed, slow]);
+        assert!(matches!(feedback.severity, FeedbackSeverity::Warning));
+        assert!(feedback.message.contains("exited with 3: no pidfile."));
+        assert!(feedback.message.contains("`sleep 5` timed out."));
+    }
+
+    #[test]
+    fn stops_collecting_output_at_the_deadline() {
+        let started = Instant::now();
+        let result = run_hook(&hook("echo hi; sleep 5 &", 300), &[]);
+        assert_eq!(result.status, HookStatus::Succeeded);
+        assert_eq!(result.output, "hi");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn skips_hooks_for_a_replaced_pid() {
+        let marker = temp_path("hook-ran");
+        let target = KillTarget {
+            pid: std::process::id() as i32,
+            label: "node".into(),
+            // Started long before this test: the PID has been reused since
+            identity: ProcessIdentity {
+                start_time: Some(1),
+                ..Default::default()
+            },
+            ladder: Vec::new(),
+            children: Vec::new(),
+            tree: false,
+            hooks: KillHooks {
+                pre: vec![hook(&format!("touch {}", marker.display()), 5_000)],
+                post: Vec::new(),
+                env: Vec::new(),
+            },
+        };
+        let (outcome, _, results) = terminate_with_hooks(&target, &FixtureScanner::new(Vec::new()));
+        assert!(matches!(outcome, KillOutcome::Replaced));
+        assert!(results.is_empty());
+        assert!(!marker.exists());
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::process::Command;
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use log::warn;
 
 use crate::model::KillFeedback;
@@ -16,15 +16,13 @@ pub fn query_brew_services_map() -> Result<HashMap<String, String>> {
         Ok(o) => o,
         Err(err) => {
             warn!("Brew command failed (brew not installed?): {}", err);
-            return Ok(map);
+            return Err(err).context("failed to run brew services list");
         }
     };
     if !out.status.success() {
-        warn!(
-            "Brew services list command failed: {}",
-            String::from_utf8_lossy(&out.stderr)
-        );
-        return Ok(map);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        warn!("Brew services list command failed: {}", stderr);
+        bail!("brew services list failed: {}", stderr.trim());
     }
     let stdout = String::from_utf8_lossy(&out.stdout);
     for (idx, line) in stdout.lines().enumerate() {
@@ -77,6 +75,23 @@ pub fn run_brew_stop(service: &str) -> KillFeedback {
     }
 }
 
+pub fn run_brew_start(service: &str) -> KillFeedback {
+    let res = Command::new(find_command("brew"))
+        .args(["services", "start", service])
+        .output();
+    match res {
+        Ok(out) if out.status.success() => {
+            KillFeedback::info(format!("Started brew service {} again.", service))
+        }
+        Ok(out) => KillFeedback::error(format!(
+            "Failed to start brew service {}: {}",
+            service,
+            String::from_utf8_lossy(&out.stderr)
+        )),
+        Err(err) => KillFeedback::error(format!("brew services error: {}", err)),
+    }
+}
+
 fn map_brew_service_from_cmd(cmd: &str) -> Option<String> {
     let lc = cmd.to_lowercase();
     if lc.contains("redis") {
@@ -1,79 +1,274 @@
-use std::collections::HashMap;
-use std::process::Command;
+use anyhow::{Context, Result, bail};
 
-use anyhow::Result;
-use log::warn;
+use crate::integrations::runtime::ContainerRuntime;
+use crate::model::{FeedbackSeverity, KillFeedback};
 
-use crate::model::{DockerContainerInfo, KillFeedback};
-use crate::utils::find_command;
+/// Restart policy of a container, e.g. "always" or "unless-stopped".
+pub fn query_restart_policy(runtime: ContainerRuntime, container: &str) -> Result<String> {
+    let out = runtime
+        .command()
+        .args([
+            "inspect",
+            "--format",
+            "{{.HostConfig.RestartPolicy.Name}}",
+            container,
+        ])
+        .output()
+        .with_context(|| format!("failed to run {} inspect", runtime.binary()))?;
+    if !out.status.success() {
+        bail!(
+            "{} inspect failed: {}",
+            runtime.binary(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+pub fn run_docker_start(runtime: ContainerRuntime, container: &str) -> KillFeedback {
+    let res = runtime.command().args(["start", container]).output();
+    match res {
+        Ok(out) if out.status.success() => {
+            KillFeedback::info(format!("Started container {} again.", container))
+        }
+        Ok(out) => KillFeedback::error(format!(
+            "Failed to start container {}: {}",
+            container,
+            String::from_utf8_lossy(&out.stderr)
+        )),
+        Err(err) => KillFeedback::error(format!("{} start error: {}", runtime.binary(), err)),
+    }
+}
+
+/// What to do to a single container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContainerAction {
+    Stop,
+    Kill,
+    Restart,
+    /// Force-remove, optionally with its anonymous volumes, and optionally
+    /// prune networks nothing uses any more.
+    Remove {
+        volumes: bool,
+        prune_networks: bool,
+    },
+}
 
-pub fn query_docker_port_map() -> Result<HashMap<u16, DockerContainerInfo>> {
-    let mut map = HashMap::new();
-    let out = Command::new(find_command("docker"))
-        .args(["ps", "--format", "{{.ID}}\t{{.Names}}\t{{.Ports}}"])
+impl ContainerAction {
+    fn args(self) -> Vec<&'static str> {
+        match self {
+            ContainerAction::Stop => vec!["stop"],
+            ContainerAction::Kill => vec!["kill"],
+            ContainerAction::Restart => vec!["restart"],
+            ContainerAction::Remove { volumes: false, .. } => vec!["rm", "-f"],
+            ContainerAction::Remove { volumes: true, .. } => vec!["rm", "-f", "-v"],
+        }
+    }
+
+    fn past_tense(self) -> &'static str {
+        match self {
+            ContainerAction::Stop => "Stopped",
+            ContainerAction::Kill => "Killed",
+            ContainerAction::Restart => "Restarted",
+            ContainerAction::Remove { volumes: false, .. } => "Removed",
+            ContainerAction::Remove { volumes: true, .. } => "Removed (with volumes)",
+        }
+    }
+
+    /// Whether `start` brings the container back afterwards.
+    pub fn undoable(self) -> bool {
+        matches!(self, ContainerAction::Stop | ContainerAction::Kill)
+    }
+}
+
+pub fn run_docker_stop(runtime: ContainerRuntime, container: &str) -> KillFeedback {
+    run_container_action(runtime, container, ContainerAction::Stop)
+}
+
+/// Run `action` on `container`. A container that vanished or already stopped
+/// in the meantime is a warning, not an error.
+pub fn run_container_action(
+    runtime: ContainerRuntime,
+    container: &str,
+    action: ContainerAction,
+) -> KillFeedback {
+    let verb = action.args()[0];
+    let res = runtime
+        .command()
+        .args(action.args())
+        .arg(container)
         .output();
-    let out = match out {
-        Ok(o) => o,
+    let out = match res {
+        Ok(out) => out,
         Err(err) => {
-            warn!("Docker command failed (docker not installed?): {}", err);
-            return Ok(map);
+            return KillFeedback::error(format!("{} {} error: {}", runtime.binary(), verb, err));
         }
     };
     if !out.status.success() {
-        warn!(
-            "Docker ps command failed: {}",
-            String::from_utf8_lossy(&out.stderr)
-        );
-        return Ok(map);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let stderr = stderr.trim();
+        if let Some(reason) = gone_reason(stderr) {
+            return KillFeedback::warning(format!("Container {} {}.", container, reason));
+        }
+        return KillFeedback::error(format!(
+            "Failed to {} container {}: {}",
+            verb, container, stderr
+        ));
     }
-    let stdout = String::from_utf8_lossy(&out.stdout);
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() < 3 {
-            continue;
+
+    let mut feedback =
+        KillFeedback::info(format!("{} container {}.", action.past_tense(), container));
+    if let ContainerAction::Remove {
+        prune_networks: true,
+        ..
+    } = action
+    {
+        let pruned = runtime.command().args(["network", "prune", "-f"]).output();
+        match pruned {
+            Ok(out) if out.status.success() => {
+                feedback.message.push_str(" Pruned unused networks.")
+            }
+            Ok(out) => {
+                feedback.message.push_str(&format!(
+                    " Network prune failed: {}",
+                    String::from_utf8_lossy(&out.stderr).trim()
+                ));
+                feedback.severity = FeedbackSeverity::Warning;
+            }
+            Err(err) => {
+                feedback
+                    .message
+                    .push_str(&format!(" Network prune failed: {}", err));
+                feedback.severity = FeedbackSeverity::Warning;
+            }
         }
-        let id = parts[0].to_string();
-        let name = parts[1].to_string();
-        let ports = parts[2];
-        for seg in ports.split(',') {
-            let seg = seg.trim();
-            if seg.is_empty() {
-                continue;
+    }
+    feedback
+}
+
+/// Recognise the runtimes' errors for a container that is already gone or
+/// not running.
+fn gone_reason(stderr: &str) -> Option<&'static str> {
+    let lower = stderr.to_lowercase();
+    if lower.contains("no such container") {
+        Some("no longer exists")
+    } else if lower.contains("is not running") {
+        Some("is not running")
+    } else {
+        None
+    }
+}
+
+/// A compose-level action on the containers of one project.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComposeAction {
+    StopProject {
+        project: String,
+    },
+    RestartService {
+        project: String,
+        service: String,
+    },
+    /// Stop and remove the project's containers and networks.
+    DownProject {
+        project: String,
+    },
+}
+
+impl ComposeAction {
+    pub fn project(&self) -> &str {
+        match self {
+            ComposeAction::StopProject { project }
+            | ComposeAction::RestartService { project, .. }
+            | ComposeAction::DownProject { project } => project,
+        }
+    }
+
+    /// Arguments after the runtime's binary. Compose finds the containers by their
+    /// project label, so no compose file is needed.
+    fn args(&self) -> Vec<&str> {
+        let mut args = vec!["compose", "-p", self.project()];
+        match self {
+            ComposeAction::StopProject { .. } => args.push("stop"),
+            ComposeAction::RestartService { service, .. } => args.extend(["restart", service]),
+            ComposeAction::DownProject { .. } => args.push("down"),
+        }
+        args
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            ComposeAction::StopProject { project } => {
+                format!("Stopped compose project {}.", project)
             }
-            if let Some((left, _right)) = seg.split_once("->")
-                && let Some((_, host)) = left.rsplit_once(':')
-            {
-                if host.contains('-') {
-                    continue;
-                }
-                if let Ok(p) = host.parse::<u16>() {
-                    map.insert(
-                        p,
-                        DockerContainerInfo {
-                            name: name.clone(),
-                            id: id.clone(),
-                        },
-                    );
-                }
+            ComposeAction::RestartService { project, service } => {
+                format!("Restarted {} in compose project {}.", service, project)
+            }
+            ComposeAction::DownProject { project } => {
+                format!("Took down compose project {}.", project)
             }
         }
     }
-    Ok(map)
 }
 
-pub fn run_docker_stop(container: &str) -> KillFeedback {
-    let res = Command::new(find_command("docker"))
-        .args(["stop", container])
-        .output();
+pub fn run_compose(runtime: ContainerRuntime, action: &ComposeAction) -> KillFeedback {
+    let res = runtime.command().args(action.args()).output();
     match res {
-        Ok(out) if out.status.success() => {
-            KillFeedback::info(format!("Stopped container {}.", container))
-        }
+        Ok(out) if out.status.success() => KillFeedback::info(action.describe()),
         Ok(out) => KillFeedback::error(format!(
-            "Failed to stop container {}: {}",
-            container,
-            String::from_utf8_lossy(&out.stderr)
+            "{} {} failed: {}",
+            runtime.binary(),
+            action.args().join(" "),
+            String::from_utf8_lossy(&out.stderr).trim()
         )),
-        Err(err) => KillFeedback::error(format!("docker stop error: {}", err)),
+        Err(err) => KillFeedback::error(format!("{} compose error: {}", runtime.binary(), err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vanished_containers_are_not_errors() {
+        assert_eq!(
+            gone_reason("Error response from daemon: No such container: shop-db-1"),
+            Some("no longer exists")
+        );
+        assert_eq!(
+            gone_reason("Error: no container with name or ID \"db\" found: no such container"),
+            Some("no longer exists")
+        );
+        assert_eq!(
+            gone_reason(
+                "Error response from daemon: Cannot kill container: db: Container 4f1c is not running"
+            ),
+            Some("is not running")
+        );
+        assert_eq!(
+            gone_reason("permission denied while trying to connect"),
+            None
+        );
+        assert_eq!(
+            ContainerAction::Remove {
+                volumes: true,
+                prune_networks: true
+            }
+            .args(),
+            ["rm", "-f", "-v"]
+        );
+    }
+
+    #[test]
+    fn compose_actions_target_the_project_by_name() {
+        let restart = ComposeAction::RestartService {
+            project: "shop".into(),
+            service: "db".into(),
+        };
+        assert_eq!(restart.args(), ["compose", "-p", "shop", "restart", "db"]);
+        let down = ComposeAction::DownProject {
+            project: "shop".into(),
+        };
+        assert_eq!(down.args(), ["compose", "-p", "shop", "down"]);
     }
 }
@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::integrations::runtime::ContainerRuntime;
+use crate::model::{DockerContainerInfo, Protocol, PublishedPort};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const EVENTS_RETRY: Duration = Duration::from_secs(10);
+// A `compose up` emits an event per container; refresh once per burst
+const EVENTS_DEBOUNCE: Duration = Duration::from_millis(500);
+pub const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+pub const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+// Only events that change which containers publish ports; health checks
+// otherwise flood the stream with exec_* events
+const EVENTS_FILTER: &str =
+    r#"{"type":["container"],"event":["start","die","destroy","pause","unpause","rename"]}"#;
+
+/// The daemon socket from `DOCKER_HOST` (unix:// only) or the usual
+/// locations. `None` when no socket exists, e.g. a TCP `DOCKER_HOST`.
+pub fn docker_socket_path() -> Option<PathBuf> {
+    if let Ok(host) = std::env::var("DOCKER_HOST")
+        && !host.is_empty()
+    {
+        return host
+            .strip_prefix("unix://")
+            .map(PathBuf::from)
+            .filter(|p| p.exists());
+    }
+    let mut candidates = vec![PathBuf::from("/var/run/docker.sock")];
+    if let Ok(home) = std::env::var("HOME") {
+        // Docker Desktop on macOS
+        candidates.push(PathBuf::from(home).join(".docker/run/docker.sock"));
+    }
+    candidates.into_iter().find(|p| p.exists())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiContainer {
+    pub id: String,
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub ports: Vec<ApiPort>,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+impl ApiContainer {
+    /// Container names come back with a leading slash.
+    pub fn name(&self) -> String {
+        self.names
+            .first()
+            .map(|n| n.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| self.id.chars().take(12).collect())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiPort {
+    #[serde(rename = "IP", default)]
+    pub ip: Option<String>,
+    #[serde(rename = "PrivatePort")]
+    pub private_port: u16,
+    #[serde(rename = "PublicPort", default)]
+    pub public_port: Option<u16>,
+    #[serde(rename = "Type")]
+    pub kind: String,
+}
+
+/// `GET /containers/json`: running containers with structured port bindings.
+pub fn list_containers(socket: &Path) -> Result<Vec<ApiContainer>> {
+    let body = get(socket, "/containers/json", Some(REQUEST_TIMEOUT))?;
+    serde_json::from_slice(&body).context("failed to parse Docker containers")
+}
+
+/// Published host ports of running containers, per host address and
+/// protocol.
+pub fn container_port_map(
+    containers: &[ApiContainer],
+) -> HashMap<PublishedPort, DockerContainerInfo> {
+    let mut map = HashMap::new();
+    for container in containers {
+        for port in &container.ports {
+            let protocol = match port.kind.as_str() {
+                "tcp" => Protocol::Tcp,
+                "udp" => Protocol::Udp,
+                _ => continue,
+            };
+            // The API leaves IP empty for bindings on every IPv4 address
+            let ip = match port.ip.as_deref() {
+                None | Some("") => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                Some(ip) => match ip.parse() {
+                    Ok(ip) => ip,
+                    Err(_) => continue,
+                },
+            };
+            if let Some(public) = port.public_port {
+                map.insert(
+                    PublishedPort {
+                        ip,
+                        port: public,
+                        protocol,
+                    },
+                    DockerContainerInfo {
+                        name: container.name(),
+                        id: container.id.clone(),
+                        project: container.labels.get(COMPOSE_PROJECT_LABEL).cloned(),
+                        service: container.labels.get(COMPOSE_SERVICE_LABEL).cloned(),
+                        runtime: ContainerRuntime::Docker,
+                    },
+                );
+            }
+        }
+    }
+    map
+}
+
+/// Follow `/events` on a background thread and call `on_change` once per
+/// burst of container starts and stops. Reconnects while `on_change` returns
+/// true.
+pub fn spawn_event_watcher(
+    socket: PathBuf,
+    on_change: impl Fn() -> bool + Send + 'static,
+) -> thread::JoinHandle<()> {
+    let (tx, rx) = crossbeam_channel::unbounded::<()>();
+    // Dropping `rx` when `on_change` gives up ends the watcher below
+    thread::spawn(move || {
+        while rx.recv().is_ok() {
+            thread::sleep(EVENTS_DEBOUNCE);
+            while rx.try_recv().is_ok() {}
+            if !on_change() {
+                return;
+            }
+        }
+    });
+    thread::spawn(move || {
+        let notify = || tx.send(()).is_ok();
+        loop {
+            match watch_events(&socket, &notify) {
+                Ok(false) => return,
+                Ok(true) => log::debug!("Docker event stream ended; reconnecting"),
+                Err(err) => log::debug!("Docker event stream failed: {:#}", err),
+            }
+            thread::sleep(EVENTS_RETRY);
+            // Catch up on anything missed while disconnected
+            if !notify() {
+                return;
+            }
+        }
+    })
+}
+
+// Ok(false) once the receiver is gone
+fn watch_events(socket: &Path, on_change: &impl Fn() -> bool) -> Result<bool> {
+    let path = format!("/events?filters={}", percent_encode(EVENTS_FILTER));
+    let body = open(socket, &path, None)?;
+    for line in BufReader::new(body).lines() {
+        let line = line.context("failed to read Docker events")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if !on_change() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn get(socket: &Path, path: &str, timeout: Option<Duration>) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    open(socket, path, timeout)?
+        .read_to_end(&mut body)
+        .context("failed to read Docker response")?;
+    Ok(body)
+}
+
+/// Send a GET and return the decoded body once the status is 2xx.
+fn open(socket: &Path, path: &str, timeout: Option<Duration>) -> Result<Box<dyn Read + Send>> {
+    let mut stream = UnixStream::connect(socket)
+        .with_context(|| format!("failed to connect to {}", socket.display()))?;
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: docker\r\nUser-Agent: PortKiller\r\nConnection: close\r\n\r\n",
+        path
+    )?;
+
+    let mut reader = BufReader::new(stream);
+    let head = read_head(&mut reader)?;
+    let body: Box<dyn Read + Send> = if head.chunked {
+        Box::new(ChunkedReader::new(reader))
+    } else if let Some(len) = head.content_length {
+        Box::new(reader.take(len))
+    } else {
+        Box::new(reader)
+    };
+    if !(200..300).contains(&head.status) {
+        let mut message = String::new();
+        let _ = body.take(1024).read_to_string(&mut message);
+        bail!(
+            "Docker API {} returned {}: {}",
+            path,
+            head.status,
+            message.trim()
+        );
+    }
+    Ok(body)
+}
+
+struct ResponseHead {
+    status: u16,
+    chunked: bool,
+    content_length: Option<u64>,
+}
+
+fn read_head(reader: &mut impl BufRead) -> Result<ResponseHead> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .with_context(|| format!("malformed HTTP status line {:?}", line.trim()))?;
+    let mut head = ResponseHead {
+        status,
+        chunked: false,
+        content_length: None,
+    };
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            head.chunked = value.eq_ignore_ascii_case("chunked");
+        } else if name.eq_ignore_ascii_case("content-length") {
+            head.content_length = value.parse().ok();
+        }
+    }
+    Ok(head)
+}
+
+/// Decodes an HTTP/1.1 chunked body.
+struct ChunkedReader<R> {
+    inner: R,
+    remaining: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let mut line = String::new();
+            if self.inner.read_line(&mut line)? == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+            let size = line.trim().split(';').next().unwrap_or("");
+            self.remaining = usize::from_str_radix(size, 16).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "bad chunk size")
+            })?;
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let want = buf.len().min(self.remaining);
+        let n = self.inner.read(&mut buf[..want])?;
+        if n == 0 {
+            self.done = true;
+            return Ok(0);
+        }
+        self.remaining -= n;
+        if self.remaining == 0 {
+            // CRLF after each chunk
+            let mut crlf = String::new();
+            self.inner.read_line(&mut crlf)?;
+        }
+        Ok(n)
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, mpsc};
+
+    use super::*;
+    use crate::test_util::temp_path;
+
+    const CONTAINERS: &str = r#"[
+        {"Id": "4f1c2d3e4a5b6c7d", "Names": ["/web-db-1"], "Labels": {"com.docker.compose.project": "web", "com.docker.compose.service": "db"},
+         "Ports": [
+            {"IP": "0.0.0.0", "PrivatePort": 5432, "PublicPort": 5432, "Type": "tcp"},
+            {"IP": "::", "PrivatePort": 5432, "PublicPort": 5432, "Type": "tcp"},
+            {"PrivatePort": 9000, "Type": "tcp"}
+         ]},
+        {"Id": "9a8b7c6d5e4f", "Names": ["/cache"], "Ports": [
+            {"IP": "127.0.0.1", "PrivatePort": 6379, "PublicPort": 6380, "Type": "tcp"}
+        ]}
+    ]"#;
+
+    /// Serve one canned response per connection and report each request line.
+    fn fake_daemon(name: &str, responses: Vec<String>) -> (PathBuf, mpsc::Receiver<String>) {
+        let path = temp_path(&format!("docker-{}.sock", name));
+        let listener = UnixListener::bind(&path).unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let _ = tx.send(request_line.trim().to_string());
+                let mut stream = stream;
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (path, rx)
+    }
+
+    fn chunked(parts: &[&str]) -> String {
+        let mut body = String::from("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+        for part in parts {
+            body.push_str(&format!("{:x}\r\n{}\r\n", part.len(), part));
+        }
+        body.push_str("0\r\n\r\n");
+        body
+    }
+
+    #[test]
+    fn lists_containers_over_the_socket() {
+        let (path, requests) = fake_daemon(
+            "list",
+            vec![
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    CONTAINERS.len(),
+                    CONTAINERS
+                ),
+                chunked(&[&CONTAINERS[..40], &CONTAINERS[40..]]),
+            ],
+        );
+
+        let containers = list_containers(&path).unwrap();
+        assert_eq!(requests.recv().unwrap(), "GET /containers/json HTTP/1.1");
+        assert_eq!(containers.len(), 2);
+        let map = container_port_map(&containers);
+        // IPv4 and IPv6 bindings of the same port stay apart
+        assert_eq!(map.len(), 3);
+        let key = |ip: &str, port| PublishedPort {
+            ip: ip.parse().unwrap(),
+            port,
+            protocol: Protocol::Tcp,
+        };
+        let db = &map[&key("0.0.0.0", 5432)];
+        assert_eq!(db.name, "web-db-1");
+        assert_eq!(map[&key("::", 5432)], *db);
+        assert_eq!(map[&key("127.0.0.1", 6380)].name, "cache");
+        assert_eq!(db.project.as_deref(), Some("web"));
+        assert_eq!(db.service.as_deref(), Some("db"));
+        assert_eq!(map[&key("127.0.0.1", 6380)].project, None);
+
+        // Chunked responses decode to the same body
+        assert_eq!(list_containers(&path).unwrap().len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reports_api_errors() {
+        let (path, _requests) = fake_daemon("error", vec![
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 24\r\n\r\n{\"message\":\"daemon sad\"}".into(),
+        ]);
+        let err = list_containers(&path).unwrap_err().to_string();
+        assert!(err.contains("500"), "{}", err);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn streams_container_events() {
+        let (path, requests) = fake_daemon(
+            "events",
+            vec![chunked(&[
+                "{\"status\":\"start\",\"id\":\"4f1c\"}\n",
+                "{\"status\":\"die\",\"id\":\"4f1c\"}\n",
+            ])],
+        );
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        // Stop after both events so the watcher does not reconnect
+        let keep_going = watch_events(&path, &move || counter.fetch_add(1, Ordering::SeqCst) < 1);
+        assert!(!keep_going.unwrap());
+        assert_eq!(seen.load(Ordering::SeqCst), 2);
+        let request = requests.recv().unwrap();
+        assert!(
+            request.starts_with("GET /events?filters=%7B%22type%22"),
+            "{}",
+            request
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn coalesces_a_burst_of_events() {
+        let events: Vec<String> = (0..5)
+            .map(|i| format!("{{\"status\":\"start\",\"id\":\"c{}\"}}\n", i))
+            .collect();
+        let parts: Vec<&str> = events.iter().map(String::as_str).collect();
+        let (path, _requests) = fake_daemon("burst", vec![chunked(&parts)]);
+        let (tx, rx) = mpsc::channel();
+        spawn_event_watcher(path.clone(), move || tx.send(()).is_ok());
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(rx.recv_timeout(EVENTS_DEBOUNCE * 2).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
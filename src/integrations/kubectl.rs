@@ -0,0 +1,217 @@
+use crate::config::RestartConfig;
+use crate::model::{AppState, ProcessInfo};
+
+// Global and port-forward flags that take a value as the next argument
+const VALUE_FLAGS: &[&str] = &[
+    "--context",
+    "-n",
+    "--namespace",
+    "--kubeconfig",
+    "--cluster",
+    "--user",
+    "-s",
+    "--server",
+    "--token",
+    "--as",
+    "--as-group",
+    "--as-uid",
+    "--address",
+    "--pod-running-timeout",
+    "--request-timeout",
+    "--cache-dir",
+    "--certificate-authority",
+    "--client-certificate",
+    "--client-key",
+    "--tls-server-name",
+    "-v",
+    "--v",
+];
+
+/// What a `kubectl port-forward` process forwards, read from its arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortForward {
+    pub context: Option<String>,
+    pub namespace: Option<String>,
+    /// As given, e.g. `svc/db` or a bare pod name.
+    pub resource: String,
+    pub ports: Vec<ForwardedPort>,
+}
+
+/// One `LOCAL:REMOTE` pair; `local` is `None` when kubectl picks it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForwardedPort {
+    pub local: Option<u16>,
+    /// A number or a named container port such as `http`.
+    pub remote: String,
+}
+
+impl PortForward {
+    /// "svc/db in staging (prod)", leaving out what kubectl would default.
+    pub fn target(&self) -> String {
+        let mut text = self.resource.clone();
+        if let Some(namespace) = &self.namespace {
+            text.push_str(&format!(" in {}", namespace));
+        }
+        if let Some(context) = &self.context {
+            text.push_str(&format!(" ({})", context));
+        }
+        text
+    }
+
+    /// "5432→5432, 8080→http".
+    pub fn mapping(&self) -> String {
+        self.ports
+            .iter()
+            .map(|p| match p.local {
+                Some(local) => format!("{}→{}", local, p.remote),
+                None => format!("?→{}", p.remote),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Parse a kubectl command line (arguments joined by spaces, as `ps` shows
+/// them). `None` unless it is a `port-forward`.
+pub fn parse_port_forward(args: &str) -> Option<PortForward> {
+    let mut tokens = args.split_whitespace();
+    let program = tokens.next()?;
+    if !program.rsplit('/').next()?.starts_with("kubectl") {
+        return None;
+    }
+
+    let mut context = None;
+    let mut namespace = None;
+    let mut subcommand = None;
+    let mut positional = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token == "--" {
+            positional.extend(tokens.by_ref());
+            break;
+        }
+        let (flag, inline) = match token.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => (flag, Some(value)),
+            _ => (token, None),
+        };
+        if flag.starts_with('-') && flag.len() > 1 {
+            // `-nstaging` is kubectl's short form too
+            let (flag, inline) = match flag.strip_prefix("-n") {
+                Some(rest) if !flag.starts_with("--") && !rest.is_empty() => ("-n", Some(rest)),
+                _ => (flag, inline),
+            };
+            if !VALUE_FLAGS.contains(&flag) {
+                continue;
+            }
+            let value = match inline {
+                Some(value) => value,
+                None => tokens.next()?,
+            };
+            match flag {
+                "--context" => context = Some(value.to_string()),
+                "-n" | "--namespace" => namespace = Some(value.to_string()),
+                _ => {}
+            }
+        } else if subcommand.is_none() {
+            subcommand = Some(token);
+        } else {
+            positional.push(token);
+        }
+    }
+    if subcommand != Some("port-forward") {
+        return None;
+    }
+
+    let (resource, specs) = positional.split_first()?;
+    let ports = specs
+        .iter()
+        .map(|spec| parse_port_spec(spec))
+        .collect::<Option<Vec<_>>>()?;
+    if ports.is_empty() {
+        return None;
+    }
+    Some(PortForward {
+        context,
+        namespace,
+        resource: resource.to_string(),
+        ports,
+    })
+}
+
+/// `8080:80`, `5432` (same on both ends) or `:80` (random local port).
+fn parse_port_spec(spec: &str) -> Option<ForwardedPort> {
+    let (local, remote) = match spec.split_once(':') {
+        Some(("", remote)) => (None, remote),
+        Some((local, remote)) => (Some(local.parse().ok()?), remote),
+        None => (Some(spec.parse().ok()?), spec),
+    };
+    if remote.is_empty() {
+        return None;
+    }
+    Some(ForwardedPort {
+        local,
+        remote: remote.to_string(),
+    })
+}
+
+/// The port-forward behind a listener, if its process is one.
+pub fn port_forward_for(state: &AppState, process: &ProcessInfo) -> Option<PortForward> {
+    if !process.command.starts_with("kubectl") {
+        return None;
+    }
+    parse_port_forward(&state.process_details.get(&process.pid)?.args)
+}
+
+/// Restart settings for re-establishing a forward: always on, and keeping
+/// `KUBECONFIG` so the new kubectl talks to the same cluster.
+pub fn forward_restart_config(config: &RestartConfig) -> RestartConfig {
+    let mut config = config.clone();
+    config.enabled = true;
+    if !config.env.iter().any(|name| name == "KUBECONFIG") {
+        config.env.push("KUBECONFIG".to_string());
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_recorded_port_forwards() {
+        let forward = parse_port_forward(
+            "/usr/local/bin/kubectl --context prod-eu -n payments port-forward svc/postgres 15432:5432",
+        )
+        .unwrap();
+        assert_eq!(forward.context.as_deref(), Some("prod-eu"));
+        assert_eq!(forward.namespace.as_deref(), Some("payments"));
+        assert_eq!(forward.resource, "svc/postgres");
+        assert_eq!(forward.mapping(), "15432→5432");
+        assert_eq!(forward.target(), "svc/postgres in payments (prod-eu)");
+
+        let forward = parse_port_forward(
+            "kubectl port-forward --address 0.0.0.0 --namespace=monitoring deployment/grafana 3000 :9090 8080:http -v=4",
+        )
+        .unwrap();
+        assert_eq!(forward.context, None);
+        assert_eq!(forward.namespace.as_deref(), Some("monitoring"));
+        assert_eq!(forward.resource, "deployment/grafana");
+        assert_eq!(forward.mapping(), "3000→3000, ?→9090, 8080→http");
+
+        let forward =
+            parse_port_forward("kubectl1.29 -nstaging port-forward redis-0 6379").unwrap();
+        assert_eq!(forward.namespace.as_deref(), Some("staging"));
+        assert_eq!(forward.target(), "redis-0 in staging");
+    }
+
+    #[test]
+    fn ignores_other_commands() {
+        assert_eq!(parse_port_forward("kubectl get pods -n port-forward"), None);
+        assert_eq!(parse_port_forward("kubectl proxy --port 8001"), None);
+        assert_eq!(parse_port_forward("ssh -L 5432:db:5432 bastion"), None);
+        assert_eq!(parse_port_forward("kubectl port-forward svc/db"), None);
+        assert_eq!(
+            parse_port_forward("kubectl port-forward svc/db abc:80"),
+            None
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::IntegrationsConfig;
+use crate::integrations::docker_api::{
+    COMPOSE_PROJECT_LABEL, COMPOSE_SERVICE_LABEL, container_port_map, docker_socket_path,
+    list_containers,
+};
+use crate::model::{DockerContainerInfo, Protocol, PublishedPort};
+use crate::utils::find_command;
+
+/// A container engine with a Docker-compatible CLI.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerRuntime {
+    #[default]
+    Docker,
+    Podman,
+    /// containerd through nerdctl, e.g. Rancher Desktop.
+    Nerdctl,
+}
+
+impl ContainerRuntime {
+    pub const ALL: [ContainerRuntime; 3] = [
+        ContainerRuntime::Docker,
+        ContainerRuntime::Podman,
+        ContainerRuntime::Nerdctl,
+    ];
+
+    /// Executable name, also used as the runtime's label in ids and metrics.
+    pub fn binary(self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+            ContainerRuntime::Nerdctl => "nerdctl",
+        }
+    }
+
+    pub fn from_binary(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.binary() == name)
+    }
+
+    pub fn enabled(self, config: &IntegrationsConfig) -> bool {
+        match self {
+            ContainerRuntime::Docker => config.docker_enabled,
+            ContainerRuntime::Podman => config.podman_enabled,
+            ContainerRuntime::Nerdctl => config.nerdctl_enabled,
+        }
+    }
+
+    /// `Command` for the runtime's CLI.
+    pub fn command(self) -> Command {
+        Command::new(find_command(self.binary()))
+    }
+}
+
+/// Runtimes turned on in the config, in lookup order.
+pub fn enabled_runtimes(config: &IntegrationsConfig) -> Vec<ContainerRuntime> {
+    ContainerRuntime::ALL
+        .into_iter()
+        .filter(|r| r.enabled(config))
+        .collect()
+}
+
+/// Published ports across several runtimes and how each runtime answered.
+#[derive(Debug, Default)]
+pub struct ContainerQuery {
+    pub ports: HashMap<PublishedPort, DockerContainerInfo>,
+    status: Vec<(ContainerRuntime, Option<bool>)>,
+}
+
+impl ContainerQuery {
+    /// Whether `runtime` answered; `None` when it was not asked or its CLI is
+    /// not installed.
+    pub fn status(&self, runtime: ContainerRuntime) -> Option<bool> {
+        self.status
+            .iter()
+            .find(|(r, _)| *r == runtime)
+            .and_then(|(_, up)| *up)
+    }
+}
+
+/// Ask every runtime in `runtimes`. A port claimed by several runtimes goes
+/// to the first.
+pub fn query_container_port_map(runtimes: &[ContainerRuntime]) -> ContainerQuery {
+    let mut query = ContainerQuery::default();
+    for &runtime in runtimes {
+        match query_runtime_port_map(runtime) {
+            Ok(found) => {
+                for (port, info) in found {
+                    query.ports.entry(port).or_insert(info);
+                }
+                query.status.push((runtime, Some(true)));
+            }
+            Err(err) if not_installed(&err) => {
+                log::debug!("{} is not installed", runtime.binary());
+                query.status.push((runtime, None));
+            }
+            Err(err) => {
+                log::warn!("{} ps failed: {:#}", runtime.binary(), err);
+                query.status.push((runtime, Some(false)));
+            }
+        }
+    }
+    query
+}
+
+/// Docker is asked over its socket first; everything else through `ps`.
+fn query_runtime_port_map(
+    runtime: ContainerRuntime,
+) -> Result<HashMap<PublishedPort, DockerContainerInfo>> {
+    if runtime == ContainerRuntime::Docker
+        && let Some(socket) = docker_socket_path()
+    {
+        match list_containers(&socket) {
+            Ok(containers) => return Ok(container_port_map(&containers)),
+            Err(err) => log::debug!("Docker API unavailable, using the CLI: {:#}", err),
+        }
+    }
+    query_cli_port_map(runtime)
+}
+
+fn query_cli_port_map(
+    runtime: ContainerRuntime,
+) -> Result<HashMap<PublishedPort, DockerContainerInfo>> {
+    let out = runtime
+        .command()
+        .args([
+            "ps",
+            "--format",
+            "{{.ID}}\t{{.Names}}\t{{.Labels}}\t{{.Ports}}",
+        ])
+        .output()
+        .with_context(|| format!("failed to run {} ps", runtime.binary()))?;
+    if !out.status.success() {
+        bail!(
+            "{} ps failed: {}",
+            runtime.binary(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(parse_ps_output(
+        runtime,
+        &String::from_utf8_lossy(&out.stdout),
+    ))
+}
+
+fn parse_ps_output(
+    runtime: ContainerRuntime,
+    stdout: &str,
+) -> HashMap<PublishedPort, DockerContainerInfo> {
+    stdout
+        .lines()
+        .flat_map(|line| parse_ps_line(runtime, line))
+        .collect()
+}
+
+fn not_installed(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::NotFound)
+}
+
+/// One `ps` row: ID, names, labels and ports separated by tabs.
+fn parse_ps_line(
+    runtime: ContainerRuntime,
+    line: &str,
+) -> Vec<(PublishedPort, DockerContainerInfo)> {
+    let parts: Vec<&str> = line.split('\t').collect();
+    if parts.len() < 4 {
+        return Vec::new();
+    }
+    let labels = parse_labels(parts[2]);
+    let info = DockerContainerInfo {
+        name: parts[1].to_string(),
+        id: parts[0].to_string(),
+        project: labels.get(COMPOSE_PROJECT_LABEL).cloned(),
+        service: labels.get(COMPOSE_SERVICE_LABEL).cloned(),
+        runtime,
+    };
+    parse_ports(parts[3])
+        .into_iter()
+        .map(|port| (port, info.clone()))
+        .collect()
+}
+
+/// The `Ports` column, e.g. `0.0.0.0:8000-8002->8000-8002/tcp, [::]:53->53/udp,
+/// 9229/tcp`. Ranges are expanded; ports that are exposed but not published
+/// (no `->`) and protocols other than TCP and UDP are skipped.
+pub fn parse_ports(field: &str) -> Vec<PublishedPort> {
+    let mut ports = Vec::new();
+    for seg in field.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((host, container)) = seg.split_once("->") else {
+            continue;
+        };
+        let protocol = match container.rsplit_once('/').map(|(_, proto)| proto) {
+            Some("tcp") => Protocol::Tcp,
+            Some("udp") => Protocol::Udp,
+            _ => continue,
+        };
+        // Docker before 26 prints IPv6 as `:::5432`, later as `[::]:5432`
+        let (ip, range) = host.rsplit_once(':').unwrap_or(("0.0.0.0", host));
+        let ip = ip.trim_start_matches('[').trim_end_matches(']');
+        let Ok(ip) = ip.parse::<IpAddr>() else {
+            continue;
+        };
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let (Ok(start), Ok(end)) = (start.parse::<u16>(), end.parse::<u16>()) else {
+            continue;
+        };
+        ports.extend((start..=end).map(|port| PublishedPort { ip, port, protocol }));
+    }
+    ports
+}
+
+/// Docker and nerdctl print labels as `k=v,k=v`; Podman prints the Go map,
+/// `map[k:v k:v]`. Fragments of values with commas or spaces are dropped,
+/// which is fine for the compose labels we read.
+fn parse_labels(raw: &str) -> HashMap<String, String> {
+    let raw = raw.trim();
+    let pairs: Vec<(&str, &str)> = match raw.strip_prefix("map[").and_then(|r| r.strip_suffix(']'))
+    {
+        Some(inner) => inner
+            .split(' ')
+            .filter_map(|pair| pair.split_once(':'))
+            .collect(),
+        None => raw
+            .split(',')
+            .filter_map(|pair| pair.split_once('='))
+            .collect(),
+    };
+    pairs
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(ip: &str, port: u16, protocol: Protocol) -> PublishedPort {
+        PublishedPort {
+            ip: ip.parse().unwrap(),
+            port,
+            protocol,
+        }
+    }
+
+    #[test]
+    fn parses_docker_ps_fixture() {
+        let map = parse_ps_output(
+            ContainerRuntime::Docker,
+            include_str!("fixtures/docker-ps.txt"),
+        );
+        // db on two families, web's 3-port range on two families, dns on
+        // UDP and TCP, and two containers sharing 8080 on different IPs
+        assert_eq!(map.len(), 2 + 6 + 2 + 2);
+
+        let db = &map[&key("::", 5432, Protocol::Tcp)];
+        assert_eq!(db.name, "shop-db-1");
+        assert_eq!(db.project.as_deref(), Some("shop"));
+        assert_eq!(db.service.as_deref(), Some("db"));
+        assert_eq!(map[&key("0.0.0.0", 5432, Protocol::Tcp)], *db);
+
+        for port in 8000..=8002 {
+            assert_eq!(map[&key("0.0.0.0", port, Protocol::Tcp)].name, "shop-web-1");
+        }
+        assert!(!map.keys().any(|k| k.port == 9229 || k.port == 6379));
+
+        assert_eq!(map[&key("127.0.0.1", 5353, Protocol::Udp)].name, "dnsmasq");
+        assert_eq!(map[&key("127.0.0.1", 5353, Protocol::Tcp)].name, "dnsmasq");
+        assert_eq!(map[&key("127.0.0.1", 8080, Protocol::Tcp)].name, "api-blue");
+        assert_eq!(
+            map[&key("127.0.0.2", 8080, Protocol::Tcp)].name,
+            "api-green"
+        );
+    }
+
+    #[test]
+    fn parses_older_ipv6_notation_and_podman() {
+        let map = parse_ps_output(
+            ContainerRuntime::Docker,
+            include_str!("fixtures/docker-ps-ipv6-colons.txt"),
+        );
+        assert_eq!(map.len(), 2 + 4);
+        assert_eq!(map[&key("::", 6379, Protocol::Tcp)].name, "redis");
+        assert_eq!(map[&key("::", 9001, Protocol::Tcp)].name, "minio");
+
+        let map = parse_ps_output(
+            ContainerRuntime::Podman,
+            include_str!("fixtures/podman-ps.txt"),
+        );
+        let db = &map[&key("0.0.0.0", 5433, Protocol::Tcp)];
+        assert_eq!(db.runtime, ContainerRuntime::Podman);
+        assert_eq!(db.project.as_deref(), Some("shop"));
+        assert_eq!(db.service.as_deref(), Some("db"));
+        let mailpit = &map[&key("0.0.0.0", 8025, Protocol::Tcp)];
+        assert_eq!(mailpit.project, None);
+    }
+
+    #[test]
+    fn skips_unpublished_and_unknown_ports() {
+        assert_eq!(parse_ports("80/tcp, 5000-5001/tcp"), []);
+        assert_eq!(parse_ports("0.0.0.0:2905->2905/sctp"), []);
+        assert_eq!(
+            parse_ports("[::1]:8443->443/tcp"),
+            [key("::1", 8443, Protocol::Tcp)]
+        );
+    }
+
+    #[test]
+    fn filters_runtimes_by_config() {
+        assert_eq!(
+            enabled_runtimes(&IntegrationsConfig::default()),
+            [ContainerRuntime::Docker]
+        );
+        let config = IntegrationsConfig {
+            nerdctl_enabled: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            enabled_runtimes(&config),
+            [ContainerRuntime::Docker, ContainerRuntime::Nerdctl]
+        );
+        assert_eq!(
+            ContainerRuntime::from_binary("podman"),
+            Some(ContainerRuntime::Podman)
+        );
+    }
+}
@@ -0,0 +1,497 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::model::{AppState, KillFeedback, ProcessInfo};
+use crate::utils::find_command;
+
+// ssh options that take an argument; see ssh(1)
+const VALUE_OPTIONS: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+/// A port an ssh process listens on for the local side of a forward.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SshTunnel {
+    pub kind: TunnelKind,
+    pub port: u16,
+    /// The forward as ssh spells it after `-L`/`-D`, e.g. `5432:db:5432`.
+    pub spec: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TunnelKind {
+    /// `-L`/`LocalForward`, to `host:port` (or a socket path) on the far side.
+    Local { target: String },
+    /// `-D`/`DynamicForward`, a SOCKS proxy.
+    Dynamic,
+}
+
+/// What an ssh process forwards and how to reach its control socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SshConnection {
+    /// The host as typed, e.g. `bastion` or `deploy@bastion`.
+    pub destination: String,
+    pub tunnels: Vec<SshTunnel>,
+    /// A ControlMaster whose forwards can be cancelled one by one.
+    pub multiplexed: bool,
+    /// `-F`, `-S`, `-p`, `-l` and `-o Control*` as given, to reach the master.
+    control_args: Vec<String>,
+}
+
+impl SshConnection {
+    /// Host part of the destination, which is what `Host` blocks match.
+    pub fn host(&self) -> &str {
+        let dest = self
+            .destination
+            .strip_prefix("ssh://")
+            .unwrap_or(&self.destination);
+        let host = dest.rsplit('@').next().unwrap_or(dest);
+        // ssh://host:2222
+        match host.rsplit_once(':') {
+            Some((host, port)) if port.parse::<u16>().is_ok() && !host.contains(':') => host,
+            _ => host,
+        }
+    }
+
+    pub fn tunnel_on(&self, port: u16) -> Option<&SshTunnel> {
+        self.tunnels.iter().find(|t| t.port == port)
+    }
+
+    /// "tunnel to db:5432 via bastion" or "SOCKS proxy via bastion".
+    pub fn describe(&self, tunnel: &SshTunnel) -> String {
+        match &tunnel.kind {
+            TunnelKind::Local { target } => format!("tunnel to {} via {}", target, self.host()),
+            TunnelKind::Dynamic => format!("SOCKS proxy via {}", self.host()),
+        }
+    }
+
+    /// Menu label for ending the whole session, which takes every tunnel
+    /// it carries with it.
+    pub fn close_label(&self) -> String {
+        match self.tunnels.len() {
+            1 => "Close Connection".to_string(),
+            n => format!("Close Connection ({} tunnels)", n),
+        }
+    }
+
+    /// Arguments for `ssh -O cancel` that drop one forward from the master.
+    pub fn cancel_args(&self, tunnel: &SshTunnel) -> Vec<String> {
+        let flag = match tunnel.kind {
+            TunnelKind::Local { .. } => "-L",
+            TunnelKind::Dynamic => "-D",
+        };
+        let mut args = self.control_args.clone();
+        args.extend(
+            ["-O", "cancel", flag, &tunnel.spec, &self.destination]
+                .into_iter()
+                .map(String::from),
+        );
+        args
+    }
+}
+
+/// Parse an ssh command line (arguments joined by spaces, as `ps` shows
+/// them) and add the forwards `config` declares for its host. `None` unless
+/// it is an ssh session that forwards something.
+pub fn parse_ssh(args: &str, config: Option<&str>) -> Option<SshConnection> {
+    let mut tokens = args.split_whitespace();
+    let program = tokens.next()?;
+    if program.rsplit('/').next()? != "ssh" {
+        return None;
+    }
+
+    let mut destination = None;
+    let mut tunnels = Vec::new();
+    let mut control_args = Vec::new();
+    let mut master = false;
+    let mut control_master = None;
+    let mut control_path = None;
+    let mut control_command = false;
+    while let Some(token) = tokens.next() {
+        let Some(flags) = token.strip_prefix('-').filter(|f| !f.is_empty()) else {
+            // OpenSSH keeps reading options after the destination; the first
+            // other word starts the remote command
+            if destination.is_some() {
+                break;
+            }
+            destination = Some(token.to_string());
+            continue;
+        };
+        for (i, flag) in flags.char_indices() {
+            if !VALUE_OPTIONS.contains(flag) {
+                master |= flag == 'M';
+                continue;
+            }
+            let rest = &flags[i + flag.len_utf8()..];
+            let value = if rest.is_empty() {
+                tokens.next()?.to_string()
+            } else {
+                rest.to_string()
+            };
+            match flag {
+                'L' => tunnels.extend(local_tunnel(&value)),
+                'D' => tunnels.extend(dynamic_tunnel(&value)),
+                // `-R` (and `RemoteForward`) listen on the far side, so no
+                // local port belongs to them; they are deliberately not listed
+                'O' => control_command = true,
+                'S' => {
+                    control_path.get_or_insert(value.clone());
+                    control_args.extend(["-S".to_string(), value]);
+                }
+                'F' | 'p' | 'l' => control_args.extend([format!("-{}", flag), value]),
+                'o' => {
+                    // `-o "Key value"` arrives split in two by `ps`
+                    let (key, value) = match value.split_once('=') {
+                        Some((key, value)) => (key.to_string(), value.to_string()),
+                        None => (value, tokens.next()?.to_string()),
+                    };
+                    match key.to_ascii_lowercase().as_str() {
+                        "controlmaster" => {
+                            control_master.get_or_insert(value.clone());
+                        }
+                        "controlpath" => {
+                            control_path.get_or_insert(value.clone());
+                        }
+                        "localforward" => {
+                            let target = tokens.next()?;
+                            tunnels.extend(local_tunnel(&format!("{}:{}", value, target)));
+                            continue;
+                        }
+                        "dynamicforward" => {
+                            tunnels.extend(dynamic_tunnel(&value));
+                            continue;
+                        }
+                        _ => continue,
+                    }
+                    control_args.extend(["-o".to_string(), format!("{}={}", key, value)]);
+                }
+                _ => {}
+            }
+            break;
+        }
+    }
+    // `ssh -O cancel …` talks to a master; it forwards nothing itself
+    if control_command {
+        return None;
+    }
+
+    let mut connection = SshConnection {
+        destination: destination?,
+        tunnels,
+        multiplexed: false,
+        control_args,
+    };
+    if let Some(config) = config {
+        let host_config = HostConfig::parse(config, connection.host());
+        connection.tunnels.extend(host_config.tunnels);
+        control_master = control_master.or(host_config.control_master);
+        control_path = control_path.or(host_config.control_path);
+    }
+    let control_master = if master {
+        Some("yes".to_string())
+    } else {
+        control_master
+    };
+    connection.multiplexed = matches!(
+        control_master
+            .as_deref()
+            .map(str::to_ascii_lowercase)
+            .as_deref(),
+        Some("yes" | "auto" | "ask" | "autoask")
+    ) && control_path.is_some_and(|p| !p.eq_ignore_ascii_case("none"));
+    (!connection.tunnels.is_empty()).then_some(connection)
+}
+
+/// `[bind:]port:host:hostport` or `[bind:]port:/remote/socket`.
+fn local_tunnel(spec: &str) -> Option<SshTunnel> {
+    let parts = split_forward(spec);
+    let (port, target) = match parts.as_slice() {
+        [_, port, socket] if socket.starts_with('/') => (port, socket.to_string()),
+        [port, host, host_port] => (port, host_and_port(host, host_port)),
+        [_, port, host, host_port] => (port, host_and_port(host, host_port)),
+        [port, socket] if socket.starts_with('/') => (port, socket.to_string()),
+        _ => return None,
+    };
+    Some(SshTunnel {
+        kind: TunnelKind::Local { target },
+        port: port.parse().ok()?,
+        spec: spec.to_string(),
+    })
+}
+
+/// `host:port`, with an IPv6 host back in brackets so the port stays
+/// readable: `[fd00::5]:443`.
+fn host_and_port(host: &str, port: &str) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// `[bind:]port`.
+fn dynamic_tunnel(spec: &str) -> Option<SshTunnel> {
+    let port = split_forward(spec).last()?.parse().ok()?;
+    Some(SshTunnel {
+        kind: TunnelKind::Dynamic,
+        port,
+        spec: spec.to_string(),
+    })
+}
+
+/// Split on `:` outside of `[ipv6]` brackets, dropping the brackets.
+fn split_forward(spec: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut bracketed = false;
+    for c in spec.chars() {
+        match c {
+            '[' => bracketed = true,
+            ']' => bracketed = false,
+            ':' if !bracketed => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    parts
+}
+
+/// The settings of `~/.ssh/config` that apply to one host alias.
+#[derive(Debug, Default)]
+struct HostConfig {
+    tunnels: Vec<SshTunnel>,
+    control_master: Option<String>,
+    control_path: Option<String>,
+}
+
+impl HostConfig {
+    /// First value wins, as in ssh; forwards accumulate. `Match` blocks and
+    /// `Include` are not followed.
+    fn parse(text: &str, host: &str) -> Self {
+        let mut config = HostConfig::default();
+        let mut active = true;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((keyword, args)) = line
+                .split_once(|c: char| c.is_whitespace() || c == '=')
+                .map(|(k, v)| {
+                    (
+                        k.to_ascii_lowercase(),
+                        v.trim_start_matches([' ', '\t', '=']),
+                    )
+                })
+            else {
+                continue;
+            };
+            match keyword.as_str() {
+                "host" => active = host_matches(args, host),
+                "match" => active = false,
+                _ if !active => {}
+                "localforward" => {
+                    let mut words = args.split_whitespace();
+                    if let (Some(listen), Some(target)) = (words.next(), words.next()) {
+                        config
+                            .tunnels
+                            .extend(local_tunnel(&format!("{}:{}", listen, target)));
+                    }
+                }
+                "dynamicforward" => config.tunnels.extend(dynamic_tunnel(args.trim())),
+                "controlmaster" => {
+                    config.control_master.get_or_insert(args.trim().to_string());
+                }
+                "controlpath" => {
+                    config.control_path.get_or_insert(args.trim().to_string());
+                }
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+/// A `Host` line's patterns: any positive match and no negated one.
+fn host_matches(patterns: &str, host: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split_whitespace() {
+        match pattern.strip_prefix('!') {
+            Some(negated) if glob(negated, host) => return false,
+            Some(_) => {}
+            None => matched |= glob(pattern, host),
+        }
+    }
+    matched
+}
+
+/// `*` and `?` wildcards, case-insensitive like ssh.
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let text: Vec<char> = text.to_ascii_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp + 1;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// `~/.ssh/config`, or the `-F` file named on the command line.
+fn read_config(args: &str) -> Option<String> {
+    let mut tokens = args.split_whitespace();
+    let path = loop {
+        match tokens.next() {
+            Some("-F") => break PathBuf::from(tokens.next()?),
+            Some(token) if token.starts_with("-F") => break PathBuf::from(&token[2..]),
+            Some(_) => {}
+            None => break PathBuf::from(std::env::var("HOME").ok()?).join(".ssh/config"),
+        }
+    };
+    fs::read_to_string(path).ok()
+}
+
+/// Parse an ssh process's command line together with the config file it
+/// reads.
+pub fn read_ssh_connection(args: &str) -> Option<SshConnection> {
+    parse_ssh(args, read_config(args).as_deref())
+}
+
+/// The ssh connection behind a listener, if its process is one that
+/// forwards the listener's port. Reads `AppState::ssh_connections`, which
+/// `query_listener_details` fills.
+pub fn ssh_tunnel_for(state: &AppState, process: &ProcessInfo) -> Option<SshConnection> {
+    if process.command != "ssh" {
+        return None;
+    }
+    let connection = state.ssh_connections.get(&process.pid)?.as_ref()?;
+    connection.tunnel_on(process.port)?;
+    Some(connection.clone())
+}
+
+/// Drop one forward through the master's control socket.
+pub fn cancel_forward(args: &[String], label: &str) -> KillFeedback {
+    match Command::new(find_command("ssh")).args(args).output() {
+        Ok(out) if out.status.success() => KillFeedback::info(format!("Closed {}.", label)),
+        Ok(out) => KillFeedback::error(format!(
+            "ssh -O cancel failed for {}: {}",
+            label,
+            String::from_utf8_lossy(&out.stderr).trim()
+        )),
+        Err(err) => KillFeedback::error(format!("ssh error: {}", err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "
+Host *
+    ServerAliveInterval 30
+
+Host bastion bastion-*  !bastion-old
+    HostName bastion.example.com
+    LocalForward 6379 cache.internal:6379
+    LocalForward [::1]:9200 search.internal:9200
+    DynamicForward 1080
+    ControlMaster auto
+    ControlPath ~/.ssh/cm-%C
+
+Match host legacy
+    LocalForward 7000 old:7000
+";
+
+    #[test]
+    fn labels_command_line_forwards() {
+        let ssh = parse_ssh("ssh -fN -L 5432:db:5432 bastion", None).unwrap();
+        assert!(!ssh.multiplexed);
+        assert_eq!(ssh.close_label(), "Close Connection");
+        let tunnel = ssh.tunnel_on(5432).unwrap();
+        assert_eq!(ssh.describe(tunnel), "tunnel to db:5432 via bastion");
+
+        let ssh = parse_ssh(
+            "/usr/bin/ssh -NL127.0.0.1:8443:[fd00::5]:443 deploy@gw.example.com -D 1081",
+            None,
+        )
+        .unwrap();
+        assert_eq!(ssh.host(), "gw.example.com");
+        assert_eq!(
+            ssh.describe(ssh.tunnel_on(8443).unwrap()),
+            "tunnel to [fd00::5]:443 via gw.example.com"
+        );
+        assert_eq!(
+            ssh.describe(ssh.tunnel_on(1081).unwrap()),
+            "SOCKS proxy via gw.example.com"
+        );
+
+        assert_eq!(parse_ssh("ssh bastion uptime", None), None);
+        assert_eq!(
+            parse_ssh("ssh -O cancel -L 5432:db:5432 bastion", None),
+            None
+        );
+        assert_eq!(parse_ssh("sshd: dev@pts/0", None), None);
+    }
+
+    #[test]
+    fn adds_forwards_from_the_matching_host_block() {
+        let ssh = parse_ssh("ssh -N bastion-eu", Some(CONFIG)).unwrap();
+        assert_eq!(ssh.tunnels.len(), 3);
+        assert!(ssh.multiplexed);
+        assert_eq!(
+            ssh.describe(ssh.tunnel_on(9200).unwrap()),
+            "tunnel to search.internal:9200 via bastion-eu"
+        );
+        assert_eq!(ssh.tunnel_on(7000), None);
+
+        assert_eq!(parse_ssh("ssh -N bastion-old", Some(CONFIG)), None);
+        // The command line wins over the config
+        let ssh = parse_ssh("ssh -o ControlMaster=no -N bastion", Some(CONFIG)).unwrap();
+        assert!(!ssh.multiplexed);
+    }
+
+    #[test]
+    fn cancels_through_the_same_control_socket() {
+        let ssh = parse_ssh(
+            "ssh -M -S /tmp/cm-bastion -p 2222 -fN -L 5432:db:5432 -L 6380:cache:6379 bastion",
+            None,
+        )
+        .unwrap();
+        assert!(ssh.multiplexed);
+        assert_eq!(ssh.close_label(), "Close Connection (2 tunnels)");
+        assert_eq!(
+            ssh.cancel_args(ssh.tunnel_on(6380).unwrap()),
+            [
+                "-S",
+                "/tmp/cm-bastion",
+                "-p",
+                "2222",
+                "-O",
+                "cancel",
+                "-L",
+                "6380:cache:6379",
+                "bastion"
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_host_patterns() {
+        assert!(glob("bastion-*", "bastion-eu"));
+        assert!(glob("db?.internal", "DB1.internal"));
+        assert!(!glob("bastion-*", "bastion"));
+        assert!(host_matches("* !prod", "dev"));
+        assert!(!host_matches("* !prod", "prod"));
+    }
+}
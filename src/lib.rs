@@ -1,14 +1,26 @@
 pub mod app;
 pub use app::run;
 
+pub mod cli;
 pub mod config;
+pub mod control;
+pub mod events;
+pub mod history;
+pub mod hooks;
 pub mod launch;
+pub mod metrics;
 pub mod model;
 pub mod update;
 pub mod utils;
 pub mod process {
+    pub mod details;
+    pub mod identity;
     pub mod kill;
+    pub mod monitor;
     pub mod ports;
+    pub mod procfs;
+    pub mod scanner;
+    pub mod tree;
 }
 pub mod ui {
     pub mod icon;
@@ -17,5 +29,14 @@ pub mod ui {
 pub mod integrations {
     pub mod brew;
     pub mod docker;
+    pub mod docker_api;
+    pub mod kubectl;
+    pub mod runtime;
+    pub mod ssh;
 }
 pub mod notify;
+pub mod respawn;
+pub mod restart;
+#[cfg(test)]
+mod test_util;
+pub mod undo;
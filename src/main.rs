@@ -1,4 +1,8 @@
 fn main() -> anyhow::Result<()> {
     env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = portkiller::cli::run(&args) {
+        std::process::exit(code);
+    }
     portkiller::run()
 }
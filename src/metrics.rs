@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::model::{KillOutcome, ProcessInfo, ProjectInfo, Protocol};
+
+// Upper bounds in seconds; a full lsof sweep usually lands in the 50-500ms range
+const SCAN_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+const KILL_OUTCOMES: [&str; 6] = [
+    "success",
+    "already_exited",
+    "permission_denied",
+    "timed_out",
+    "replaced",
+    "failed",
+];
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+struct Inner {
+    listeners: BTreeMap<(u16, Protocol), u64>,
+    projects: BTreeMap<String, u64>,
+    kills: BTreeMap<&'static str, u64>,
+    scan_buckets: [u64; SCAN_BUCKETS.len()],
+    scan_sum: f64,
+    scan_count: u64,
+    integrations: BTreeMap<&'static str, bool>,
+}
+
+/// Counters and gauges exposed on `/metrics` in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the listener gauges with the current scan result.
+    pub fn set_listeners(&self, processes: &[ProcessInfo], projects: &HashMap<i32, ProjectInfo>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.listeners.clear();
+        inner.projects.clear();
+        for process in processes {
+            *inner
+                .listeners
+                .entry((process.port, process.protocol))
+                .or_default() += 1;
+            if let Some(project) = projects.get(&process.pid) {
+                *inner.projects.entry(project.name.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn record_kill(&self, outcome: &KillOutcome) {
+        *self
+            .inner
+            .lock()
+            .unwrap()
+            .kills
+            .entry(outcome.label())
+            .or_default() += 1;
+    }
+
+    pub fn observe_scan(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let mut inner = self.inner.lock().unwrap();
+        for (idx, bound) in SCAN_BUCKETS.iter().enumerate() {
+            if secs <= *bound {
+                inner.scan_buckets[idx] += 1;
+            }
+        }
+        inner.scan_sum += secs;
+        inner.scan_count += 1;
+    }
+
+    /// Record whether the last query of an integration succeeded; `None`
+    /// when the integration is disabled and should not be reported.
+    pub fn set_integration_up(&self, integration: &'static str, up: Option<bool>) {
+        let mut inner = self.inner.lock().unwrap();
+        match up {
+            Some(up) => inner.integrations.insert(integration, up),
+            None => inner.integrations.remove(integration),
+        };
+    }
+
+    pub fn render(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "portkiller_listeners",
+            "gauge",
+            "Active listeners by port and protocol.",
+        );
+        for ((port, protocol), count) in &inner.listeners {
+            let proto = match protocol {
+                Protocol::Tcp => "tcp",
+                Protocol::Udp => "udp",
+            };
+            let _ = writeln!(
+                out,
+                "portkiller_listeners{{port=\"{}\",protocol=\"{}\"}} {}",
+                port, proto, count
+            );
+        }
+
+        header(
+            &mut out,
+            "portkiller_project_listeners",
+            "gauge",
+            "Active listeners by detected project.",
+        );
+        for (project, count) in &inner.projects {
+            let _ = writeln!(
+                out,
+                "portkiller_project_listeners{{project=\"{}\"}} {}",
+                escape_label(project),
+                count
+            );
+        }
+
+        header(
+            &mut out,
+            "portkiller_kills_total",
+            "counter",
+            "Kill attempts by outcome.",
+        );
+        for outcome in KILL_OUTCOMES {
+            let count = inner.kills.get(outcome).copied().unwrap_or(0);
+            let _ = writeln!(
+                out,
+                "portkiller_kills_total{{outcome=\"{}\"}} {}",
+                outcome, count
+            );
+        }
+
+        header(
+            &mut out,
+            "portkiller_scan_duration_seconds",
+            "histogram",
+            "Time spent scanning for listeners.",
+        );
+        for (bound, count) in SCAN_BUCKETS.iter().zip(inner.scan_buckets) {
+            let _ = writeln!(
+                out,
+                "portkiller_scan_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "portkiller_scan_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            inner.scan_count
+        );
+        let _ = writeln!(
+            out,
+            "portkiller_scan_duration_seconds_sum {}",
+            inner.scan_sum
+        );
+        let _ = writeln!(
+            out,
+            "portkiller_scan_duration_seconds_count {}",
+            inner.scan_count
+        );
+
+        header(
+            &mut out,
+            "portkiller_integration_up",
+            "gauge",
+            "Whether the last Docker or brew query succeeded.",
+        );
+        for (integration, up) in &inner.integrations {
+            let _ = writeln!(
+                out,
+                "portkiller_integration_up{{integration=\"{}\"}} {}",
+                integration,
+                u8::from(*up)
+            );
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serve `GET /metrics` on 127.0.0.1 only. Returns the bound address
+/// (useful with port 0).
+pub fn spawn_metrics_server(port: u16, metrics: Arc<Metrics>) -> Result<SocketAddr> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .with_context(|| format!("failed to bind metrics port {}", port))?;
+    let addr = listener.local_addr()?;
+    log::info!("Serving metrics on http://{}/metrics", addr);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = serve_request(stream, &metrics) {
+                        log::debug!("Metrics request failed: {}", err);
+                    }
+                }
+                Err(err) => log::warn!("Metrics accept failed: {}", err),
+            }
+        }
+    });
+    Ok(addr)
+}
+
+fn serve_request(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    // Only the request line matters; scrapers send small GETs, but a client
+    // may write it in pieces, so read until the end of the headers
+    let mut buf = [0u8; 1024];
+    let mut read = 0;
+    while read < buf.len() {
+        let n = stream.read(&mut buf[read..])?;
+        read += n;
+        if n == 0 || buf[..read].windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+    let request = String::from_utf8_lossy(&buf[..read]);
+    let mut parts = request.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            ("200 OK", "text/plain; version=0.0.4", metrics.render())
+        }
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::test_util::listener;
+
+    #[test]
+    fn renders_gauges_counters_and_histogram() {
+        let metrics = Metrics::new();
+        let mut projects = HashMap::new();
+        projects.insert(
+            1,
+            ProjectInfo {
+                name: "web \"app\"".into(),
+                path: PathBuf::from("/tmp/web"),
+            },
+        );
+        metrics.set_listeners(&[listener(3000, 1), listener(3000, 2)], &projects);
+        metrics.record_kill(&KillOutcome::TimedOut);
+        metrics.observe_scan(Duration::from_millis(30));
+        metrics.observe_scan(Duration::from_secs(10));
+        metrics.set_integration_up("docker", Some(false));
+
+        let out = metrics.render();
+        assert!(out.contains("portkiller_listeners{port=\"3000\",protocol=\"tcp\"} 2"));
+        assert!(out.contains("portkiller_project_listeners{project=\"web \\\"app\\\"\"} 1"));
+        assert!(out.contains("portkiller_kills_total{outcome=\"timed_out\"} 1"));
+        assert!(out.contains("portkiller_kills_total{outcome=\"success\"} 0"));
+        assert!(out.contains("portkiller_scan_duration_seconds_bucket{le=\"0.025\"} 0"));
+        assert!(out.contains("portkiller_scan_duration_seconds_bucket{le=\"0.05\"} 1"));
+        assert!(out.contains("portkiller_scan_duration_seconds_bucket{le=\"+Inf\"} 2"));
+        assert!(out.contains("portkiller_scan_duration_seconds_count 2"));
+        assert!(out.contains("portkiller_integration_up{integration=\"docker\"} 0"));
+        assert!(!out.contains("integration=\"brew\""));
+    }
+
+    #[test]
+    fn serves_metrics_over_http() {
+        let metrics = Arc::new(Metrics::new());
+        let addr = spawn_metrics_server(0, metrics).unwrap();
+
+        let fetch = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let ok = fetch("/metrics");
+        assert!(ok.starts_with("HTTP/1.1 200 OK"));
+        assert!(ok.contains("portkiller_kills_total"));
+        assert!(fetch("/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -1,35 +1,167 @@
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use nix::errno::Errno;
+use nix::sys::signal::Signal;
+use serde::{Deserialize, Serialize};
 
 use crate::update::UpdateInfo;
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
 pub struct ProcessInfo {
     pub port: u16,
+    pub protocol: Protocol,
+    pub address: IpAddr,
     pub pid: i32,
     pub command: String,
 }
 
+impl ProcessInfo {
+    /// Compact port label for menus: TCP ports are shown bare, others are suffixed.
+    pub fn port_label(&self) -> String {
+        self.protocol.port_label(self.port)
+    }
+
+    pub fn family(&self) -> AddressFamily {
+        match self.address {
+            IpAddr::V4(_) => AddressFamily::Ipv4,
+            IpAddr::V6(_) => AddressFamily::Ipv6,
+        }
+    }
+
+    /// Bound to 0.0.0.0 or ::, i.e. reachable from every network interface.
+    pub fn is_exposed(&self) -> bool {
+        self.address.is_unspecified()
+    }
+
+    /// Bind address and port, e.g. "127.0.0.1:5173" or "[::]:8000".
+    pub fn endpoint(&self) -> String {
+        match self.address {
+            IpAddr::V4(addr) => format!("{}:{}", addr, self.port),
+            IpAddr::V6(addr) => format!("[{}]:{}", addr, self.port),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl std::fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressFamily::Ipv4 => write!(f, "IPv4"),
+            AddressFamily::Ipv6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    pub fn port_label(self, port: u16) -> String {
+        match self {
+            Protocol::Tcp => port.to_string(),
+            Protocol::Udp => format!("{}/udp", port),
+        }
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "TCP"),
+            Protocol::Udp => write!(f, "UDP"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum UserEvent {
-    ProcessesUpdated(Vec<ProcessInfo>),
+    /// A changed scan, with details looked up on the monitor thread.
+    ProcessesUpdated(Vec<ProcessInfo>, ListenerDetails),
     MenuAction(MenuAction),
     KillFeedback(KillFeedback),
     MonitorError(String),
-    ConfigReloaded(crate::config::Config),
+    ConfigReloaded(Box<crate::config::Config>),
     ConfigReloadFailed(String),
     UpdateCheckResult(Option<UpdateInfo>),
+    /// The Docker daemon reported a container starting or stopping.
+    DockerChanged,
+    /// The worker re-queried the container runtimes after `DockerChanged`.
+    ContainersUpdated(HashMap<PublishedPort, DockerContainerInfo>),
+    /// The worker worked out what respawned a killed listener.
+    RespawnIdentified(crate::respawn::RespawnWarning),
+    /// A request from the control socket; the response goes back on the sender.
+    ControlRequest(
+        crate::control::ControlRequest,
+        crossbeam_channel::Sender<crate::control::ControlResponse>,
+    ),
 }
 
 #[derive(Clone, Debug)]
 pub enum MenuAction {
-    KillPid { pid: i32 },
+    /// `force` overrides a matching `protected` entry.
+    KillPid {
+        pid: i32,
+        force: bool,
+    },
+    /// Kill the process and all of its descendants.
+    KillTree {
+        pid: i32,
+        force: bool,
+    },
+    /// Stop the process that keeps respawning a killed listener.
+    KillSupervisor {
+        pid: i32,
+    },
+    DismissRespawn,
+    /// Start a container or brew service again shortly after stopping it.
+    UndoStop(crate::undo::StoppedService),
+    /// Relaunch a killed listener from its captured command line.
+    Restart {
+        port: u16,
+        protocol: Protocol,
+    },
+    /// Restart a `kubectl port-forward` with the same arguments.
+    ReestablishForward {
+        pid: i32,
+        force: bool,
+    },
+    /// Close one ssh tunnel through its ControlMaster's socket.
+    CancelSshTunnel {
+        pid: i32,
+        port: u16,
+        force: bool,
+    },
     KillAll,
-    DockerStop { container: String },
+    /// Containers are named per runtime, so both travel together.
+    DockerStop {
+        container: String,
+        runtime: crate::integrations::runtime::ContainerRuntime,
+        force: bool,
+    },
     DockerStopAll,
-    BrewStop { service: String },
+    /// Anything other than a plain stop, from the container's submenu or CLI.
+    DockerAction {
+        container: String,
+        runtime: crate::integrations::runtime::ContainerRuntime,
+        action: crate::integrations::docker::ContainerAction,
+    },
+    Compose(crate::integrations::docker::ComposeAction),
+    BrewStop {
+        service: String,
+        force: bool,
+    },
     BrewStopAll,
     EditConfig,
     ReloadConfig,
@@ -43,27 +175,122 @@ pub enum MenuAction {
 
 #[derive(Clone, Debug)]
 pub enum WorkerCommand {
-    KillPid(KillTarget),
-    KillAll(Vec<KillTarget>),
-    DockerStop { container: String },
-    BrewStop { service: String },
+    /// The launch is captured right before the kill, for Restart.
+    KillPid {
+        target: KillTarget,
+        launch: crate::restart::LaunchContext,
+    },
+    KillSupervisor(KillTarget),
+    Restart {
+        spec: crate::restart::LaunchSpec,
+        timeout: std::time::Duration,
+    },
+    /// Stop a port-forward and start it again; its command line is
+    /// captured on the worker right before the kill.
+    ReestablishForward {
+        target: KillTarget,
+        launch: crate::restart::LaunchContext,
+        timeout: std::time::Duration,
+    },
+    /// Find what respawned a listener; answers with `RespawnIdentified`.
+    IdentifySupervisor {
+        rebind: crate::respawn::Rebind,
+        owner: crate::respawn::PortOwner,
+    },
+    /// Re-query these runtimes; answers with `ContainersUpdated`.
+    RefreshContainers(Vec<crate::integrations::runtime::ContainerRuntime>),
+    /// Run `ssh` with arguments from `SshConnection::cancel_args`.
+    CancelSshTunnel {
+        args: Vec<String>,
+        label: String,
+    },
+    KillAll {
+        targets: Vec<KillTarget>,
+        launch: crate::restart::LaunchContext,
+    },
+    UndoStop(crate::undo::StoppedService),
+    DockerAction {
+        container: String,
+        runtime: crate::integrations::runtime::ContainerRuntime,
+        action: crate::integrations::docker::ContainerAction,
+    },
+    Compose {
+        action: crate::integrations::docker::ComposeAction,
+        runtime: crate::integrations::runtime::ContainerRuntime,
+    },
+    BrewStop {
+        service: String,
+    },
 }
 
 #[derive(Clone, Debug)]
 pub struct KillTarget {
     pub pid: i32,
     pub label: String,
+    pub identity: ProcessIdentity,
+    pub ladder: Vec<KillStep>,
+    /// Descendants to stop after this process (tree kill), parents first.
+    pub children: Vec<KillTarget>,
+    /// Fill `children` on the worker right before the kill.
+    pub tree: bool,
+    pub hooks: KillHooks,
 }
 
-#[derive(Clone, Debug)]
+/// Hooks resolved for one kill and the environment they run with.
+#[derive(Clone, Debug, Default)]
+pub struct KillHooks {
+    pub pre: Vec<crate::config::KillHook>,
+    pub post: Vec<crate::config::KillHook>,
+    pub env: Vec<(String, String)>,
+}
+
+/// A descendant of a listening process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChildProcess {
+    pub pid: i32,
+    pub command: String,
+}
+
+/// One rung of the signal ladder: send `signal`, wait up to `timeout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KillStep {
+    pub signal: Signal,
+    pub timeout: Duration,
+}
+
+/// What makes a PID unambiguous at kill time: the start time of the process
+/// the user saw, plus on Linux a pidfd pinned to that exact process.
+#[derive(Clone, Debug, Default)]
+pub struct ProcessIdentity {
+    pub start_time: Option<u64>,
+    #[cfg(target_os = "linux")]
+    pub pidfd: Option<std::sync::Arc<std::os::fd::OwnedFd>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct KillFeedback {
     pub message: String,
     pub severity: FeedbackSeverity,
+    /// PIDs the action stopped, so the next scan can spot respawns.
+    #[serde(skip)]
+    pub stopped_pids: Vec<i32>,
+    /// A container or brew service the action stopped, offered for undo.
+    #[serde(skip)]
+    pub undoable: Option<crate::undo::StoppedService>,
+    /// How the stopped listeners were started, offered under Restart.
+    #[serde(skip)]
+    pub launches: Vec<crate::restart::LaunchSpec>,
 }
 
 impl KillFeedback {
     pub fn new(message: String, severity: FeedbackSeverity) -> Self {
-        Self { message, severity }
+        Self {
+            message,
+            severity,
+            stopped_pids: Vec::new(),
+            undoable: None,
+            launches: Vec::new(),
+        }
     }
 
     pub fn info(message: String) -> Self {
@@ -79,43 +306,282 @@ impl KillFeedback {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FeedbackSeverity {
     Info,
     Warning,
     Error,
 }
 
+/// Per-PID lookups for a scan's listeners, made off the event loop.
+#[derive(Clone, Debug, Default)]
+pub struct ListenerDetails {
+    pub details: HashMap<i32, ProcessDetails>,
+    pub children: HashMap<i32, Vec<ChildProcess>>,
+    pub ssh_connections: HashMap<i32, Option<crate::integrations::ssh::SshConnection>>,
+}
+
+/// The listeners on one port, split by how each is stopped.
+#[derive(Debug, Default)]
+pub struct PortOwners<'a> {
+    pub containers: Vec<&'a DockerContainerInfo>,
+    pub brew_services: Vec<String>,
+    pub pids: Vec<i32>,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct AppState {
     pub processes: Vec<ProcessInfo>,
     pub last_feedback: Option<KillFeedback>,
     pub config: crate::config::Config,
     pub project_cache: HashMap<i32, ProjectInfo>,
-    pub docker_port_map: HashMap<u16, DockerContainerInfo>,
+    pub process_details: HashMap<i32, ProcessDetails>,
+    pub process_children: HashMap<i32, Vec<ChildProcess>>,
+    /// Parsed once per ssh PID, since that reads its config file.
+    pub ssh_connections: HashMap<i32, Option<crate::integrations::ssh::SshConnection>>,
+    pub docker_port_map: HashMap<PublishedPort, DockerContainerInfo>,
     pub brew_services_map: HashMap<String, String>, // service_name -> status
     pub available_update: Option<UpdateInfo>,
+    /// Latest listener starts and stops, oldest first.
+    pub recent_history: Vec<crate::history::HistoryRecord>,
+    /// A listener came straight back after we killed it.
+    pub respawn_warning: Option<crate::respawn::RespawnWarning>,
+    /// Further respawns, e.g. after a Kill All, shown once the current
+    /// warning is dismissed.
+    pub pending_respawns: Vec<crate::respawn::RespawnWarning>,
+    /// Launches captured from killed listeners, newest first.
+    pub restartable: Vec<crate::restart::LaunchSpec>,
+    /// Recent container and brew stops that can still be undone.
+    pub undo: crate::undo::UndoBuffer,
+}
+
+impl AppState {
+    /// Show a respawn warning, or queue it behind the one on screen.
+    pub fn push_respawn_warning(&mut self, warning: crate::respawn::RespawnWarning) {
+        if self.respawn_warning.is_none() {
+            self.respawn_warning = Some(warning);
+        } else {
+            self.pending_respawns.push(warning);
+        }
+    }
+
+    /// Replace the current respawn warning with the next queued one whose
+    /// listener is still up.
+    pub fn next_respawn_warning(&mut self) {
+        let processes = &self.processes;
+        self.pending_respawns
+            .retain(|w| processes.iter().any(|p| p.pid == w.rebind.new_pid));
+        self.respawn_warning =
+            (!self.pending_respawns.is_empty()).then(|| self.pending_respawns.remove(0));
+    }
+
+    /// Take in details looked up for the current listeners. Stopped PIDs
+    /// keep their last details until cleanup so notifications can use them.
+    pub fn apply_details(&mut self, details: ListenerDetails) {
+        self.process_details.extend(details.details);
+        self.process_children = details.children;
+        self.ssh_connections = details.ssh_connections;
+    }
+
+    /// Whether a listener matches one of the config's `protected` entries.
+    pub fn is_protected(&self, process: &ProcessInfo) -> bool {
+        let args = self
+            .process_details
+            .get(&process.pid)
+            .map(|d| d.args.as_str());
+        let project = self
+            .project_cache
+            .get(&process.pid)
+            .map(|p| p.name.as_str());
+        let container = self.container_for(process).map(|c| c.name.as_str());
+        self.config
+            .protected
+            .iter()
+            .any(|rule| rule.matches(process, args, project, container))
+    }
+
+    /// The container publishing the listener's port: the binding with the
+    /// same address if there is one, else a wildcard binding of the port and
+    /// protocol (the runtime's proxy may listen on a different address).
+    /// Two specific addresses never match, so a host process on
+    /// 127.0.0.1:8080 is not taken for a container on 127.0.0.2:8080.
+    pub fn container_for(&self, process: &ProcessInfo) -> Option<&DockerContainerInfo> {
+        let exact = PublishedPort {
+            ip: process.address,
+            port: process.port,
+            protocol: process.protocol,
+        };
+        self.docker_port_map.get(&exact).or_else(|| {
+            self.docker_port_map
+                .iter()
+                .filter(|(key, _)| {
+                    key.port == process.port
+                        && key.protocol == process.protocol
+                        && (key.ip.is_unspecified() || process.address.is_unspecified())
+                })
+                .min_by_key(|(key, _)| **key)
+                .map(|(_, c)| c)
+        })
+    }
+
+    /// What owns each listener on `port`, for stopping the port: containers
+    /// and brew services are stopped through their tools, the rest by PID.
+    pub fn owners_on(&self, port: u16) -> PortOwners<'_> {
+        let mut owners = PortOwners::default();
+        for process in self.processes.iter().filter(|p| p.port == port) {
+            if let Some(container) = self.container_for(process) {
+                if !owners
+                    .containers
+                    .iter()
+                    .any(|c| c.runtime == container.runtime && c.name == container.name)
+                {
+                    owners.containers.push(container);
+                }
+            } else if let Some(service) = crate::integrations::brew::get_brew_managed_service(
+                &process.command,
+                process.port,
+                &self.brew_services_map,
+            ) {
+                if !owners.brew_services.contains(&service) {
+                    owners.brew_services.push(service);
+                }
+            } else if !owners.pids.contains(&process.pid) {
+                owners.pids.push(process.pid);
+            }
+        }
+        owners.pids.sort();
+        owners
+    }
+
+    /// Killing a PID takes down every port it holds, so one protected port
+    /// protects the whole process.
+    pub fn is_pid_protected(&self, pid: i32) -> bool {
+        self.processes
+            .iter()
+            .any(|p| p.pid == pid && self.is_protected(p))
+    }
+
+    pub fn is_container_protected(&self, container: &str) -> bool {
+        self.processes.iter().any(|p| {
+            self.container_for(p).is_some_and(|c| c.name == container) && self.is_protected(p)
+        })
+    }
+
+    /// Runtimes running a container by this name; usually one, but Docker
+    /// and Podman may both run the same compose project.
+    pub fn container_runtimes(
+        &self,
+        container: &str,
+    ) -> Vec<crate::integrations::runtime::ContainerRuntime> {
+        let mut runtimes: Vec<_> = self
+            .docker_port_map
+            .values()
+            .filter(|c| c.name == container)
+            .map(|c| c.runtime)
+            .collect();
+        runtimes.sort();
+        runtimes.dedup();
+        runtimes
+    }
+
+    pub fn compose_runtime(&self, project: &str) -> crate::integrations::runtime::ContainerRuntime {
+        self.docker_port_map
+            .values()
+            .find(|c| c.project.as_deref() == Some(project))
+            .map(|c| c.runtime)
+            .unwrap_or_default()
+    }
+
+    /// Whether a protected container belongs to the compose project, or to
+    /// one service of it when `service` is given.
+    pub fn is_compose_protected(&self, project: &str, service: Option<&str>) -> bool {
+        self.processes.iter().any(|p| {
+            self.container_for(p).is_some_and(|c| {
+                c.project.as_deref() == Some(project)
+                    && service.is_none_or(|s| c.service.as_deref() == Some(s))
+            }) && self.is_protected(p)
+        })
+    }
+
+    pub fn is_brew_service_protected(&self, service: &str) -> bool {
+        self.processes.iter().any(|p| {
+            crate::integrations::brew::get_brew_managed_service(
+                &p.command,
+                p.port,
+                &self.brew_services_map,
+            )
+            .is_some_and(|s| s == service)
+                && self.is_protected(p)
+        })
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub enum KillOutcome {
-    Success,
+    /// Exited after the ladder step at index `step` (0-based) sent `signal`.
+    Success {
+        step: usize,
+        signal: Signal,
+    },
     AlreadyExited,
     PermissionDenied,
     TimedOut,
+    /// The PID now belongs to a different process; nothing was signalled.
+    Replaced,
     Failed(Errno),
 }
 
-#[derive(Clone, Debug)]
+impl KillOutcome {
+    /// Stable snake_case name for metrics and hook environments.
+    pub fn label(&self) -> &'static str {
+        match self {
+            KillOutcome::Success { .. } => "success",
+            KillOutcome::AlreadyExited => "already_exited",
+            KillOutcome::PermissionDenied => "permission_denied",
+            KillOutcome::TimedOut => "timed_out",
+            KillOutcome::Replaced => "replaced",
+            KillOutcome::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct ProjectInfo {
     pub name: String,
-    #[allow(dead_code)]
     pub path: PathBuf,
 }
 
-#[derive(Clone, Debug)]
+/// Per-process metadata that is too volatile to live in `ProcessInfo`
+/// (CPU and memory change every scan and would defeat change detection).
+#[derive(Clone, Debug, Serialize)]
+pub struct ProcessDetails {
+    pub ppid: i32,
+    pub user: String,
+    /// Full command line, arguments joined by spaces.
+    pub args: String,
+    /// Start time in seconds since the Unix epoch.
+    pub start_time: u64,
+    pub rss_kb: u64,
+    pub cpu_percent: f32,
+}
+
+/// A host binding published by a container, e.g. 0.0.0.0:5432/tcp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct PublishedPort {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub protocol: Protocol,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct DockerContainerInfo {
     pub name: String,
     #[allow(dead_code)]
     pub id: String,
+    /// From the `com.docker.compose.project` label.
+    pub project: Option<String>,
+    /// From the `com.docker.compose.service` label.
+    pub service: Option<String>,
+    pub runtime: crate::integrations::runtime::ContainerRuntime,
 }
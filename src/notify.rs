@@ -1,7 +1,8 @@
 use std::collections::HashSet;
 use std::process::Command;
 
-use crate::model::{AppState, ProcessInfo};
+use crate::model::{AppState, ProcessInfo, Protocol};
+use crate::process::details::describe_command;
 use crate::utils::find_command;
 
 const BUNDLE_ID: &str = "com.samarthgupta.portkiller";
@@ -11,47 +12,56 @@ pub fn maybe_notify_changes(state: &AppState, prev: &[ProcessInfo]) {
         return;
     }
 
-    let prev_ports: HashSet<u16> = prev.iter().map(|p| p.port).collect();
-    let curr_ports: HashSet<u16> = state.processes.iter().map(|p| p.port).collect();
+    let prev_ports: HashSet<(u16, Protocol)> = prev.iter().map(|p| (p.port, p.protocol)).collect();
+    let curr_ports: HashSet<(u16, Protocol)> = state
+        .processes
+        .iter()
+        .map(|p| (p.port, p.protocol))
+        .collect();
 
     // Notify for added ports
-    let added: Vec<u16> = curr_ports.difference(&prev_ports).copied().collect();
-    for port in added {
-        if let Some(process) = state.processes.iter().find(|p| p.port == port) {
-            let (title, body) = format_notification(port, process, state, true);
+    let added: Vec<(u16, Protocol)> = curr_ports.difference(&prev_ports).copied().collect();
+    for key in added {
+        if let Some(process) = state.processes.iter().find(|p| (p.port, p.protocol) == key) {
+            let (title, body) = format_notification(process, state, true);
             notify(&title, &body);
         }
     }
 
     // Notify for removed ports
-    let removed: Vec<u16> = prev_ports.difference(&curr_ports).copied().collect();
-    for port in removed {
-        if let Some(process) = prev.iter().find(|p| p.port == port) {
-            let (title, body) = format_notification(port, process, state, false);
+    let removed: Vec<(u16, Protocol)> = prev_ports.difference(&curr_ports).copied().collect();
+    for key in removed {
+        if let Some(process) = prev.iter().find(|p| (p.port, p.protocol) == key) {
+            let (title, body) = format_notification(process, state, false);
             notify(&title, &body);
         }
     }
 }
 
 fn format_notification(
-    port: u16,
     process: &ProcessInfo,
     state: &AppState,
     is_start: bool,
 ) -> (String, String) {
     let title = if is_start {
-        format!("Port {} Started", port)
+        format!("{} Port {} Started", process.protocol, process.port)
     } else {
-        format!("Port {} Stopped", port)
+        format!("{} Port {} Stopped", process.protocol, process.port)
     };
 
-    let command = truncate_command(&process.command, 40);
+    let command = truncate_command(
+        &describe_command(&process.command, state.process_details.get(&process.pid)),
+        40,
+    );
 
-    let body = if let Some(project) = state.project_cache.get(&process.pid) {
+    let mut body = if let Some(project) = state.project_cache.get(&process.pid) {
         format!("{} ({}) • {}", command, process.pid, project.name)
     } else {
         format!("{} ({})", command, process.pid)
     };
+    if is_start && process.is_exposed() {
+        body.push_str(" • all interfaces");
+    }
 
     (title, body)
 }
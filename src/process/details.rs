@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::model::ProcessDetails;
+
+const MAX_DISPLAY_LEN: usize = 40;
+
+/// Collect argv, owner, parent, start time and resource usage for the given
+/// PIDs with a single `ps` call. PIDs that vanished are simply missing.
+pub fn query_process_details(pids: &[i32]) -> HashMap<i32, ProcessDetails> {
+    if pids.is_empty() {
+        return HashMap::new();
+    }
+    let pid_list = pids
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let out = Command::new("ps")
+        .args([
+            "-o",
+            "pid=,ppid=,user=,rss=,%cpu=,etime=,args=",
+            "-p",
+            &pid_list,
+        ])
+        // Keep decimal separators predictable for %cpu
+        .env("LC_ALL", "C")
+        .output();
+    match out {
+        // ps exits non-zero when some PIDs are gone but still prints the rest
+        Ok(out) => parse_ps_output(&String::from_utf8_lossy(&out.stdout), unix_now()),
+        Err(err) => {
+            log::warn!("ps command failed: {}", err);
+            HashMap::new()
+        }
+    }
+}
+
+/// Short human label for a process: the program name plus its first few
+/// arguments with paths reduced to basenames, e.g. "node next dev".
+pub fn describe_command(command: &str, details: Option<&ProcessDetails>) -> String {
+    let Some(details) = details.filter(|d| !d.args.trim().is_empty()) else {
+        return command.to_string();
+    };
+    let mut label = String::new();
+    for token in details.args.split_whitespace() {
+        let part = token.rsplit('/').next().unwrap_or(token);
+        if part.is_empty() {
+            continue;
+        }
+        if !label.is_empty() && label.len() + part.len() + 1 > MAX_DISPLAY_LEN {
+            break;
+        }
+        if !label.is_empty() {
+            label.push(' ');
+        }
+        label.push_str(part);
+    }
+    if label.is_empty() {
+        command.to_string()
+    } else {
+        label
+    }
+}
+
+fn parse_ps_output(output: &str, now: u64) -> HashMap<i32, ProcessDetails> {
+    let mut map = HashMap::new();
+    for line in output.lines() {
+        let mut rest = line.trim_start();
+        let mut fields = Vec::with_capacity(6);
+        for _ in 0..6 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+        let (Ok(pid), Ok(ppid), Ok(rss_kb), Ok(cpu_percent), Some(elapsed)) = (
+            fields[0].parse::<i32>(),
+            fields[1].parse::<i32>(),
+            fields[3].parse::<u64>(),
+            fields[4].parse::<f32>(),
+            parse_etime(fields[5]),
+        ) else {
+            continue;
+        };
+        map.insert(
+            pid,
+            ProcessDetails {
+                ppid,
+                user: fields[2].to_string(),
+                args: rest.trim_end().to_string(),
+                start_time: now.saturating_sub(elapsed),
+                rss_kb,
+                cpu_percent,
+            },
+        );
+    }
+    map
+}
+
+// Parse ps elapsed time: "[[dd-]hh:]mm:ss".
+fn parse_etime(value: &str) -> Option<u64> {
+    let (days, clock) = match value.split_once('-') {
+        Some((d, rest)) => (d.parse::<u64>().ok()?, rest),
+        None => (0, value),
+    };
+    let mut secs = 0u64;
+    for part in clock.split(':') {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    Some(days * 86_400 + secs)
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_etime_formats() {
+        assert_eq!(parse_etime("00:05"), Some(5));
+        assert_eq!(parse_etime("12:34"), Some(754));
+        assert_eq!(parse_etime("01:00:00"), Some(3600));
+        assert_eq!(parse_etime("2-03:00:01"), Some(2 * 86_400 + 3 * 3600 + 1));
+        assert_eq!(parse_etime("garbage"), None);
+    }
+
+    #[test]
+    fn parses_ps_rows_with_spaced_arguments() {
+        let output = "  4242     1 dev         120340   3.5    01:40 /usr/local/bin/node /Users/dev/web/node_modules/.bin/next dev --port 3000\n  999   4242 root            12   0.0 00:01 \nbogus line\n";
+        let map = parse_ps_output(output, 1_000);
+        assert_eq!(map.len(), 2);
+        let node = &map[&4242];
+        assert_eq!(node.ppid, 1);
+        assert_eq!(node.user, "dev");
+        assert_eq!(node.rss_kb, 120_340);
+        assert_eq!(node.start_time, 900);
+        assert!((node.cpu_percent - 3.5).abs() < f32::EPSILON);
+        assert!(node.args.ends_with("next dev --port 3000"));
+        assert_eq!(map[&999].args, "");
+    }
+
+    #[test]
+    fn describes_commands_from_argv() {
+        let details = ProcessDetails {
+            ppid: 1,
+            user: "dev".into(),
+            args: "/usr/local/bin/node /Users/dev/web/node_modules/.bin/next dev".into(),
+            start_time: 0,
+            rss_kb: 0,
+            cpu_percent: 0.0,
+        };
+        assert_eq!(describe_command("node", Some(&details)), "node next dev");
+        assert_eq!(describe_command("node", None), "node");
+    }
+}
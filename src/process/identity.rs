@@ -0,0 +1,182 @@
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+
+use nix::errno::Errno;
+use nix::sys::signal::{Signal, kill};
+use nix::unistd::Pid;
+
+use crate::model::ProcessIdentity;
+use crate::process::details::query_process_details;
+#[cfg(target_os = "linux")]
+use crate::process::procfs;
+
+// ps reports elapsed time with one-second resolution, and the start time is
+// derived from "now", so two readings of the same process can differ slightly.
+const START_TIME_TOLERANCE_SECS: u64 = 2;
+
+/// Result of comparing a PID against the identity captured earlier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdentityCheck {
+    /// The PID still refers to the captured process.
+    Same,
+    /// The captured process exited and the PID now belongs to another process.
+    Replaced,
+    /// Nothing runs under the PID anymore.
+    Gone,
+}
+
+/// Capture the identity of `pid` as the user saw it. `known_start` is the start
+/// time from the scan that produced the menu; when absent it is queried now.
+/// On Linux a pidfd is opened and kept only if it provably refers to that process.
+pub fn capture_identity(pid: i32, known_start: Option<u64>) -> ProcessIdentity {
+    #[cfg(target_os = "linux")]
+    {
+        // Validate after opening: if the start time still matches, the pidfd
+        // was opened on the original process and is immune to PID reuse.
+        let pidfd = open_pidfd(pid);
+        let current = current_start_time(pid);
+        let start_time = known_start.or(current);
+        let pidfd = pidfd.filter(|_| {
+            matches!(
+                (start_time, current),
+                (Some(expected), Some(actual)) if start_times_match(expected, actual)
+            )
+        });
+        ProcessIdentity {
+            start_time,
+            pidfd: pidfd.map(Arc::new),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    ProcessIdentity {
+        start_time: known_start.or_else(|| current_start_time(pid)),
+    }
+}
+
+/// Compare the process currently running as `pid` with the captured identity.
+pub fn check_identity(pid: i32, identity: &ProcessIdentity) -> IdentityCheck {
+    let pid_alive = !matches!(kill(Pid::from_raw(pid), None), Err(Errno::ESRCH));
+
+    #[cfg(target_os = "linux")]
+    if let Some(fd) = identity.pidfd.as_deref() {
+        return match pidfd_send_signal(fd, None) {
+            Err(Errno::ESRCH) if pid_alive => IdentityCheck::Replaced,
+            Err(Errno::ESRCH) => IdentityCheck::Gone,
+            _ => IdentityCheck::Same,
+        };
+    }
+
+    if !pid_alive {
+        return IdentityCheck::Gone;
+    }
+    match (identity.start_time, current_start_time(pid)) {
+        (Some(expected), Some(actual)) if !start_times_match(expected, actual) => {
+            IdentityCheck::Replaced
+        }
+        // The process can vanish between kill(0) and ps
+        (Some(_), None) => IdentityCheck::Gone,
+        _ => IdentityCheck::Same,
+    }
+}
+
+/// Deliver `signal` (or probe with `None`) to the captured process only.
+/// Returns ESRCH when the original process is gone, even if the PID was reused.
+pub fn signal_identity(
+    pid: i32,
+    identity: &ProcessIdentity,
+    signal: Option<Signal>,
+) -> Result<(), Errno> {
+    #[cfg(target_os = "linux")]
+    if let Some(fd) = identity.pidfd.as_deref() {
+        return pidfd_send_signal(fd, signal);
+    }
+
+    match check_identity(pid, identity) {
+        IdentityCheck::Same => kill(Pid::from_raw(pid), signal),
+        IdentityCheck::Replaced | IdentityCheck::Gone => Err(Errno::ESRCH),
+    }
+}
+
+fn current_start_time(pid: i32) -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    if procfs::is_available() {
+        return procfs::process_start_time(pid);
+    }
+    query_process_details(&[pid])
+        .get(&pid)
+        .map(|d| d.start_time)
+}
+
+fn start_times_match(expected: u64, actual: u64) -> bool {
+    expected.abs_diff(actual) <= START_TIME_TOLERANCE_SECS
+}
+
+#[cfg(target_os = "linux")]
+fn open_pidfd(pid: i32) -> Option<OwnedFd> {
+    // SAFETY: pidfd_open takes a pid and flags and returns a new fd or -1.
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+    if fd < 0 {
+        // ENOSYS on pre-5.3 kernels; callers fall back to start-time checks
+        return None;
+    }
+    // SAFETY: the kernel just handed us ownership of this descriptor.
+    Some(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
+
+#[cfg(target_os = "linux")]
+fn pidfd_send_signal(fd: &OwnedFd, signal: Option<Signal>) -> Result<(), Errno> {
+    let signum = signal.map(|s| s as i32).unwrap_or(0);
+    // SAFETY: fd is a valid pidfd; a null siginfo with zero flags mirrors kill(2).
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            fd.as_raw_fd(),
+            signum,
+            std::ptr::null::<libc::siginfo_t>(),
+            0,
+        )
+    };
+    if res < 0 { Err(Errno::last()) } else { Ok(()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn own_pid() -> i32 {
+        std::process::id() as i32
+    }
+
+    #[test]
+    fn own_process_matches_captured_identity() {
+        let identity = capture_identity(own_pid(), None);
+        assert!(identity.start_time.is_some());
+        assert_eq!(check_identity(own_pid(), &identity), IdentityCheck::Same);
+        assert!(signal_identity(own_pid(), &identity, None).is_ok());
+    }
+
+    #[test]
+    fn mismatched_start_time_is_reported_as_replaced() {
+        let identity = ProcessIdentity {
+            start_time: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            check_identity(own_pid(), &identity),
+            IdentityCheck::Replaced
+        );
+        assert_eq!(
+            signal_identity(own_pid(), &identity, None),
+            Err(Errno::ESRCH)
+        );
+    }
+
+    #[test]
+    fn start_time_tolerance() {
+        assert!(start_times_match(1_000, 1_002));
+        assert!(!start_times_match(1_000, 1_010));
+    }
+}
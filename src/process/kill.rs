@@ -2,86 +2,155 @@ use std::thread;
 use std::time::Duration;
 
 use nix::errno::Errno;
-use nix::sys::signal::{Signal, kill};
-use nix::unistd::Pid;
 
-use crate::model::KillOutcome;
-use crate::process::ports::verify_pid_is_listener;
+use crate::model::{KillOutcome, KillTarget, ProcessIdentity};
+use crate::process::identity::{IdentityCheck, check_identity, signal_identity};
+use crate::process::scanner::PortScanner;
 
-const SIGTERM_GRACE: Duration = Duration::from_secs(2);
-const SIGKILL_GRACE: Duration = Duration::from_secs(1);
 const POLL_STEP: Duration = Duration::from_millis(200);
 
-pub fn terminate_pid(pid_raw: i32) -> KillOutcome {
-    let pid = Pid::from_raw(pid_raw);
+/// What happened to the descendants during a tree kill.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChildSummary {
+    pub stopped: usize,
+    pub survived: usize,
+}
+
+/// Kill the target, then any of its recorded descendants that outlived it.
+/// The parent goes first so supervisors (gunicorn, npm) cannot respawn
+/// workers we have just stopped.
+pub fn terminate_tree(
+    target: &KillTarget,
+    scanner: &dyn PortScanner,
+) -> (KillOutcome, ChildSummary) {
+    let outcome = terminate_pid(target, scanner);
+    (outcome, stop_children(target, outcome))
+}
+
+/// Stop a supervisor that keeps respawning a listener, then whatever it
+/// left running. Unlike `terminate_tree` the target need not listen itself.
+pub fn terminate_supervisor(target: &KillTarget) -> (KillOutcome, ChildSummary) {
+    let outcome = match check_identity(target.pid, &target.identity) {
+        IdentityCheck::Same => run_ladder(target),
+        IdentityCheck::Gone => KillOutcome::AlreadyExited,
+        IdentityCheck::Replaced => KillOutcome::Replaced,
+    };
+    (outcome, stop_children(target, outcome))
+}
+
+fn stop_children(target: &KillTarget, outcome: KillOutcome) -> ChildSummary {
+    let mut summary = ChildSummary::default();
+    if !matches!(
+        outcome,
+        KillOutcome::Success { .. } | KillOutcome::AlreadyExited
+    ) {
+        return summary;
+    }
 
-    // Check if process exists
-    match kill(pid, None) {
-        Err(Errno::ESRCH) => return KillOutcome::AlreadyExited,
-        Err(err) => return KillOutcome::Failed(err),
-        Ok(()) => {}
+    for child in &target.children {
+        let stopped = match check_identity(child.pid, &child.identity) {
+            // Exited with its parent, or long gone and the PID was recycled
+            IdentityCheck::Gone | IdentityCheck::Replaced => true,
+            IdentityCheck::Same => matches!(
+                run_ladder(child),
+                KillOutcome::Success { .. } | KillOutcome::AlreadyExited
+            ),
+        };
+        if stopped {
+            summary.stopped += 1;
+        } else {
+            summary.survived += 1;
+        }
+    }
+    summary
+}
+
+/// Walk the target's signal ladder until the process exits or the ladder
+/// runs out.
+pub fn terminate_pid(target: &KillTarget, scanner: &dyn PortScanner) -> KillOutcome {
+    let pid_raw = target.pid;
+    let identity = &target.identity;
+
+    // Check the PID still refers to the process the user picked
+    match check_identity(pid_raw, identity) {
+        IdentityCheck::Same => {}
+        IdentityCheck::Gone => return KillOutcome::AlreadyExited,
+        IdentityCheck::Replaced => {
+            log::warn!(
+                "PID {} now belongs to a different process, refusing to signal it",
+                pid_raw
+            );
+            return KillOutcome::Replaced;
+        }
     }
 
-    // TOCTOU mitigation: verify PID is still a TCP listener before killing
-    // This reduces (but doesn't eliminate) the risk of killing a reused PID
-    if !verify_pid_is_listener(pid_raw) {
+    // TOCTOU mitigation: verify PID is still a listener before killing
+    if !scanner.is_listener(pid_raw) {
         log::warn!(
-            "PID {} is no longer a TCP listener, skipping kill to avoid TOCTOU race",
+            "PID {} is no longer a listener, skipping kill to avoid TOCTOU race",
             pid_raw
         );
         return KillOutcome::AlreadyExited;
     }
 
-    let mut last_perm_denied = false;
-
-    // Send SIGTERM to the specific PID only (not process group)
-    match kill(pid, Signal::SIGTERM) {
-        Ok(()) => {}
-        Err(Errno::ESRCH) => return KillOutcome::AlreadyExited,
-        Err(Errno::EPERM) => last_perm_denied = true,
-        Err(err) => return KillOutcome::Failed(err),
-    }
+    run_ladder(target)
+}
 
-    // Wait for graceful shutdown
-    match wait_for_exit(pid, SIGTERM_GRACE) {
-        Ok(true) => return KillOutcome::Success,
-        Ok(false) => {}
-        Err(err) => return KillOutcome::Failed(err),
-    }
+fn run_ladder(target: &KillTarget) -> KillOutcome {
+    let pid_raw = target.pid;
+    let identity = &target.identity;
+    let mut last_perm_denied = false;
 
-    // Force kill if still running
-    match kill(pid, Signal::SIGKILL) {
-        Ok(()) => {}
-        Err(Errno::ESRCH) => return KillOutcome::Success,
-        Err(Errno::EPERM) => last_perm_denied = true,
-        Err(err) => return KillOutcome::Failed(err),
-    }
+    for (idx, step) in target.ladder.iter().enumerate() {
+        // Signal the specific PID only (not process group).
+        // signal_identity refuses to deliver if the PID was recycled meanwhile.
+        match signal_identity(pid_raw, identity, Some(step.signal)) {
+            Ok(()) => {}
+            Err(Errno::ESRCH) if idx == 0 => return KillOutcome::AlreadyExited,
+            // Exited right after the previous step's grace period ran out
+            Err(Errno::ESRCH) => {
+                return KillOutcome::Success {
+                    step: idx - 1,
+                    signal: target.ladder[idx - 1].signal,
+                };
+            }
+            Err(Errno::EPERM) => last_perm_denied = true,
+            Err(err) => return KillOutcome::Failed(err),
+        }
 
-    match wait_for_exit(pid, SIGKILL_GRACE) {
-        Ok(true) => KillOutcome::Success,
-        Ok(false) => {
-            if last_perm_denied {
-                KillOutcome::PermissionDenied
-            } else {
-                KillOutcome::TimedOut
+        match wait_for_exit(pid_raw, identity, step.timeout) {
+            Ok(true) => {
+                return KillOutcome::Success {
+                    step: idx,
+                    signal: step.signal,
+                };
             }
+            Ok(false) => {}
+            Err(err) => return KillOutcome::Failed(err),
         }
-        Err(err) => KillOutcome::Failed(err),
+    }
+
+    if last_perm_denied {
+        KillOutcome::PermissionDenied
+    } else {
+        KillOutcome::TimedOut
     }
 }
 
-fn wait_for_exit(pid: Pid, timeout: Duration) -> Result<bool, Errno> {
+fn wait_for_exit(pid: i32, identity: &ProcessIdentity, timeout: Duration) -> Result<bool, Errno> {
     let deadline = std::time::Instant::now() + timeout;
     loop {
-        match kill(pid, None) {
+        // A recycled PID counts as exited: the original process is gone
+        match signal_identity(pid, identity, None) {
             Err(Errno::ESRCH) => return Ok(true),
             Err(err) => return Err(err),
             Ok(()) => {}
         }
 
-        if std::time::Instant::now() >= deadline {
+        let now = std::time::Instant::now();
+        if now >= deadline {
             return Ok(false);
         }
-        thread::sleep(POLL_STEP);
+        thread::sleep(POLL_STEP.min(deadline - now));
     }
 }
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::model::ProcessInfo;
+use crate::process::scanner::{PortScanner, ScanOptions};
+
+/// How long without changes before the monitor switches to the idle interval.
+pub const IDLE_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Result of a single monitor poll.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MonitorPoll {
+    /// The listener set differs from the previous poll.
+    Changed(Vec<ProcessInfo>),
+    /// Nothing changed since the previous poll.
+    Unchanged,
+}
+
+/// Change detection over successive scans, independent of the event loop.
+pub struct ListenerMonitor {
+    scanner: Arc<dyn PortScanner>,
+    previous: Vec<ProcessInfo>,
+    last_change: Instant,
+}
+
+impl ListenerMonitor {
+    pub fn new(scanner: Arc<dyn PortScanner>) -> Self {
+        Self {
+            scanner,
+            previous: Vec::new(),
+            last_change: Instant::now(),
+        }
+    }
+
+    pub fn poll(&mut self, options: &ScanOptions) -> Result<MonitorPoll> {
+        let mut processes = self.scanner.scan(options)?;
+        processes.sort();
+        if processes != self.previous {
+            self.last_change = Instant::now();
+            self.previous = processes.clone();
+            Ok(MonitorPoll::Changed(processes))
+        } else {
+            Ok(MonitorPoll::Unchanged)
+        }
+    }
+
+    /// Whether the listener set has been stable long enough to poll less often.
+    pub fn is_idle(&self) -> bool {
+        self.last_change.elapsed() > IDLE_THRESHOLD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::scanner::FixtureScanner;
+
+    fn options() -> ScanOptions {
+        ScanOptions {
+            port_ranges: vec![(3000, 3010), (8000, 8100)],
+            include_udp: false,
+        }
+    }
+
+    fn monitor_for(snapshots: &[&str]) -> ListenerMonitor {
+        let scanner = FixtureScanner::new(snapshots.iter().map(|s| s.to_string()).collect());
+        ListenerMonitor::new(Arc::new(scanner))
+    }
+
+    #[test]
+    fn reports_changes_only_when_listeners_differ() {
+        let node = "p101\ncnode\nf22\nPTCP\nn*:3000\nTST=LISTEN";
+        let both = "p101\ncnode\nf22\nPTCP\nn*:3000\nTST=LISTEN\np202\ncpython3\nf4\nPTCP\nn*:8000\nTST=LISTEN";
+        let mut monitor = monitor_for(&[node, node, both, node]);
+
+        assert!(
+            matches!(monitor.poll(&options()).unwrap(), MonitorPoll::Changed(p) if p.len() == 1)
+        );
+        assert_eq!(monitor.poll(&options()).unwrap(), MonitorPoll::Unchanged);
+        assert!(
+            matches!(monitor.poll(&options()).unwrap(), MonitorPoll::Changed(p) if p.len() == 2)
+        );
+        assert!(
+            matches!(monitor.poll(&options()).unwrap(), MonitorPoll::Changed(p) if p.len() == 1)
+        );
+        assert_eq!(monitor.poll(&options()).unwrap(), MonitorPoll::Unchanged);
+        assert!(!monitor.is_idle());
+    }
+
+    #[test]
+    fn ignores_listeners_outside_ranges() {
+        let debugger = "p101\ncnode\nf23\nPTCP\nn127.0.0.1:9229\nTST=LISTEN";
+        let mut monitor = monitor_for(&[debugger]);
+        // Empty to empty is not a change
+        assert_eq!(monitor.poll(&options()).unwrap(), MonitorPoll::Unchanged);
+    }
+}
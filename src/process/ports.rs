@@ -1,17 +1,76 @@
 use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
 use std::process::Command;
 
 use anyhow::{Context, Result, anyhow};
 
-use crate::model::ProcessInfo;
+use crate::model::{AddressFamily, ProcessInfo, Protocol};
+use crate::process::scanner::{PortScanner, ScanOptions};
 
-pub fn scan_ports(port_ranges: &[(u16, u16)]) -> Result<Vec<ProcessInfo>> {
-    fn in_ranges(port: u16, ranges: &[(u16, u16)]) -> bool {
-        ranges.iter().any(|(s, e)| port >= *s && port <= *e)
+/// Scanner backend that shells out to `lsof`. Works on macOS and on Linux
+/// hosts where lsof is installed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LsofScanner;
+
+impl PortScanner for LsofScanner {
+    fn scan(&self, options: &ScanOptions) -> Result<Vec<ProcessInfo>> {
+        let mut stdout = run_lsof_sweep(&["-nP", "-iTCP", "-sTCP:LISTEN", "-FpcnPTt"])?;
+        if options.include_udp {
+            // A TCP state filter would also hide UDP files, so UDP gets its own sweep.
+            // lsof exits non-zero when nothing matches, which is normal for UDP.
+            if let Ok(udp) = run_lsof_sweep(&["-nP", "-iUDP", "-FpcnPTt"]) {
+                stdout.push('\n');
+                stdout.push_str(&udp);
+            }
+        }
+        Ok(parse_lsof_listeners(&stdout, options))
     }
 
+    /// Verify that a PID is still associated with a TCP listener.
+    /// Used to mitigate TOCTOU race conditions before killing a process.
+    fn is_listener(&self, pid: i32) -> bool {
+        let output = Command::new("lsof")
+            .args([
+                "-nP",
+                "-p",
+                &pid.to_string(),
+                "-iTCP",
+                "-sTCP:LISTEN",
+                "-Fn",
+            ])
+            .output();
+
+        let is_tcp_listener = match output {
+            Ok(out) if out.status.success() => {
+                // If lsof returns any "n" lines, PID is still listening
+                String::from_utf8_lossy(&out.stdout)
+                    .lines()
+                    .any(|line| line.starts_with('n'))
+            }
+            _ => false,
+        };
+        is_tcp_listener || is_udp_bound(pid)
+    }
+
+    fn process_cwd(&self, pid: i32) -> Option<PathBuf> {
+        let out = Command::new("lsof")
+            .args(["-a", "-p", &pid.to_string(), "-d", "cwd", "-Fn"])
+            .output()
+            .ok()?;
+        if !out.status.success() {
+            return None;
+        }
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix('n'))
+            .map(PathBuf::from)
+    }
+}
+
+fn run_lsof_sweep(args: &[&str]) -> Result<String> {
     let output = Command::new("lsof")
-        .args(["-nP", "-iTCP", "-sTCP:LISTEN", "-FpcnPT"])
+        .args(args)
         .output()
         .context("failed to execute lsof sweep")?;
 
@@ -22,13 +81,31 @@ pub fn scan_ports(port_ranges: &[(u16, u16)]) -> Result<Vec<ProcessInfo>> {
         ));
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Unconnected UDP sockets have no "->" peer, so any parsable name means bound.
+fn is_udp_bound(pid: i32) -> bool {
+    run_lsof_sweep(&["-nP", "-a", "-p", &pid.to_string(), "-iUDP", "-Fn"])
+        .map(|out| {
+            out.lines()
+                .filter_map(|line| line.strip_prefix('n'))
+                .any(|name| parse_port_from_lsof(name).is_some())
+        })
+        .unwrap_or(false)
+}
+
+/// Parse `lsof -F` field output into listeners matching the scan options.
+/// Shared by the live lsof backend and the recorded-fixture backend.
+pub fn parse_lsof_listeners(output: &str, options: &ScanOptions) -> Vec<ProcessInfo> {
     let mut current_pid: Option<i32> = None;
     let mut current_cmd: Option<String> = None;
+    let mut current_protocol = Protocol::Tcp;
+    let mut current_family = AddressFamily::Ipv4;
     let mut results: Vec<ProcessInfo> = Vec::new();
-    let mut seen: HashSet<(u16, i32)> = HashSet::new();
+    let mut seen: HashSet<(u16, Protocol, IpAddr, i32)> = HashSet::new();
 
-    for line in stdout.lines() {
+    for line in output.lines() {
         if line.is_empty() {
             continue;
         }
@@ -41,14 +118,38 @@ pub fn scan_ports(port_ranges: &[(u16, u16)]) -> Result<Vec<ProcessInfo>> {
             "c" => {
                 current_cmd = Some(val.trim().to_string());
             }
+            "f" => {
+                current_protocol = Protocol::Tcp;
+                current_family = AddressFamily::Ipv4;
+            }
+            "t" => {
+                current_family = match val.trim() {
+                    "IPv6" => AddressFamily::Ipv6,
+                    _ => AddressFamily::Ipv4,
+                };
+            }
+            "P" => {
+                current_protocol = match val.trim() {
+                    "UDP" => Protocol::Udp,
+                    _ => Protocol::Tcp,
+                };
+            }
             "n" => {
+                if current_protocol == Protocol::Udp && !options.include_udp {
+                    continue;
+                }
                 if let (Some(pid), Some(cmd)) = (current_pid, current_cmd.as_ref())
                     && let Some(port) = parse_port_from_lsof(val.trim())
-                    && in_ranges(port, port_ranges)
-                    && seen.insert((port, pid))
+                    && options.in_ranges(port)
                 {
+                    let address = parse_bind_address(val.trim(), current_family);
+                    if !seen.insert((port, current_protocol, address, pid)) {
+                        continue;
+                    }
                     results.push(ProcessInfo {
                         port,
+                        protocol: current_protocol,
+                        address,
                         pid,
                         command: cmd.clone(),
                     });
@@ -59,32 +160,7 @@ pub fn scan_ports(port_ranges: &[(u16, u16)]) -> Result<Vec<ProcessInfo>> {
     }
 
     results.sort();
-    Ok(results)
-}
-
-/// Verify that a PID is still associated with a TCP listener.
-/// Used to mitigate TOCTOU race conditions before killing a process.
-pub fn verify_pid_is_listener(pid: i32) -> bool {
-    let output = Command::new("lsof")
-        .args([
-            "-nP",
-            "-p",
-            &pid.to_string(),
-            "-iTCP",
-            "-sTCP:LISTEN",
-            "-Fn",
-        ])
-        .output();
-
-    match output {
-        Ok(out) if out.status.success() => {
-            // If lsof returns any "n" lines, PID is still listening
-            String::from_utf8_lossy(&out.stdout)
-                .lines()
-                .any(|line| line.starts_with('n'))
-        }
-        _ => false,
-    }
+    results
 }
 
 // Extract a port number from an lsof name field.
@@ -113,9 +189,33 @@ pub fn parse_port_from_lsof(name: &str) -> Option<u16> {
     digits.parse::<u16>().ok()
 }
 
+// Extract the bind address from an lsof name field. The wildcard "*" carries no
+// family of its own, so the file type ("t" field) decides between 0.0.0.0 and ::.
+// Unparsable hosts are treated as wildcards so they are never hidden as loopback.
+pub fn parse_bind_address(name: &str, family: AddressFamily) -> IpAddr {
+    let unspecified = match family {
+        AddressFamily::Ipv4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        AddressFamily::Ipv6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let Some((host, _port)) = name.rsplit_once(':') else {
+        return unspecified;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    // Drop IPv6 zone identifiers such as "fe80::1%lo0"
+    let host = host.split('%').next().unwrap_or(host);
+    if host == "*" {
+        return unspecified;
+    }
+    host.parse::<IpAddr>().unwrap_or(unspecified)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_port_from_lsof;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use super::{parse_bind_address, parse_lsof_listeners, parse_port_from_lsof};
+    use crate::model::{AddressFamily, Protocol};
+    use crate::process::scanner::ScanOptions;
 
     #[test]
     fn parses_ipv4_wildcard() {
@@ -138,4 +238,77 @@ mod tests {
         assert_eq!(parse_port_from_lsof("127.0.0.1->192.168.0.1:1234"), None);
         assert_eq!(parse_port_from_lsof("garbage"), None);
     }
+
+    #[test]
+    fn parses_field_output_within_ranges() {
+        let output = "p101\ncnode\nf22\nPTCP\nn*:3000\nTST=LISTEN\nf23\nPTCP\nn*:9229\nTST=LISTEN\np202\ncpython3\nf5\nPTCP\nn127.0.0.1:8000\nTST=LISTEN\n";
+        let options = ScanOptions {
+            port_ranges: vec![(3000, 3010), (8000, 8100)],
+            include_udp: false,
+        };
+        let listeners = parse_lsof_listeners(output, &options);
+        assert_eq!(listeners.len(), 2);
+        assert_eq!((listeners[0].port, listeners[0].pid), (3000, 101));
+        assert_eq!(listeners[0].command, "node");
+        assert_eq!((listeners[1].port, listeners[1].pid), (8000, 202));
+    }
+
+    #[test]
+    fn parses_udp_only_when_enabled() {
+        let output = "p303\ncmdns-proxy\nf7\nPUDP\nn*:5353\nf8\nPUDP\nn10.0.0.2:5353->224.0.0.251:5353\nf9\nPTCP\nn*:5353\nTST=LISTEN\n";
+        let mut options = ScanOptions {
+            port_ranges: vec![(5353, 5353)],
+            include_udp: false,
+        };
+        let tcp_only = parse_lsof_listeners(output, &options);
+        assert_eq!(tcp_only.len(), 1);
+        assert_eq!(tcp_only[0].protocol, Protocol::Tcp);
+
+        options.include_udp = true;
+        let both = parse_lsof_listeners(output, &options);
+        assert_eq!(both.len(), 2);
+        assert_eq!(both[0].protocol, Protocol::Tcp);
+        assert_eq!(both[1].protocol, Protocol::Udp);
+        assert_eq!(both[1].port_label(), "5353/udp");
+    }
+
+    #[test]
+    fn parses_bind_addresses_and_families() {
+        assert_eq!(
+            parse_bind_address("*:3000", AddressFamily::Ipv4),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        );
+        assert_eq!(
+            parse_bind_address("*:3000", AddressFamily::Ipv6),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        );
+        assert_eq!(
+            parse_bind_address("127.0.0.1:5173", AddressFamily::Ipv4),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+        assert_eq!(
+            parse_bind_address("[::1]:8000", AddressFamily::Ipv6),
+            IpAddr::V6(Ipv6Addr::LOCALHOST)
+        );
+        assert_eq!(
+            parse_bind_address("[fe80::1%lo0]:8000", AddressFamily::Ipv6),
+            "fe80::1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn keeps_separate_v4_and_v6_bindings() {
+        let output = "p101\ncnode\nf22\ntIPv4\nPTCP\nn127.0.0.1:3000\nTST=LISTEN\nf23\ntIPv6\nPTCP\nn*:3000\nTST=LISTEN\n";
+        let options = ScanOptions {
+            port_ranges: vec![(3000, 3000)],
+            include_udp: false,
+        };
+        let listeners = parse_lsof_listeners(output, &options);
+        assert_eq!(listeners.len(), 2);
+        assert!(!listeners[0].is_exposed());
+        assert_eq!(listeners[0].endpoint(), "127.0.0.1:3000");
+        assert_eq!(listeners[1].family(), AddressFamily::Ipv6);
+        assert!(listeners[1].is_exposed());
+        assert_eq!(listeners[1].endpoint(), "[::]:3000");
+    }
 }
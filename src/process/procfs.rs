@@ -0,0 +1,326 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::model::{ProcessInfo, Protocol};
+use crate::process::ports::LsofScanner;
+use crate::process::scanner::{PortScanner, ScanOptions};
+
+const PROC_NET_TCP: &str = "/proc/net/tcp";
+const PROC_NET_TCP6: &str = "/proc/net/tcp6";
+const PROC_NET_UDP: &str = "/proc/net/udp";
+const PROC_NET_UDP6: &str = "/proc/net/udp6";
+// Kernel socket states (include/net/tcp_states.h). Bound but unconnected UDP
+// sockets report TCP_CLOSE; connected ones report TCP_ESTABLISHED.
+const TCP_LISTEN: u8 = 0x0A;
+const TCP_CLOSE: u8 = 0x07;
+
+/// A single row from /proc/net/{tcp,tcp6,udp,udp6}.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SocketEntry {
+    pub protocol: Protocol,
+    pub address: IpAddr,
+    pub port: u16,
+    pub state: u8,
+    pub inode: u64,
+}
+
+/// Scanner backend that reads Linux procfs directly instead of spawning lsof.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcfsScanner;
+
+impl PortScanner for ProcfsScanner {
+    fn scan(&self, options: &ScanOptions) -> Result<Vec<ProcessInfo>> {
+        match scan_listeners(options) {
+            Ok(processes) => Ok(processes),
+            Err(err) => {
+                log::debug!("procfs scan failed, falling back to lsof: {}", err);
+                LsofScanner.scan(options)
+            }
+        }
+    }
+
+    fn is_listener(&self, pid: i32) -> bool {
+        pid_has_listener(pid)
+    }
+
+    fn process_cwd(&self, pid: i32) -> Option<PathBuf> {
+        fs::read_link(format!("/proc/{}/cwd", pid)).ok()
+    }
+}
+
+/// Check whether the procfs socket tables are readable on this host.
+pub fn is_available() -> bool {
+    fs::metadata(PROC_NET_TCP).is_ok()
+}
+
+/// Enumerate listeners by reading the kernel socket tables directly and
+/// mapping socket inodes back to PIDs through /proc/<pid>/fd.
+pub fn scan_listeners(options: &ScanOptions) -> Result<Vec<ProcessInfo>> {
+    let mut sockets_by_inode: HashMap<u64, SocketEntry> = HashMap::new();
+    for entry in read_listening_sockets(options.include_udp)? {
+        if options.in_ranges(entry.port) {
+            sockets_by_inode.insert(entry.inode, entry);
+        }
+    }
+    if sockets_by_inode.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut results: Vec<ProcessInfo> = Vec::new();
+    let mut seen: HashSet<(u16, Protocol, IpAddr, i32)> = HashSet::new();
+
+    for (pid, inodes) in socket_inodes_by_pid(&sockets_by_inode) {
+        let command = read_comm(pid).unwrap_or_else(|| format!("pid {}", pid));
+        for inode in inodes {
+            if let Some(entry) = sockets_by_inode.get(&inode)
+                && seen.insert((entry.port, entry.protocol, entry.address, pid))
+            {
+                results.push(ProcessInfo {
+                    port: entry.port,
+                    protocol: entry.protocol,
+                    address: entry.address,
+                    pid,
+                    command: command.clone(),
+                });
+            }
+        }
+    }
+
+    results.sort();
+    Ok(results)
+}
+
+/// Verify that a PID still owns at least one listening TCP or bound UDP socket.
+pub fn pid_has_listener(pid: i32) -> bool {
+    let listening: HashSet<u64> = match read_listening_sockets(true) {
+        Ok(entries) => entries.into_iter().map(|e| e.inode).collect(),
+        Err(_) => return false,
+    };
+    socket_inodes_for(pid)
+        .into_iter()
+        .any(|inode| listening.contains(&inode))
+}
+
+fn read_listening_sockets(include_udp: bool) -> Result<Vec<SocketEntry>> {
+    let mut entries = Vec::new();
+    let v4 = fs::read_to_string(PROC_NET_TCP).context("failed to read /proc/net/tcp")?;
+    entries.extend(parse_table(&v4, Protocol::Tcp, TCP_LISTEN));
+    // The v6 tables are absent when IPv6 is disabled; treat that as "no v6 sockets"
+    if let Ok(v6) = fs::read_to_string(PROC_NET_TCP6) {
+        entries.extend(parse_table(&v6, Protocol::Tcp, TCP_LISTEN));
+    }
+    if include_udp {
+        for path in [PROC_NET_UDP, PROC_NET_UDP6] {
+            if let Ok(table) = fs::read_to_string(path) {
+                entries.extend(parse_table(&table, Protocol::Udp, TCP_CLOSE));
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_table(table: &str, protocol: Protocol, state: u8) -> Vec<SocketEntry> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| parse_socket_line(line, protocol))
+        .filter(|e| e.state == state)
+        .collect()
+}
+
+fn socket_inodes_by_pid(wanted: &HashMap<u64, SocketEntry>) -> Vec<(i32, Vec<u64>)> {
+    let Ok(dir) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for entry in dir.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<i32>().ok())
+        else {
+            continue;
+        };
+        let inodes: Vec<u64> = socket_inodes_for(pid)
+            .into_iter()
+            .filter(|inode| wanted.contains_key(inode))
+            .collect();
+        if !inodes.is_empty() {
+            out.push((pid, inodes));
+        }
+    }
+    out
+}
+
+fn socket_inodes_for(pid: i32) -> Vec<u64> {
+    // Processes owned by other users are unreadable without privileges, same as lsof
+    let Ok(fds) = fs::read_dir(format!("/proc/{}/fd", pid)) else {
+        return Vec::new();
+    };
+    fds.flatten()
+        .filter_map(|fd| fs::read_link(fd.path()).ok())
+        .filter_map(|target| parse_socket_link(&target.to_string_lossy()))
+        .collect()
+}
+
+fn read_comm(pid: i32) -> Option<String> {
+    let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    let comm = comm.trim();
+    if comm.is_empty() {
+        None
+    } else {
+        Some(comm.to_string())
+    }
+}
+
+/// Start time of `pid` in seconds since the epoch, from field 22 of
+/// /proc/<pid>/stat (clock ticks since boot) and the boot time in /proc/stat.
+pub fn process_start_time(pid: i32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let boot_time = parse_boot_time(&fs::read_to_string("/proc/stat").ok()?)?;
+    // SAFETY: sysconf has no preconditions.
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_sec <= 0 {
+        return None;
+    }
+    Some(boot_time + parse_start_ticks(&stat)? / ticks_per_sec as u64)
+}
+
+fn parse_start_ticks(stat: &str) -> Option<u64> {
+    // comm (field 2) may contain spaces and parentheses; field 3 follows the last ')'
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(22 - 3)?.parse().ok()
+}
+
+fn parse_boot_time(stat: &str) -> Option<u64> {
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+// Extract the inode from an fd symlink target such as "socket:[123456]".
+fn parse_socket_link(target: &str) -> Option<u64> {
+    target
+        .strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse::<u64>()
+        .ok()
+}
+
+/// Parse one data row of a /proc/net socket table. TCP and UDP share the layout.
+pub fn parse_socket_line(line: &str, protocol: Protocol) -> Option<SocketEntry> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        return None;
+    }
+    let (addr_hex, port_hex) = fields[1].split_once(':')?;
+    let address = decode_address(addr_hex)?;
+    let port = u16::from_str_radix(port_hex, 16).ok()?;
+    let state = u8::from_str_radix(fields[3], 16).ok()?;
+    let inode = fields[9].parse::<u64>().ok()?;
+    Some(SocketEntry {
+        protocol,
+        address,
+        port,
+        state,
+        inode,
+    })
+}
+
+// The kernel prints each 32-bit word of the address in host byte order,
+// so every word has to be converted back with to_ne_bytes.
+fn decode_address(hex: &str) -> Option<IpAddr> {
+    match hex.len() {
+        8 => {
+            let word = u32::from_str_radix(hex, 16).ok()?;
+            Some(IpAddr::V4(Ipv4Addr::from(word.to_ne_bytes())))
+        }
+        32 => {
+            let mut octets = [0u8; 16];
+            for (idx, chunk) in octets.chunks_mut(4).enumerate() {
+                let word = u32::from_str_radix(&hex[idx * 8..idx * 8 + 8], 16).ok()?;
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn le_hex_v4(addr: Ipv4Addr) -> String {
+        format!("{:08X}", u32::from_ne_bytes(addr.octets()))
+    }
+
+    #[test]
+    fn parses_ipv4_listen_row() {
+        let line = format!(
+            "   0: {}:0BB8 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 48213 1 0000000000000000 100 0 0 10 0",
+            le_hex_v4(Ipv4Addr::LOCALHOST)
+        );
+        let entry = parse_socket_line(&line, Protocol::Tcp).expect("row should parse");
+        assert_eq!(entry.port, 3000);
+        assert_eq!(entry.state, TCP_LISTEN);
+        assert_eq!(entry.inode, 48213);
+        assert_eq!(entry.address, IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+
+    #[test]
+    fn parses_ipv6_wildcard_row() {
+        let line = "   1: 00000000000000000000000000000000:1F40 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 99120 1 0000000000000000 100 0 0 10 0";
+        let entry = parse_socket_line(line, Protocol::Tcp).expect("row should parse");
+        assert_eq!(entry.port, 8000);
+        assert_eq!(entry.address, IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+        assert_eq!(entry.inode, 99120);
+    }
+
+    #[test]
+    fn udp_table_keeps_only_unconnected_sockets() {
+        let table = "   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+  283: 00000000:14E9 00000000:0000 07 00000000:00000000 00:00000000 00000000   101        0 20114 2 0000000000000000 0
+  901: 0F02000A:D2A4 08080808:0035 01 00000000:00000000 00:00000000 00000000  1000        0 77001 2 0000000000000000 0";
+        let entries = parse_table(table, Protocol::Udp, TCP_CLOSE);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].port, 5353);
+        assert_eq!(entries[0].protocol, Protocol::Udp);
+    }
+
+    #[test]
+    fn rejects_header_and_short_rows() {
+        assert_eq!(
+            parse_socket_line(
+                "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode",
+                Protocol::Tcp
+            ),
+            None
+        );
+        assert_eq!(parse_socket_line("0: 0100007F:0BB8", Protocol::Tcp), None);
+    }
+
+    #[test]
+    fn parses_start_time_fields() {
+        let stat = "4242 (tmux: server) S 1 4242 4242 0 -1 4194560 1033 0 0 0 12 5 0 0 20 0 1 0 873421 10485760 812 18446744073709551615";
+        assert_eq!(parse_start_ticks(stat), Some(873421));
+        assert_eq!(parse_start_ticks("4242 (sh)"), None);
+        assert_eq!(
+            parse_boot_time("cpu  1 2 3\nbtime 1760000000\nprocesses 9\n"),
+            Some(1_760_000_000)
+        );
+    }
+
+    #[test]
+    fn parses_socket_links() {
+        assert_eq!(parse_socket_link("socket:[48213]"), Some(48213));
+        assert_eq!(parse_socket_link("pipe:[48213]"), None);
+        assert_eq!(parse_socket_link("/dev/null"), None);
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+
+use crate::config::MonitoringConfig;
+use crate::model::ProcessInfo;
+use crate::process::ports::{LsofScanner, parse_lsof_listeners};
+use crate::process::procfs::{self, ProcfsScanner};
+
+/// What a scan should report.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    pub port_ranges: Vec<(u16, u16)>,
+    pub include_udp: bool,
+}
+
+impl ScanOptions {
+    pub fn in_ranges(&self, port: u16) -> bool {
+        self.port_ranges
+            .iter()
+            .any(|(s, e)| port >= *s && port <= *e)
+    }
+}
+
+impl From<&MonitoringConfig> for ScanOptions {
+    fn from(monitoring: &MonitoringConfig) -> Self {
+        Self {
+            port_ranges: monitoring.port_ranges.clone(),
+            include_udp: monitoring.include_udp,
+        }
+    }
+}
+
+/// Backend used to discover listeners and inspect the processes behind them.
+/// The monitor loop and kill path only talk to this trait so they can run
+/// against recorded output in tests.
+pub trait PortScanner: Send + Sync {
+    /// Enumerate listeners matching the options, sorted.
+    fn scan(&self, options: &ScanOptions) -> Result<Vec<ProcessInfo>>;
+
+    /// Whether the PID still owns at least one listening TCP or bound UDP socket.
+    fn is_listener(&self, pid: i32) -> bool;
+
+    /// Working directory of the PID, if it can be determined.
+    fn process_cwd(&self, pid: i32) -> Option<PathBuf>;
+}
+
+/// Pick the fastest backend available on this host.
+pub fn default_scanner() -> Arc<dyn PortScanner> {
+    if cfg!(target_os = "linux") && procfs::is_available() {
+        Arc::new(ProcfsScanner)
+    } else {
+        Arc::new(LsofScanner)
+    }
+}
+
+/// Replays recorded `lsof -F` output. Each call to `scan` advances to the next
+/// snapshot; once exhausted the last snapshot keeps being returned.
+pub struct FixtureScanner {
+    snapshots: Vec<String>,
+    cursor: Mutex<usize>,
+}
+
+impl FixtureScanner {
+    pub fn new(snapshots: Vec<String>) -> Self {
+        Self {
+            snapshots,
+            cursor: Mutex::new(0),
+        }
+    }
+
+    /// Load snapshots from a recording where consecutive `lsof -F` dumps are
+    /// separated by blank lines (lsof itself never emits blank lines).
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read scanner fixture {}", path.display()))?;
+        let snapshots = content
+            .split("\n\n")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        Ok(Self::new(snapshots))
+    }
+
+    // The snapshot most recently returned by `scan` (or the first one before any scan).
+    fn current(&self) -> &str {
+        let cursor = *self.cursor.lock().unwrap();
+        let idx = cursor
+            .saturating_sub(1)
+            .min(self.snapshots.len().saturating_sub(1));
+        self.snapshots.get(idx).map(String::as_str).unwrap_or("")
+    }
+}
+
+impl PortScanner for FixtureScanner {
+    fn scan(&self, options: &ScanOptions) -> Result<Vec<ProcessInfo>> {
+        let snapshot = {
+            let mut cursor = self.cursor.lock().unwrap();
+            let idx = (*cursor).min(self.snapshots.len().saturating_sub(1));
+            if *cursor < self.snapshots.len() {
+                *cursor += 1;
+            }
+            self.snapshots.get(idx).cloned().unwrap_or_default()
+        };
+        Ok(parse_lsof_listeners(&snapshot, options))
+    }
+
+    fn is_listener(&self, pid: i32) -> bool {
+        let everything = ScanOptions {
+            port_ranges: vec![(0, u16::MAX)],
+            include_udp: true,
+        };
+        parse_lsof_listeners(self.current(), &everything)
+            .iter()
+            .any(|p| p.pid == pid)
+    }
+
+    fn process_cwd(&self, pid: i32) -> Option<PathBuf> {
+        // Recordings may include `-d cwd` file sets: "fcwd" followed by "n<path>"
+        let mut current_pid = None;
+        let mut in_cwd = false;
+        for line in self.current().lines() {
+            if let Some(val) = line.strip_prefix('p') {
+                current_pid = val.parse::<i32>().ok();
+            } else if let Some(val) = line.strip_prefix('f') {
+                in_cwd = val == "cwd";
+            } else if let Some(val) = line.strip_prefix('n')
+                && in_cwd
+                && current_pid == Some(pid)
+            {
+                return Some(PathBuf::from(val));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+
+    const FIRST: &str = "p101\ncnode\nfcwd\nn/Users/dev/web\nf22\nPTCP\nn*:3000\nTST=LISTEN";
+    const SECOND: &str = "p202\ncvite\nf9\nPTCP\nn[::1]:5173\nTST=LISTEN";
+
+    #[test]
+    fn fixture_replays_snapshots_in_order() {
+        let scanner = FixtureScanner::new(vec![FIRST.into(), SECOND.into()]);
+        let options = ScanOptions {
+            port_ranges: vec![(3000, 6000)],
+            include_udp: false,
+        };
+        assert_eq!(scanner.scan(&options).unwrap()[0].pid, 101);
+        assert!(scanner.is_listener(101));
+        assert_eq!(
+            scanner.process_cwd(101),
+            Some(PathBuf::from("/Users/dev/web"))
+        );
+
+        assert_eq!(scanner.scan(&options).unwrap()[0].pid, 202);
+        assert!(!scanner.is_listener(101));
+        // Exhausted fixtures keep returning the final snapshot
+        assert_eq!(scanner.scan(&options).unwrap()[0].pid, 202);
+    }
+
+    #[test]
+    fn fixture_file_splits_on_blank_lines() {
+        let path = temp_path("fixture");
+        fs::write(&path, format!("{}\n\n{}\n", FIRST, SECOND)).unwrap();
+        let scanner = FixtureScanner::from_file(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(scanner.snapshots.len(), 2);
+    }
+}
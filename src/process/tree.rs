@@ -0,0 +1,139 @@
+use std::collections::{HashMap, VecDeque};
+use std::process::Command;
+
+use crate::model::ChildProcess;
+
+/// Parent → children view of the process table.
+#[derive(Debug, Default)]
+pub struct ProcessTable {
+    children: HashMap<i32, Vec<ChildProcess>>,
+    parents: HashMap<i32, i32>,
+    groups: HashMap<i32, i32>,
+}
+
+impl ProcessTable {
+    /// All descendants of `pid`, parents before their children.
+    pub fn descendants(&self, pid: i32) -> Vec<ChildProcess> {
+        let mut found = Vec::new();
+        let mut queue = VecDeque::from([pid]);
+        while let Some(parent) = queue.pop_front() {
+            for child in self.children.get(&parent).into_iter().flatten() {
+                // Guard against cycles from a table that changed mid-read
+                if child.pid == pid || found.iter().any(|c: &ChildProcess| c.pid == child.pid) {
+                    continue;
+                }
+                queue.push_back(child.pid);
+                found.push(child.clone());
+            }
+        }
+        found
+    }
+
+    /// The parent of `pid`, if both are in the table.
+    pub fn parent(&self, pid: i32) -> Option<ChildProcess> {
+        let ppid = *self.parents.get(&pid)?;
+        let grandparent = self.parents.get(&ppid)?;
+        self.children
+            .get(grandparent)?
+            .iter()
+            .find(|p| p.pid == ppid)
+            .cloned()
+    }
+
+    /// Whether `pid` leads its own process group, as job-control shells and
+    /// the jobs they start do.
+    pub fn is_group_leader(&self, pid: i32) -> bool {
+        self.groups.get(&pid) == Some(&pid)
+    }
+}
+
+/// Snapshot every process's parent with a single `ps` call.
+pub fn query_process_table() -> ProcessTable {
+    match Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,pgid=,comm="])
+        .output()
+    {
+        Ok(out) => parse_process_table(&String::from_utf8_lossy(&out.stdout)),
+        Err(err) => {
+            log::warn!("ps command failed: {}", err);
+            ProcessTable::default()
+        }
+    }
+}
+
+pub(crate) fn parse_process_table(output: &str) -> ProcessTable {
+    let mut children: HashMap<i32, Vec<ChildProcess>> = HashMap::new();
+    let mut parents = HashMap::new();
+    let mut groups = HashMap::new();
+    for line in output.lines() {
+        let mut rest = line.trim_start();
+        let mut fields = [""; 3];
+        for field in &mut fields {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            *field = &rest[..end];
+            rest = rest[end..].trim_start();
+        }
+        let (Ok(pid), Ok(ppid), Ok(pgid)) = (
+            fields[0].parse::<i32>(),
+            fields[1].parse::<i32>(),
+            fields[2].parse::<i32>(),
+        ) else {
+            continue;
+        };
+        // macOS prints the full executable path, Linux the short name
+        let comm = rest.trim_end();
+        let command = comm.rsplit('/').next().unwrap_or(comm).to_string();
+        parents.insert(pid, ppid);
+        groups.insert(pid, pgid);
+        children
+            .entry(ppid)
+            .or_default()
+            .push(ChildProcess { pid, command });
+    }
+    ProcessTable {
+        children,
+        parents,
+        groups,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PS_OUTPUT: &str = "    1     0     1 /sbin/launchd
+  500     1   500 /usr/local/bin/npm
+  501   500   500 /usr/local/bin/node
+  502   501   500 /Users/dev/web/node_modules/@esbuild/darwin-arm64/bin/esbuild
+  503   501   500 sh
+  600     1   600 gunicorn
+garbage line
+";
+
+    #[test]
+    fn finds_descendants_breadth_first() {
+        let table = parse_process_table(PS_OUTPUT);
+        let tree = table.descendants(500);
+        let pids: Vec<i32> = tree.iter().map(|c| c.pid).collect();
+        assert_eq!(pids, vec![501, 502, 503]);
+        assert_eq!(tree[1].command, "esbuild");
+        assert!(table.descendants(600).is_empty());
+    }
+
+    #[test]
+    fn finds_parents() {
+        let table = parse_process_table(PS_OUTPUT);
+        assert_eq!(table.parent(501).map(|p| p.command).as_deref(), Some("npm"));
+        assert_eq!(table.parent(500).map(|p| p.pid), Some(1));
+        assert!(table.parent(1).is_none());
+        assert!(table.parent(999).is_none());
+        assert!(table.is_group_leader(500));
+        assert!(!table.is_group_leader(503));
+    }
+
+    #[test]
+    fn tolerates_cycles() {
+        let table = parse_process_table("10 11 10 a\n11 10 11 b\n");
+        assert_eq!(table.descendants(10).len(), 1);
+    }
+}
@@ -0,0 +1,389 @@
+use std::time::{Duration, Instant};
+
+use crate::integrations::brew::get_brew_managed_service;
+use crate::integrations::runtime::ContainerRuntime;
+use crate::model::{AppState, DockerContainerInfo, ProcessIdentity, ProcessInfo, Protocol};
+use crate::process::identity::capture_identity;
+use crate::process::tree::ProcessTable;
+
+// A new listener on the port this soon after a kill counts as a respawn
+pub const RESPAWN_WINDOW: Duration = Duration::from_secs(10);
+// Forget kills that never report back; covers the longest allowed ladder
+const PENDING_TIMEOUT: Duration = Duration::from_secs(240);
+// Wrappers between a supervisor and the process it runs (`sh -c "node app.js"`)
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "fish"];
+
+/// A kill we are watching for a rebind.
+#[derive(Clone, Debug)]
+struct KillWatch {
+    pid: i32,
+    ports: Vec<(u16, Protocol)>,
+    dispatched: Instant,
+    stopped: Option<Instant>,
+}
+
+impl KillWatch {
+    fn expired(&self, now: Instant) -> bool {
+        match self.stopped {
+            Some(at) => now.duration_since(at) > RESPAWN_WINDOW,
+            None => now.duration_since(self.dispatched) > PENDING_TIMEOUT,
+        }
+    }
+}
+
+/// Correlates kills with the scans that follow them.
+#[derive(Debug, Default)]
+pub struct RespawnTracker {
+    watches: Vec<KillWatch>,
+}
+
+impl RespawnTracker {
+    /// Start watching the ports `pid` listens on; call when the kill is dispatched.
+    pub fn watch(&mut self, pid: i32, processes: &[ProcessInfo], now: Instant) {
+        let mut ports: Vec<(u16, Protocol)> = processes
+            .iter()
+            .filter(|p| p.pid == pid)
+            .map(|p| (p.port, p.protocol))
+            .collect();
+        ports.sort();
+        ports.dedup();
+        if ports.is_empty() {
+            return;
+        }
+        self.watches.retain(|w| w.pid != pid);
+        self.watches.push(KillWatch {
+            pid,
+            ports,
+            dispatched: now,
+            stopped: None,
+        });
+    }
+
+    /// The kill feedback reported these PIDs as gone; the respawn window starts now.
+    pub fn confirm(&mut self, stopped_pids: &[i32], now: Instant) {
+        for watch in &mut self.watches {
+            if stopped_pids.contains(&watch.pid) {
+                watch.stopped = Some(now);
+            }
+        }
+    }
+
+    /// Listeners that took over a watched port from a killed PID.
+    pub fn detect(&mut self, processes: &[ProcessInfo], now: Instant) -> Vec<Rebind> {
+        self.watches.retain(|w| !w.expired(now));
+        let mut rebinds = Vec::new();
+        self.watches.retain(|watch| {
+            // Still shutting down, keep waiting
+            if processes.iter().any(|p| p.pid == watch.pid) {
+                return true;
+            }
+            let newcomer = processes
+                .iter()
+                .find(|p| watch.ports.contains(&(p.port, p.protocol)));
+            match newcomer {
+                Some(process) => {
+                    rebinds.push(Rebind {
+                        old_pid: watch.pid,
+                        new_pid: process.pid,
+                        port: process.port,
+                        protocol: process.protocol,
+                        command: process.command.clone(),
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+        rebinds
+    }
+}
+
+/// A killed listener's port was bound again by a new process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rebind {
+    pub old_pid: i32,
+    pub new_pid: i32,
+    pub port: u16,
+    pub protocol: Protocol,
+    pub command: String,
+}
+
+/// Whatever brought the listener back.
+#[derive(Clone, Debug)]
+pub enum Supervisor {
+    /// A parent process such as nodemon, pm2 or cargo-watch.
+    Process {
+        pid: i32,
+        name: String,
+        /// Taken when the respawn was seen, so a recycled PID is not stopped.
+        identity: ProcessIdentity,
+    },
+    /// launchd (KeepAlive) or systemd; cannot be stopped from here.
+    Init {
+        name: String,
+    },
+    BrewService {
+        service: String,
+    },
+    Container {
+        name: String,
+        runtime: ContainerRuntime,
+        restart_policy: Option<String>,
+    },
+}
+
+impl Supervisor {
+    pub fn describe(&self) -> String {
+        match self {
+            Supervisor::Process { pid, name, .. } => format!("{} (PID {})", name, pid),
+            Supervisor::Init { name } if name == "launchd" => "a launchd KeepAlive job".into(),
+            Supervisor::Init { name } => format!("a {} service", name),
+            Supervisor::BrewService { service } => format!("brew service {}", service),
+            Supervisor::Container {
+                name,
+                restart_policy: Some(policy),
+                ..
+            } if !policy.is_empty() && policy != "no" => {
+                format!("container {} (restart policy {})", name, policy)
+            }
+            Supervisor::Container { name, .. } => format!("container {}", name),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RespawnWarning {
+    pub rebind: Rebind,
+    pub supervisor: Option<Supervisor>,
+}
+
+impl RespawnWarning {
+    pub fn message(&self) -> String {
+        let rebind = &self.rebind;
+        let mut message = format!(
+            "{} on port {} came back as PID {} right after PID {} was stopped",
+            rebind.command,
+            rebind.protocol.port_label(rebind.port),
+            rebind.new_pid,
+            rebind.old_pid
+        );
+        match &self.supervisor {
+            Some(supervisor) => {
+                message.push_str(&format!("; likely restarted by {}.", supervisor.describe()))
+            }
+            None => message.push_str("; something is supervising it."),
+        }
+        message
+    }
+}
+
+/// What the integrations say owns a respawned port. Read on the UI thread
+/// so the rest of the search can run on the worker.
+#[derive(Clone, Debug, Default)]
+pub struct PortOwner {
+    pub container: Option<DockerContainerInfo>,
+    pub brew_service: Option<String>,
+}
+
+impl PortOwner {
+    pub fn of(rebind: &Rebind, state: &AppState) -> Self {
+        let listener = state.processes.iter().find(|p| {
+            p.pid == rebind.new_pid && p.port == rebind.port && p.protocol == rebind.protocol
+        });
+        PortOwner {
+            container: listener.and_then(|p| state.container_for(p)).cloned(),
+            brew_service: get_brew_managed_service(
+                &rebind.command,
+                rebind.port,
+                &state.brew_services_map,
+            ),
+        }
+    }
+}
+
+/// Best guess at what respawned the listener: the container or brew service
+/// that owns the port, else the nearest non-shell ancestor of the new PID.
+/// `None` when that walk reaches an interactive shell: someone restarted
+/// the listener by hand.
+pub fn identify_supervisor(
+    rebind: &Rebind,
+    owner: &PortOwner,
+    table: &ProcessTable,
+    restart_policy: impl Fn(&DockerContainerInfo) -> Option<String>,
+) -> Option<Supervisor> {
+    if let Some(container) = &owner.container {
+        return Some(Supervisor::Container {
+            name: container.name.clone(),
+            runtime: container.runtime,
+            restart_policy: restart_policy(container),
+        });
+    }
+    if let Some(service) = &owner.brew_service {
+        return Some(Supervisor::BrewService {
+            service: service.clone(),
+        });
+    }
+
+    let mut parent = table.parent(rebind.new_pid)?;
+    // Login shells show up as `-zsh`
+    while SHELLS.contains(&parent.command.trim_start_matches('-')) {
+        // `sh -c` wrappers share their parent's process group; a shell that
+        // leads its own is a terminal, tmux or IDE session
+        if table.is_group_leader(parent.pid) {
+            return None;
+        }
+        match table.parent(parent.pid) {
+            Some(next) => parent = next,
+            None => break,
+        }
+    }
+    if parent.pid <= 1 {
+        return Some(Supervisor::Init {
+            name: parent.command,
+        });
+    }
+    Some(Supervisor::Process {
+        pid: parent.pid,
+        name: parent.command,
+        identity: capture_identity(parent.pid, None),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+    use crate::model::{DockerContainerInfo, PublishedPort};
+    use crate::process::tree::parse_process_table;
+    use crate::test_util::listener;
+
+    #[test]
+    fn detects_rebind_after_kill() {
+        let start = Instant::now();
+        let mut tracker = RespawnTracker::default();
+        tracker.watch(10, &[listener(3000, 10), listener(4000, 11)], start);
+
+        // Still alive: not a respawn yet
+        assert!(tracker.detect(&[listener(3000, 10)], start).is_empty());
+        tracker.confirm(&[10], start);
+        let rebinds = tracker.detect(&[listener(3000, 12)], start + Duration::from_secs(2));
+        assert_eq!(
+            rebinds,
+            vec![Rebind {
+                old_pid: 10,
+                new_pid: 12,
+                port: 3000,
+                protocol: Protocol::Tcp,
+                command: "node".into(),
+            }]
+        );
+        // Reported once
+        assert!(tracker.detect(&[listener(3000, 12)], start).is_empty());
+    }
+
+    #[test]
+    fn ignores_rebinds_after_the_window() {
+        let start = Instant::now();
+        let mut tracker = RespawnTracker::default();
+        tracker.watch(10, &[listener(3000, 10)], start);
+        tracker.confirm(&[10], start);
+        assert!(
+            tracker
+                .detect(&[], start + Duration::from_secs(1))
+                .is_empty()
+        );
+        let late = start + RESPAWN_WINDOW + Duration::from_secs(1);
+        assert!(tracker.detect(&[listener(3000, 12)], late).is_empty());
+    }
+
+    const PS_OUTPUT: &str = "    1     0     1 /sbin/launchd
+ 1200     1  1200 node
+ 1201  1200  1200 /bin/sh
+ 1300  1201  1200 node
+ 1400     1  1400 node
+ 1500     1  1500 -zsh
+ 1501  1500  1501 node
+";
+
+    fn rebind(new_pid: i32, port: u16) -> Rebind {
+        Rebind {
+            old_pid: 1,
+            new_pid,
+            port,
+            protocol: Protocol::Tcp,
+            command: "node".into(),
+        }
+    }
+
+    #[test]
+    fn names_parent_supervisor_through_shells() {
+        let table = parse_process_table(PS_OUTPUT);
+        let owner = PortOwner::default();
+        let no_policy = |_: &DockerContainerInfo| None;
+        assert!(matches!(
+            identify_supervisor(&rebind(1300, 3000), &owner, &table, no_policy),
+            Some(Supervisor::Process { pid: 1200, name, .. }) if name == "node"
+        ));
+        let launchd = identify_supervisor(&rebind(1400, 3000), &owner, &table, no_policy);
+        assert_eq!(
+            launchd.map(|s| s.describe()).as_deref(),
+            Some("a launchd KeepAlive job")
+        );
+        // Restarted by hand from a terminal
+        assert!(identify_supervisor(&rebind(1501, 3000), &owner, &table, no_policy).is_none());
+    }
+
+    #[test]
+    fn queues_warnings_behind_the_one_shown() {
+        let warning = |new_pid, port| RespawnWarning {
+            rebind: rebind(new_pid, port),
+            supervisor: None,
+        };
+        let mut state = AppState {
+            processes: vec![listener(3000, 1200), listener(3002, 1202)],
+            ..Default::default()
+        };
+        for (pid, port) in [(1200, 3000), (1201, 3001), (1202, 3002)] {
+            state.push_respawn_warning(warning(pid, port));
+        }
+        assert_eq!(state.respawn_warning.as_ref().unwrap().rebind.new_pid, 1200);
+        // 1201 is gone again, so it is skipped
+        state.next_respawn_warning();
+        assert_eq!(state.respawn_warning.as_ref().unwrap().rebind.new_pid, 1202);
+        state.next_respawn_warning();
+        assert!(state.respawn_warning.is_none());
+    }
+
+    #[test]
+    fn names_container_restart_policy() {
+        let table = parse_process_table(PS_OUTPUT);
+        let mut state = AppState {
+            processes: vec![listener(5432, 1300)],
+            ..Default::default()
+        };
+        state.docker_port_map.insert(
+            PublishedPort {
+                ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                port: 5432,
+                protocol: Protocol::Tcp,
+            },
+            DockerContainerInfo {
+                name: "db".into(),
+                id: "abc".into(),
+                project: None,
+                service: None,
+                runtime: Default::default(),
+            },
+        );
+        let owner = PortOwner::of(&rebind(1300, 5432), &state);
+        let supervisor = identify_supervisor(&rebind(1300, 5432), &owner, &table, |_| {
+            Some("always".into())
+        })
+        .unwrap();
+        assert_eq!(
+            supervisor.describe(),
+            "container db (restart policy always)"
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::RestartConfig;
+use crate::model::{ProcessInfo, Protocol};
+use crate::process::details::unix_now;
+use crate::process::scanner::{PortScanner, ScanOptions};
+
+// Launches remembered across runs; older ones are dropped
+const STORE_CAPACITY: usize = 20;
+const VERIFY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Everything needed to start a killed listener again.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchSpec {
+    pub argv: Vec<String>,
+    pub cwd: PathBuf,
+    /// Whitelisted variables only; see `restart.env`.
+    pub env: Vec<(String, String)>,
+    pub port: u16,
+    pub protocol: Protocol,
+    pub command: String,
+    pub captured_at: u64,
+}
+
+impl LaunchSpec {
+    pub fn command_line(&self) -> String {
+        self.argv.join(" ")
+    }
+}
+
+pub fn restart_path(config: &RestartConfig) -> PathBuf {
+    config.path.clone().unwrap_or_else(|| {
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(home).join(".portkiller-restart.json")
+    })
+}
+
+/// Record how `pid` was started, just before it is killed. `None` when the
+/// process table cannot be read (another user's process, or it already exited).
+pub fn capture_launch(
+    pid: i32,
+    processes: &[ProcessInfo],
+    config: &RestartConfig,
+    scanner: &dyn PortScanner,
+) -> Option<LaunchSpec> {
+    if !config.enabled {
+        return None;
+    }
+    let listener = processes
+        .iter()
+        .filter(|p| p.pid == pid)
+        .min_by_key(|p| (p.protocol, p.port))?;
+    let (argv, environ) = read_argv_env(pid)?;
+    if argv.is_empty() || argv[0].is_empty() {
+        return None;
+    }
+    Some(LaunchSpec {
+        argv,
+        cwd: scanner.process_cwd(pid)?,
+        env: filter_env(&environ, &config.env),
+        port: listener.port,
+        protocol: listener.protocol,
+        command: listener.command.clone(),
+        captured_at: unix_now(),
+    })
+}
+
+/// The listeners and settings `capture_launch` reads, taken when a kill is
+/// dispatched so the capture itself can run on the worker.
+#[derive(Clone, Debug, Default)]
+pub struct LaunchContext {
+    processes: Vec<ProcessInfo>,
+    config: RestartConfig,
+}
+
+impl LaunchContext {
+    pub fn new(processes: &[ProcessInfo], config: &RestartConfig) -> Self {
+        LaunchContext {
+            processes: processes.to_vec(),
+            config: config.clone(),
+        }
+    }
+
+    pub fn capture(&self, pid: i32, scanner: &dyn PortScanner) -> Option<LaunchSpec> {
+        capture_launch(pid, &self.processes, &self.config, scanner)
+    }
+}
+
+/// Keep the `NAME=value` entries whose name is whitelisted.
+pub fn filter_env(environ: &[String], whitelist: &[String]) -> Vec<(String, String)> {
+    environ
+        .iter()
+        .filter_map(|entry| entry.split_once('='))
+        .filter(|(name, _)| {
+            whitelist
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == pattern,
+                })
+        })
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn read_argv_env(pid: i32) -> Option<(Vec<String>, Vec<String>)> {
+    let argv = split_nul(&fs::read(format!("/proc/{}/cmdline", pid)).ok()?);
+    let environ = fs::read(format!("/proc/{}/environ", pid))
+        .map(|bytes| split_nul(&bytes))
+        .unwrap_or_default();
+    Some((argv, environ))
+}
+
+#[cfg(target_os = "macos")]
+fn read_argv_env(pid: i32) -> Option<(Vec<String>, Vec<String>)> {
+    let mut argmax: libc::c_int = 0;
+    let mut size = std::mem::size_of::<libc::c_int>();
+    let mut mib = [libc::CTL_KERN, libc::KERN_ARGMAX];
+    let res = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            2,
+            &mut argmax as *mut libc::c_int as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 || argmax <= 0 {
+        return None;
+    }
+    let mut buf = vec![0u8; argmax as usize];
+    let mut size = buf.len();
+    let mut mib = [libc::CTL_KERN, libc::KERN_PROCARGS2, pid];
+    let res = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            3,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 {
+        return None;
+    }
+    buf.truncate(size);
+    parse_procargs2(&buf)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_argv_env(_pid: i32) -> Option<(Vec<String>, Vec<String>)> {
+    None
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn split_nul(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .split(|b| *b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
+/// KERN_PROCARGS2 layout: argc (i32), the exec path, NUL padding, argc
+/// arguments, then the environment, each NUL-terminated.
+#[cfg(any(target_os = "macos", test))]
+fn parse_procargs2(buf: &[u8]) -> Option<(Vec<String>, Vec<String>)> {
+    let argc = i32::from_ne_bytes(buf.get(..4)?.try_into().ok()?);
+    let rest = &buf[4..];
+    let exec_end = rest.iter().position(|b| *b == 0)?;
+    let mut strings = rest[exec_end..]
+        .split(|b| *b == 0)
+        .skip_while(|s| s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned());
+    let argv: Vec<String> = strings.by_ref().take(argc.max(0) as usize).collect();
+    let environ = strings.take_while(|s| !s.is_empty()).collect();
+    Some((argv, environ))
+}
+
+/// Recently killed launches, newest first, one per port and protocol.
+pub struct RestartStore {
+    path: PathBuf,
+    specs: Vec<LaunchSpec>,
+}
+
+impl RestartStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        let specs = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("failed to parse {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()));
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            specs,
+        })
+    }
+
+    pub fn specs(&self) -> &[LaunchSpec] {
+        &self.specs
+    }
+
+    /// Newest launch recorded for the port, over any protocol.
+    pub fn find(&self, port: u16, protocol: Option<Protocol>) -> Option<&LaunchSpec> {
+        self.specs
+            .iter()
+            .find(|s| s.port == port && protocol.is_none_or(|p| p == s.protocol))
+    }
+
+    /// Add a launch and save. Rereads the file first so the app and the
+    /// CLI do not drop each other's entries.
+    pub fn remember(&mut self, spec: LaunchSpec) -> Result<()> {
+        if let Ok(current) = Self::open(&self.path) {
+            self.specs = current.specs;
+        }
+        self.specs
+            .retain(|s| (s.port, s.protocol) != (spec.port, spec.protocol));
+        self.specs.insert(0, spec);
+        self.specs.truncate(STORE_CAPACITY);
+        self.save()
+    }
+
+    // The environment may hold secrets; keep the file private
+    fn save(&self) -> Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        file.write_all(serde_json::to_string_pretty(&self.specs)?.as_bytes())?;
+        fs::rename(&tmp, &self.path).context("failed to replace restart store")
+    }
+}
+
+/// Start the process again, detached from PortKiller, and wait until it
+/// listens on its old port. Returns the PID holding the port.
+pub fn relaunch(spec: &LaunchSpec, scanner: &dyn PortScanner, timeout: Duration) -> Result<i32> {
+    let options = ScanOptions {
+        port_ranges: vec![(spec.port, spec.port)],
+        include_udp: spec.protocol == Protocol::Udp,
+    };
+    let holder = || -> Option<i32> {
+        scanner
+            .scan(&options)
+            .ok()?
+            .into_iter()
+            .find(|p| p.port == spec.port && p.protocol == spec.protocol)
+            .map(|p| p.pid)
+    };
+    if let Some(pid) = holder() {
+        bail!(
+            "port {} is already in use by PID {}",
+            spec.protocol.port_label(spec.port),
+            pid
+        );
+    }
+
+    let (program, args) = spec.argv.split_first().context("empty command line")?;
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(&spec.cwd)
+        .env_clear()
+        .envs(spec.env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Keep terminal and tray signals away from the relaunched server
+        .process_group(0)
+        .spawn()
+        .with_context(|| format!("failed to start {}", spec.command_line()))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut exited = None;
+    loop {
+        if let Some(pid) = holder() {
+            if exited.is_none() {
+                // Reap it whenever it exits so it never lingers as a zombie
+                thread::spawn(move || child.wait());
+            }
+            return Ok(pid);
+        }
+        if exited.is_none() {
+            exited = child.try_wait().ok().flatten();
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+        thread::sleep(VERIFY_INTERVAL);
+    }
+    match exited {
+        Some(status) => bail!(
+            "{} exited ({}) without binding port {}",
+            spec.command,
+            status,
+            spec.protocol.port_label(spec.port)
+        ),
+        None => {
+            let pid = child.id();
+            thread::spawn(move || child.wait());
+            bail!(
+                "{} (PID {}) is running but did not bind port {} within {}s",
+                spec.command,
+                pid,
+                spec.protocol.port_label(spec.port),
+                timeout.as_secs()
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::scanner::FixtureScanner;
+    use crate::test_util::temp_path;
+
+    fn spec(port: u16, argv: &[&str]) -> LaunchSpec {
+        LaunchSpec {
+            argv: argv.iter().map(|s| s.to_string()).collect(),
+            cwd: std::env::temp_dir(),
+            env: vec![("PATH".into(), std::env::var("PATH").unwrap_or_default())],
+            port,
+            protocol: Protocol::Tcp,
+            command: argv[0].into(),
+            captured_at: 0,
+        }
+    }
+
+    #[test]
+    fn parses_procargs2_buffers() {
+        let mut buf = 2i32.to_ne_bytes().to_vec();
+        buf.extend_from_slice(
+            b"/usr/local/bin/node\0\0\0\0node\0server.js\0PORT=3000\0HOME=/Users/dev\0\0junk",
+        );
+        let (argv, environ) = parse_procargs2(&buf).unwrap();
+        assert_eq!(argv, vec!["node", "server.js"]);
+        assert_eq!(environ, vec!["PORT=3000", "HOME=/Users/dev"]);
+    }
+
+    #[test]
+    fn keeps_only_whitelisted_variables() {
+        let environ: Vec<String> = ["PATH=/bin", "LC_ALL=C", "AWS_SECRET=x", "PORT=3000"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let whitelist = vec!["PATH".to_string(), "LC_*".to_string(), "PORT".to_string()];
+        assert_eq!(
+            filter_env(&environ, &whitelist),
+            vec![
+                ("PATH".to_string(), "/bin".to_string()),
+                ("LC_ALL".to_string(), "C".to_string()),
+                ("PORT".to_string(), "3000".to_string()),
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_own_command_line() {
+        let (argv, environ) = read_argv_env(std::process::id() as i32).unwrap();
+        assert!(!argv.is_empty());
+        assert!(environ.iter().any(|e| e.contains('=')));
+    }
+
+    #[test]
+    fn store_keeps_newest_launch_per_port() {
+        let path = temp_path("restart");
+        let mut store = RestartStore::open(&path).unwrap();
+        store.remember(spec(3000, &["node", "old.js"])).unwrap();
+        store.remember(spec(5173, &["vite"])).unwrap();
+        store.remember(spec(3000, &["node", "new.js"])).unwrap();
+
+        let reopened = RestartStore::open(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(reopened.specs().len(), 2);
+        assert_eq!(
+            reopened.find(3000, None).unwrap().command_line(),
+            "node new.js"
+        );
+    }
+
+    #[test]
+    fn relaunch_waits_for_the_port() {
+        let bound = FixtureScanner::new(vec![
+            String::new(),
+            "p4242\ncnode\nf22\nPTCP\nn*:3000\nTST=LISTEN".into(),
+        ]);
+        let pid = relaunch(&spec(3000, &["true"]), &bound, Duration::from_secs(5)).unwrap();
+        assert_eq!(pid, 4242);
+
+        let never = FixtureScanner::new(vec![String::new()]);
+        let err = relaunch(&spec(3000, &["false"]), &never, Duration::from_millis(500))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("without binding port 3000"), "{}", err);
+    }
+}
@@ -0,0 +1,30 @@
+//! Fixtures shared by the unit tests.
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+
+use crate::model::{ProcessInfo, Protocol};
+
+/// A `node` process listening on TCP `port` on localhost.
+pub fn listener(port: u16, pid: i32) -> ProcessInfo {
+    listener_on(port, IpAddr::V4(Ipv4Addr::LOCALHOST), pid)
+}
+
+/// Like [`listener`], bound to `address`.
+pub fn listener_on(port: u16, address: IpAddr, pid: i32) -> ProcessInfo {
+    ProcessInfo {
+        port,
+        protocol: Protocol::Tcp,
+        address,
+        pid,
+        command: "node".into(),
+    }
+}
+
+/// A path in the temp dir unique to `name` and this test run, with anything
+/// an earlier run left there removed.
+pub fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("portkiller-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
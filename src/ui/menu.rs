@@ -1,11 +1,29 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use anyhow::Result;
-use tray_icon::menu::{Menu, MenuId, MenuItem, PredefinedMenuItem};
+use tray_icon::menu::{Menu, MenuId, MenuItem, PredefinedMenuItem, Submenu};
 
-use crate::model::{AppState, FeedbackSeverity, KillFeedback, ProcessInfo};
+use crate::history::{HistoryKind, HistoryQuery, HistoryRecord, churn, format_clock};
+use crate::integrations::docker::{ComposeAction, ContainerAction};
+use crate::integrations::kubectl::{PortForward, port_forward_for};
+use crate::integrations::runtime::ContainerRuntime;
+use crate::integrations::ssh::{SshConnection, ssh_tunnel_for};
+use crate::model::{
+    AddressFamily, AppState, ChildProcess, FeedbackSeverity, KillFeedback, ProcessDetails,
+    ProcessInfo, Protocol,
+};
+use crate::process::details::{describe_command, unix_now};
+use crate::respawn::{RespawnWarning, Supervisor};
+use crate::restart::LaunchSpec;
+use crate::undo::StoppedService;
 
 const MAX_TOOLTIP_ENTRIES: usize = 5;
+const MAX_ARGS_LABEL_LEN: usize = 80;
+const MAX_CHILD_NAMES: usize = 3;
+const MAX_HISTORY_ITEMS: usize = 10;
+const MAX_RESTART_ITEMS: usize = 5;
+// Starts within a day before a port is called out as respawning
+const RESPAWN_THRESHOLD: usize = 3;
 const MENU_ID_KILL_ALL: &str = "kill_all";
 const MENU_ID_DOCKER_STOP_ALL: &str = "docker_stop_all";
 const MENU_ID_BREW_STOP_ALL: &str = "brew_stop_all";
@@ -18,8 +36,29 @@ const MENU_ID_TOGGLE_AUTO_UPDATE: &str = "toggle_auto_update";
 const MENU_ID_DOWNLOAD_UPDATE: &str = "download_update";
 const MENU_ID_DISMISS_UPDATE: &str = "dismiss_update";
 const MENU_ID_PROCESS_PREFIX: &str = "process_";
+const MENU_ID_PROCESS_TREE_PREFIX: &str = "process_tree_";
+const MENU_ID_PROCESS_FORCE_PREFIX: &str = "process_force_";
+const MENU_ID_DOCKER_FORCE_PREFIX: &str = "docker_force_";
+const MENU_ID_BREW_FORCE_PREFIX: &str = "brew_force_";
 const MENU_ID_DOCKER_STOP_PREFIX: &str = "docker_stop_";
+const MENU_ID_DOCKER_KILL_PREFIX: &str = "docker_kill_";
+const MENU_ID_DOCKER_RESTART_PREFIX: &str = "docker_restart_";
+const MENU_ID_DOCKER_REMOVE_PREFIX: &str = "docker_rm_";
+const MENU_ID_DOCKER_REMOVE_VOLUMES_PREFIX: &str = "docker_rmv_";
+const MENU_ID_DOCKER_REMOVE_PRUNE_PREFIX: &str = "docker_rmnet_";
 const MENU_ID_BREW_STOP_PREFIX: &str = "brew_stop_";
+const MENU_ID_FORWARD_REESTABLISH_PREFIX: &str = "forward_reestablish_";
+const MENU_ID_FORWARD_REESTABLISH_FORCE_PREFIX: &str = "forward_reestablish_force_";
+const MENU_ID_SSH_CANCEL_PREFIX: &str = "ssh_cancel_";
+const MENU_ID_SSH_CANCEL_FORCE_PREFIX: &str = "ssh_cancel_force_";
+const MENU_ID_COMPOSE_STOP_PREFIX: &str = "compose_stop_";
+const MENU_ID_COMPOSE_RESTART_PREFIX: &str = "compose_restart_";
+const MENU_ID_COMPOSE_DOWN_PREFIX: &str = "compose_down_";
+const MENU_ID_SUPERVISOR_PREFIX: &str = "supervisor_";
+const MENU_ID_DISMISS_RESPAWN: &str = "dismiss_respawn";
+const MENU_ID_RESTART_PREFIX: &str = "restart_";
+const MENU_ID_UNDO_CONTAINER_PREFIX: &str = "undo_container_";
+const MENU_ID_UNDO_BREW_PREFIX: &str = "undo_brew_";
 const MENU_ID_EMPTY: &str = "empty";
 
 /// Maps common container names to friendly display names
@@ -51,6 +90,15 @@ fn friendly_container_name(raw_name: &str) -> String {
     }
 }
 
+/// Listeners owned by one PID, collapsed into a single menu row.
+struct PidGroup {
+    command: String,
+    ports: Vec<(u16, Protocol)>,
+    exposed: bool,
+    conflict: bool,
+    protected: bool,
+}
+
 pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
     let menu = Menu::new();
     let processes = &state.processes;
@@ -66,6 +114,23 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
     menu.append(&header)?;
     menu.append(&PredefinedMenuItem::separator())?;
 
+    if !state.undo.entries().is_empty() {
+        for entry in state.undo.entries() {
+            menu.append(&MenuItem::with_id(
+                undo_menu_id(&entry.service),
+                format!("↩ Undo stop of {}", entry.service.name()),
+                true,
+                None,
+            ))?;
+        }
+        menu.append(&PredefinedMenuItem::separator())?;
+    }
+
+    if let Some(warning) = &state.respawn_warning {
+        append_respawn_warning(&menu, warning)?;
+        menu.append(&PredefinedMenuItem::separator())?;
+    }
+
     if processes.is_empty() {
         let item = MenuItem::with_id(MENU_ID_EMPTY, "No dev ports listening", false, None);
         menu.append(&item)?;
@@ -73,10 +138,12 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
         // Separate processes into Docker, Brew, and regular processes
         let mut docker_items: Vec<(&ProcessInfo, &crate::model::DockerContainerInfo)> = Vec::new();
         let mut brew_items: Vec<(&ProcessInfo, String)> = Vec::new();
+        let mut forward_items: Vec<(&ProcessInfo, PortForward)> = Vec::new();
+        let mut tunnel_items: Vec<(&ProcessInfo, SshConnection)> = Vec::new();
         let mut regular_processes: Vec<&ProcessInfo> = Vec::new();
 
         for process in processes {
-            if let Some(dc) = state.docker_port_map.get(&process.port) {
+            if let Some(dc) = state.container_for(process) {
                 docker_items.push((process, dc));
             } else if let Some(service) = crate::integrations::brew::get_brew_managed_service(
                 &process.command,
@@ -84,11 +151,16 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
                 &state.brew_services_map,
             ) {
                 brew_items.push((process, service));
+            } else if let Some(forward) = port_forward_for(state, process) {
+                forward_items.push((process, forward));
+            } else if let Some(connection) = ssh_tunnel_for(state, process) {
+                tunnel_items.push((process, connection));
             } else {
                 regular_processes.push(process);
             }
         }
 
+        let conflicts = bind_conflicts(processes);
         let mut has_any_section = false;
 
         // === PROCESSES SECTION ===
@@ -96,14 +168,21 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
             has_any_section = true;
 
             // Group by PID to count unique processes
-            let mut by_pid: BTreeMap<i32, (String, Vec<u16>)> = BTreeMap::new();
+            let mut by_pid: BTreeMap<i32, PidGroup> = BTreeMap::new();
             for p in &regular_processes {
-                let entry = by_pid
-                    .entry(p.pid)
-                    .or_insert_with(|| (p.command.clone(), Vec::new()));
-                if !entry.1.contains(&p.port) {
-                    entry.1.push(p.port);
+                let entry = by_pid.entry(p.pid).or_insert_with(|| PidGroup {
+                    command: p.command.clone(),
+                    ports: Vec::new(),
+                    exposed: false,
+                    conflict: false,
+                    protected: false,
+                });
+                if !entry.ports.contains(&(p.port, p.protocol)) {
+                    entry.ports.push((p.port, p.protocol));
                 }
+                entry.exposed |= p.is_exposed();
+                entry.conflict |= conflicts.contains(&(p.port, p.protocol));
+                entry.protected |= state.is_protected(p);
             }
 
             let header = MenuItem::with_id(
@@ -115,39 +194,89 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
             menu.append(&header)?;
 
             // Create clickable menu item for each process (grouped by PID)
-            for (pid, (command, ports)) in &mut by_pid {
-                ports.sort();
+            for (pid, group) in &mut by_pid {
+                group.ports.sort();
 
                 // Get project name for this PID
                 let project_name = state.project_cache.get(pid).map(|pi| pi.name.clone());
+                let details = state.process_details.get(pid);
+                let command = describe_command(&group.command, details);
 
                 // Build main menu label: "ports · command · project"
-                let ports_str = ports
-                    .iter()
-                    .map(|p| p.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ");
+                let ports_str = join_port_labels(&group.ports);
+                let markers = listener_markers(group.exposed, group.conflict, group.protected);
 
-                let main_label = if let Some(ref project) = project_name {
-                    format!("{} · {} · {}", ports_str, command, project)
+                let mut main_label = if let Some(ref project) = project_name {
+                    format!("{}{} · {} · {}", markers, ports_str, command, project)
                 } else {
-                    format!("{} · {}", ports_str, command)
+                    format!("{}{} · {}", markers, ports_str, command)
                 };
+                if group.conflict {
+                    main_label.push_str(" · v4/v6 conflict");
+                }
 
-                // Create clickable menu item that kills the process when clicked
-                let process_item = MenuItem::with_id(
-                    MenuId::new(process_menu_id(*pid, ports[0])),
-                    main_label,
+                // Submenu with process metadata and the kill action
+                let process_menu = Submenu::new(main_label, true);
+                for line in detail_lines(*pid, details) {
+                    process_menu.append(&MenuItem::new(line, false, None))?;
+                }
+                process_menu.append(&PredefinedMenuItem::separator())?;
+                let children = state
+                    .process_children
+                    .get(pid)
+                    .map(Vec::as_slice)
+                    .unwrap_or(&[]);
+                let port_numbers: Vec<u16> = group.ports.iter().map(|(p, _)| *p).collect();
+                let tree_by_default = state.config.kill.tree_for(&group.command, &port_numbers);
+                let kill_label = if tree_by_default && !children.is_empty() {
+                    format!("Kill + {}", format_children_label(children))
+                } else {
+                    "Kill".to_string()
+                };
+                if group.protected {
+                    process_menu.append(&MenuItem::new(
+                        "🔒 Protected · skipped by Kill All",
+                        false,
+                        None,
+                    ))?;
+                    let force_item = MenuItem::with_id(
+                        MenuId::new(process_force_menu_id(*pid, group.ports[0].0)),
+                        format!("{} Anyway", kill_label),
+                        true,
+                        None,
+                    );
+                    process_menu.append(&force_item)?;
+                    menu.append(&process_menu)?;
+                    continue;
+                }
+                let kill_item = MenuItem::with_id(
+                    MenuId::new(process_menu_id(*pid, group.ports[0].0)),
+                    kill_label,
                     true,
                     None,
                 );
-                menu.append(&process_item)?;
+                process_menu.append(&kill_item)?;
+                if !tree_by_default && !children.is_empty() {
+                    let tree_item = MenuItem::with_id(
+                        MenuId::new(process_tree_menu_id(*pid, group.ports[0].0)),
+                        format!("Kill Tree + {}", format_children_label(children)),
+                        true,
+                        None,
+                    );
+                    process_menu.append(&tree_item)?;
+                }
+                menu.append(&process_menu)?;
             }
 
             // Kill All only if multiple processes
-            if by_pid.len() > 1 {
-                let kill_all =
-                    MenuItem::with_id(MENU_ID_KILL_ALL, "Kill All Processes", true, None);
+            let protected_count = by_pid.values().filter(|g| g.protected).count();
+            if by_pid.len() > 1 && protected_count < by_pid.len() {
+                let label = if protected_count > 0 {
+                    "Kill All Unprotected Processes"
+                } else {
+                    "Kill All Processes"
+                };
+                let kill_all = MenuItem::with_id(MENU_ID_KILL_ALL, label, true, None);
                 menu.append(&kill_all)?;
             }
         }
@@ -159,18 +288,27 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
             }
             has_any_section = true;
 
-            // Group by container name
-            let mut by_container: BTreeMap<String, Vec<u16>> = BTreeMap::new();
+            // Group by container; the same name under two runtimes is two rows
+            let mut by_container: BTreeMap<(String, ContainerRuntime), DockerRow> = BTreeMap::new();
             for (process, dc) in &docker_items {
-                by_container
-                    .entry(dc.name.clone())
-                    .or_default()
-                    .push(process.port);
+                let entry = by_container
+                    .entry((dc.name.clone(), dc.runtime))
+                    .or_insert_with(|| DockerRow {
+                        ports: Vec::new(),
+                        exposed: false,
+                        project: dc.project.clone(),
+                        service: dc.service.clone(),
+                        runtime: dc.runtime,
+                    });
+                if !entry.ports.contains(&(process.port, process.protocol)) {
+                    entry.ports.push((process.port, process.protocol));
+                }
+                entry.exposed |= process.is_exposed();
             }
 
             let header = MenuItem::with_id(
                 "header_docker",
-                format!("Docker Containers · {}", by_container.len()),
+                format!("Containers · {}", by_container.len()),
                 false,
                 None,
             );
@@ -179,27 +317,53 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
             // Check if we need Stop All before consuming the map
             let needs_stop_all = by_container.len() > 1;
 
-            // Create clickable menu item for each container
-            for (container_name, mut ports) in by_container {
-                ports.sort();
+            // Compose containers collapse under their project
+            let mut by_project: BTreeMap<String, Vec<(String, DockerRow)>> = BTreeMap::new();
+            for ((container_name, _), mut row) in by_container {
+                row.ports.sort();
+                if let Some(project) = row.project.clone() {
+                    by_project
+                        .entry(project)
+                        .or_default()
+                        .push((container_name, row));
+                    continue;
+                }
                 let friendly = friendly_container_name(&container_name);
 
                 // Build label: "ports · container_name"
-                let ports_str = ports
-                    .iter()
-                    .map(|p| p.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                let main_label = format!("{} · {}", ports_str, friendly);
-
-                // Create clickable menu item that stops the container when clicked
-                let container_item = MenuItem::with_id(
-                    format!("{}{}", MENU_ID_DOCKER_STOP_PREFIX, container_name),
-                    main_label,
-                    true,
-                    None,
+                let ports_str = join_port_labels(&row.ports);
+                let protected = state.is_container_protected(&container_name);
+                let markers = listener_markers(row.exposed, false, protected);
+                let main_label = format!(
+                    "{}{} · {}{}",
+                    markers,
+                    ports_str,
+                    friendly,
+                    runtime_suffix(row.runtime)
                 );
-                menu.append(&container_item)?;
+
+                if protected {
+                    menu.append(&protected_submenu(
+                        main_label,
+                        container_menu_id(
+                            MENU_ID_DOCKER_FORCE_PREFIX,
+                            row.runtime,
+                            &container_name,
+                        ),
+                        "Stop Container Anyway",
+                    )?)?;
+                    continue;
+                }
+
+                menu.append(&container_submenu(
+                    main_label,
+                    &container_name,
+                    row.runtime,
+                )?)?;
+            }
+
+            for (project, containers) in by_project {
+                menu.append(&compose_project_submenu(state, &project, &containers)?)?;
             }
 
             // Stop All only if multiple containers
@@ -210,6 +374,158 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
             }
         }
 
+        // === PORT FORWARD SECTION ===
+        if !forward_items.is_empty() {
+            if has_any_section {
+                menu.append(&PredefinedMenuItem::separator())?;
+            }
+            has_any_section = true;
+
+            // One kubectl can forward several ports
+            let mut by_pid: BTreeMap<i32, ForwardRow> = BTreeMap::new();
+            for (process, forward) in forward_items {
+                let entry = by_pid.entry(process.pid).or_insert_with(|| ForwardRow {
+                    ports: Vec::new(),
+                    exposed: false,
+                    forward,
+                });
+                if !entry.ports.contains(&(process.port, process.protocol)) {
+                    entry.ports.push((process.port, process.protocol));
+                }
+                // `--address 0.0.0.0` shares the cluster with the network
+                entry.exposed |= process.is_exposed();
+            }
+
+            menu.append(&MenuItem::with_id(
+                "header_forwards",
+                format!("Port Forwards · {}", by_pid.len()),
+                false,
+                None,
+            ))?;
+            for (pid, row) in by_pid {
+                let ForwardRow {
+                    mut ports,
+                    exposed,
+                    forward,
+                } = row;
+                ports.sort();
+                let protected = state.is_pid_protected(pid);
+                let label = format!(
+                    "{}{} → {}",
+                    listener_markers(exposed, false, protected),
+                    join_port_labels(&ports),
+                    forward.target()
+                );
+                if protected {
+                    let submenu = protected_submenu(
+                        label,
+                        process_force_menu_id(pid, ports[0].0),
+                        "Stop Forward Anyway",
+                    )?;
+                    submenu.append(&MenuItem::with_id(
+                        format!("{}{}", MENU_ID_FORWARD_REESTABLISH_FORCE_PREFIX, pid),
+                        "Re-establish Forward Anyway",
+                        true,
+                        None,
+                    ))?;
+                    menu.append(&submenu)?;
+                    continue;
+                }
+                let submenu = Submenu::new(label, true);
+                submenu.append(&MenuItem::new(
+                    format!("kubectl · {} · PID {}", forward.mapping(), pid),
+                    false,
+                    None,
+                ))?;
+                submenu.append(&MenuItem::with_id(
+                    process_menu_id(pid, ports[0].0),
+                    "Stop Forward",
+                    true,
+                    None,
+                ))?;
+                submenu.append(&MenuItem::with_id(
+                    format!("{}{}", MENU_ID_FORWARD_REESTABLISH_PREFIX, pid),
+                    "Re-establish Forward",
+                    true,
+                    None,
+                ))?;
+                menu.append(&submenu)?;
+            }
+        }
+
+        // === SSH TUNNEL SECTION ===
+        if !tunnel_items.is_empty() {
+            if has_any_section {
+                menu.append(&PredefinedMenuItem::separator())?;
+            }
+            has_any_section = true;
+
+            // One row per tunnel; IPv4 and IPv6 listeners of a port are one
+            let mut by_port: BTreeMap<(u16, i32), (SshConnection, bool)> = BTreeMap::new();
+            for (process, connection) in tunnel_items {
+                let entry = by_port
+                    .entry((process.port, process.pid))
+                    .or_insert((connection, false));
+                entry.1 |= process.is_exposed();
+            }
+
+            menu.append(&MenuItem::with_id(
+                "header_ssh_tunnels",
+                format!("SSH Tunnels · {}", by_port.len()),
+                false,
+                None,
+            ))?;
+            for ((port, pid), (connection, exposed)) in by_port {
+                let Some(tunnel) = connection.tunnel_on(port) else {
+                    continue;
+                };
+                let protected = state.is_pid_protected(pid);
+                let label = format!(
+                    "{}{} → {}",
+                    listener_markers(exposed, false, protected),
+                    port,
+                    connection.describe(tunnel)
+                );
+                // A ControlMaster drops just this forward; anything else
+                // only stops by ending the ssh session
+                let (stop_label, force_label) =
+                    if !connection.multiplexed && connection.tunnels.len() > 1 {
+                        (connection.close_label(), "Close Connection Anyway")
+                    } else {
+                        ("Stop Tunnel".to_string(), "Stop Tunnel Anyway")
+                    };
+                let (stop_id, force_id) = if connection.multiplexed {
+                    (
+                        format!("{}{}_{}", MENU_ID_SSH_CANCEL_PREFIX, pid, port),
+                        format!("{}{}_{}", MENU_ID_SSH_CANCEL_FORCE_PREFIX, pid, port),
+                    )
+                } else {
+                    (process_menu_id(pid, port), process_force_menu_id(pid, port))
+                };
+                if protected {
+                    menu.append(&protected_submenu(label, force_id, force_label)?)?;
+                    continue;
+                }
+                let submenu = Submenu::new(label, true);
+                let info = if connection.multiplexed {
+                    format!("ssh · ControlMaster · PID {}", pid)
+                } else {
+                    format!("ssh · PID {}", pid)
+                };
+                submenu.append(&MenuItem::new(info, false, None))?;
+                submenu.append(&MenuItem::with_id(stop_id, stop_label, true, None))?;
+                if connection.multiplexed {
+                    submenu.append(&MenuItem::with_id(
+                        process_menu_id(pid, port),
+                        connection.close_label(),
+                        true,
+                        None,
+                    ))?;
+                }
+                menu.append(&submenu)?;
+            }
+        }
+
         // === BREW SECTION ===
         if !brew_items.is_empty() {
             if has_any_section {
@@ -217,12 +533,13 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
             }
 
             // Group by service name
-            let mut by_service: BTreeMap<String, Vec<u16>> = BTreeMap::new();
+            let mut by_service: BTreeMap<String, (Vec<(u16, Protocol)>, bool)> = BTreeMap::new();
             for (process, service) in &brew_items {
-                by_service
-                    .entry(service.clone())
-                    .or_default()
-                    .push(process.port);
+                let entry = by_service.entry(service.clone()).or_default();
+                if !entry.0.contains(&(process.port, process.protocol)) {
+                    entry.0.push((process.port, process.protocol));
+                }
+                entry.1 |= process.is_exposed();
             }
 
             let header = MenuItem::with_id(
@@ -237,16 +554,23 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
             let needs_stop_all = by_service.len() > 1;
 
             // Create clickable menu item for each service
-            for (service_name, mut ports) in by_service {
+            for (service_name, (mut ports, exposed)) in by_service {
                 ports.sort();
 
                 // Build label: "ports · service_name"
-                let ports_str = ports
-                    .iter()
-                    .map(|p| p.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                let main_label = format!("{} · {}", ports_str, service_name);
+                let ports_str = join_port_labels(&ports);
+                let protected = state.is_brew_service_protected(&service_name);
+                let markers = listener_markers(exposed, false, protected);
+                let main_label = format!("{}{} · {}", markers, ports_str, service_name);
+
+                if protected {
+                    menu.append(&protected_submenu(
+                        main_label,
+                        format!("{}{}", MENU_ID_BREW_FORCE_PREFIX, service_name),
+                        "Stop Service Anyway",
+                    )?)?;
+                    continue;
+                }
 
                 // Create clickable menu item that stops the service when clicked
                 let service_item = MenuItem::with_id(
@@ -267,6 +591,30 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
         }
     }
 
+    let restartable = restartable_launches(state);
+    if !restartable.is_empty() || state.config.history.enabled {
+        menu.append(&PredefinedMenuItem::separator())?;
+    }
+    if !restartable.is_empty() {
+        let submenu = Submenu::new("Restart", true);
+        for spec in restartable {
+            submenu.append(&MenuItem::with_id(
+                restart_menu_id(spec.port, spec.protocol),
+                format!(
+                    "{} · {}",
+                    spec.protocol.port_label(spec.port),
+                    truncate_label(&spec.command_line(), MAX_ARGS_LABEL_LEN)
+                ),
+                true,
+                None,
+            ))?;
+        }
+        menu.append(&submenu)?;
+    }
+    if state.config.history.enabled {
+        menu.append(&build_history_submenu(&state.recent_history)?)?;
+    }
+
     menu.append(&PredefinedMenuItem::separator())?;
     let edit_config_item =
         MenuItem::with_id(MENU_ID_EDIT_CONFIG, "Edit Configuration...", true, None);
@@ -319,10 +667,499 @@ pub fn build_menu_with_context(state: &AppState) -> Result<Menu> {
     Ok(menu)
 }
 
+// Informational rows shown inside a process submenu.
+fn detail_lines(pid: i32, details: Option<&ProcessDetails>) -> Vec<String> {
+    let Some(d) = details else {
+        return vec![format!("PID {}", pid)];
+    };
+    let mut lines = vec![
+        format!("PID {} · parent {} · {}", pid, d.ppid, d.user),
+        format!(
+            "{} · CPU {:.1}% · up {}",
+            format_rss(d.rss_kb),
+            d.cpu_percent,
+            format_uptime(unix_now().saturating_sub(d.start_time))
+        ),
+    ];
+    if !d.args.is_empty() {
+        lines.push(truncate_label(&d.args, MAX_ARGS_LABEL_LEN));
+    }
+    lines
+}
+
+fn append_respawn_warning(menu: &Menu, warning: &RespawnWarning) -> Result<()> {
+    let rebind = &warning.rebind;
+    menu.append(&MenuItem::new(
+        format!(
+            "⚠ {} respawned as PID {} (was {})",
+            rebind.protocol.port_label(rebind.port),
+            rebind.new_pid,
+            rebind.old_pid
+        ),
+        false,
+        None,
+    ))?;
+    match &warning.supervisor {
+        Some(supervisor) => match supervisor_menu_id(supervisor) {
+            Some(id) => menu.append(&MenuItem::with_id(
+                id,
+                format!("Stop {} Instead", supervisor.describe()),
+                true,
+                None,
+            ))?,
+            None => menu.append(&MenuItem::new(
+                format!("Kept alive by {}", supervisor.describe()),
+                false,
+                None,
+            ))?,
+        },
+        None => menu.append(&MenuItem::new("Supervisor not found", false, None))?,
+    }
+    menu.append(&MenuItem::with_id(
+        MENU_ID_DISMISS_RESPAWN,
+        "Dismiss",
+        true,
+        None,
+    ))?;
+    Ok(())
+}
+
+/// Menu id of the item that stops a supervisor; `None` for launchd/systemd.
+pub fn supervisor_menu_id(supervisor: &Supervisor) -> Option<String> {
+    match supervisor {
+        Supervisor::Process { pid, .. } => Some(format!("{}{}", MENU_ID_SUPERVISOR_PREFIX, pid)),
+        Supervisor::Init { .. } => None,
+        Supervisor::BrewService { service } => {
+            Some(format!("{}{}", MENU_ID_BREW_STOP_PREFIX, service))
+        }
+        Supervisor::Container { name, runtime, .. } => Some(container_menu_id(
+            MENU_ID_DOCKER_STOP_PREFIX,
+            *runtime,
+            name,
+        )),
+    }
+}
+
+/// Killed launches whose port is free again, newest first.
+fn restartable_launches(state: &AppState) -> Vec<&LaunchSpec> {
+    state
+        .restartable
+        .iter()
+        .filter(|spec| {
+            !state
+                .processes
+                .iter()
+                .any(|p| p.port == spec.port && p.protocol == spec.protocol)
+        })
+        .take(MAX_RESTART_ITEMS)
+        .collect()
+}
+
+pub fn undo_menu_id(service: &StoppedService) -> String {
+    match service {
+        StoppedService::Container { name, runtime } => {
+            container_menu_id(MENU_ID_UNDO_CONTAINER_PREFIX, *runtime, name)
+        }
+        StoppedService::BrewService(name) => format!("{}{}", MENU_ID_UNDO_BREW_PREFIX, name),
+    }
+}
+
+pub fn restart_menu_id(port: u16, protocol: Protocol) -> String {
+    format!(
+        "{}{}_{}",
+        MENU_ID_RESTART_PREFIX,
+        protocol.to_string().to_lowercase(),
+        port
+    )
+}
+
+fn build_history_submenu(records: &[HistoryRecord]) -> Result<Submenu> {
+    let submenu = Submenu::new("History", true);
+    if records.is_empty() {
+        submenu.append(&MenuItem::new(
+            "No listener changes recorded yet",
+            false,
+            None,
+        ))?;
+        return Ok(submenu);
+    }
+
+    let last_day = HistoryQuery {
+        since: Some(unix_now().saturating_sub(86_400)),
+        ..Default::default()
+    };
+    let respawning: Vec<_> = churn(records, &last_day)
+        .into_iter()
+        .filter(|c| c.starts >= RESPAWN_THRESHOLD)
+        .collect();
+    for port in &respawning {
+        let label = format!(
+            "↻ {} started {}× today · {}",
+            port.protocol.port_label(port.port),
+            port.starts,
+            port.commands.join(", ")
+        );
+        submenu.append(&MenuItem::new(label, false, None))?;
+    }
+    if !respawning.is_empty() {
+        submenu.append(&PredefinedMenuItem::separator())?;
+    }
+
+    for record in records.iter().rev().take(MAX_HISTORY_ITEMS) {
+        submenu.append(&MenuItem::new(format_history_label(record), false, None))?;
+    }
+    Ok(submenu)
+}
+
+// e.g. "14:05 ▶ 3000 node (PID 123) · web" or "14:07 ■ 3000 node (PID 123) · ran 2m"
+fn format_history_label(record: &HistoryRecord) -> String {
+    let marker = match record.kind {
+        HistoryKind::Started => "▶",
+        HistoryKind::Stopped => "■",
+    };
+    let mut label = format!(
+        "{} {} {} {} (PID {})",
+        format_clock(record.ts),
+        marker,
+        record.protocol.port_label(record.port),
+        record.command,
+        record.pid
+    );
+    if let Some(owner) = record.container.as_ref().or(record.project.as_ref()) {
+        label.push_str(&format!(" · {}", owner));
+    }
+    if let Some(secs) = record.duration_secs {
+        label.push_str(&format!(" · ran {}", format_uptime(secs)));
+    }
+    label
+}
+
+fn format_rss(rss_kb: u64) -> String {
+    if rss_kb >= 1024 * 1024 {
+        format!("{:.1} GB", rss_kb as f64 / (1024.0 * 1024.0))
+    } else if rss_kb >= 1024 {
+        format!("{:.1} MB", rss_kb as f64 / 1024.0)
+    } else {
+        format!("{} KB", rss_kb)
+    }
+}
+
+pub fn format_uptime(secs: u64) -> String {
+    let (days, hours, mins) = (secs / 86_400, (secs % 86_400) / 3600, (secs % 3600) / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, mins)
+    } else if mins > 0 {
+        format!("{}m", mins)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn truncate_label(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let kept: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+        format!("{}…", kept)
+    }
+}
+
+// Leading flags for a menu row: conflicting v4/v6 owners, then all-interface exposure.
+fn listener_markers(exposed: bool, conflict: bool, protected: bool) -> String {
+    let mut markers = String::new();
+    if protected {
+        markers.push_str("🔒 ");
+    }
+    if conflict {
+        markers.push_str("⚠️ ");
+    }
+    if exposed {
+        markers.push_str("🌐 ");
+    }
+    markers
+}
+
+/// Ports bound separately on IPv4 and IPv6 by different PIDs.
+/// Connections then land on a different process depending on how the
+/// client resolves "localhost", which is a classic source of confusion.
+pub fn bind_conflicts(processes: &[ProcessInfo]) -> HashSet<(u16, Protocol)> {
+    let mut owners: HashMap<(u16, Protocol), Vec<(AddressFamily, i32)>> = HashMap::new();
+    for p in processes {
+        owners
+            .entry((p.port, p.protocol))
+            .or_default()
+            .push((p.family(), p.pid));
+    }
+    owners
+        .into_iter()
+        .filter(|(_, binds)| {
+            binds.iter().any(|(fam_a, pid_a)| {
+                binds
+                    .iter()
+                    .any(|(fam_b, pid_b)| fam_a != fam_b && pid_a != pid_b)
+            })
+        })
+        .map(|(key, _)| key)
+        .collect()
+}
+
+fn join_port_labels(ports: &[(u16, Protocol)]) -> String {
+    ports
+        .iter()
+        .map(|(port, protocol)| protocol.port_label(*port))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 pub fn process_menu_id(pid: i32, port: u16) -> String {
     format!("{}{}_{}", MENU_ID_PROCESS_PREFIX, pid, port)
 }
 
+pub fn process_force_menu_id(pid: i32, port: u16) -> String {
+    format!("{}{}_{}", MENU_ID_PROCESS_FORCE_PREFIX, pid, port)
+}
+
+// A protected container or service is a submenu, so one stray click cannot stop it
+/// One container row of the Docker section.
+struct DockerRow {
+    ports: Vec<(u16, Protocol)>,
+    exposed: bool,
+    project: Option<String>,
+    service: Option<String>,
+    runtime: ContainerRuntime,
+}
+
+/// One `kubectl port-forward` process of the Port Forwards section.
+struct ForwardRow {
+    ports: Vec<(u16, Protocol)>,
+    exposed: bool,
+    forward: PortForward,
+}
+
+/// Docker is the default; other runtimes are named next to the container.
+fn runtime_suffix(runtime: ContainerRuntime) -> String {
+    match runtime {
+        ContainerRuntime::Docker => String::new(),
+        other => format!(" ({})", other.binary()),
+    }
+}
+
+/// "5432/tcp, 6379/tcp · shop · 2 services" with a submenu per service and
+/// project-wide stop and down actions.
+fn compose_project_submenu(
+    state: &AppState,
+    project: &str,
+    containers: &[(String, DockerRow)],
+) -> Result<Submenu> {
+    let mut ports: Vec<(u16, Protocol)> = containers
+        .iter()
+        .flat_map(|(_, row)| row.ports.iter().copied())
+        .collect();
+    ports.sort();
+    ports.dedup();
+    let exposed = containers.iter().any(|(_, row)| row.exposed);
+    let protected = state.is_compose_protected(project, None);
+    let noun = if containers.len() == 1 {
+        "service"
+    } else {
+        "services"
+    };
+    let submenu = Submenu::new(
+        format!(
+            "{}{} · {} · {} {}{}",
+            listener_markers(exposed, false, protected),
+            join_port_labels(&ports),
+            project,
+            containers.len(),
+            noun,
+            runtime_suffix(containers[0].1.runtime)
+        ),
+        true,
+    );
+
+    for (container_name, row) in containers {
+        let protected = state.is_container_protected(container_name);
+        let label = format!(
+            "{}{} · {}",
+            listener_markers(row.exposed, false, protected),
+            join_port_labels(&row.ports),
+            row.service.as_deref().unwrap_or(container_name)
+        );
+        if protected {
+            submenu.append(&protected_submenu(
+                label,
+                container_menu_id(MENU_ID_DOCKER_FORCE_PREFIX, row.runtime, container_name),
+                "Stop Container Anyway",
+            )?)?;
+            continue;
+        }
+        let service_menu = container_submenu(label, container_name, row.runtime)?;
+        if let Some(service) = &row.service {
+            service_menu.append(&PredefinedMenuItem::separator())?;
+            service_menu.append(&MenuItem::with_id(
+                compose_menu_id(&ComposeAction::RestartService {
+                    project: project.to_string(),
+                    service: service.clone(),
+                }),
+                "Restart Service",
+                true,
+                None,
+            ))?;
+        }
+        submenu.append(&service_menu)?;
+    }
+
+    submenu.append(&PredefinedMenuItem::separator())?;
+    if protected {
+        // Project-wide actions would take the protected containers with them
+        submenu.append(&MenuItem::new(
+            "🔒 Protected containers · stop others individually",
+            false,
+            None,
+        ))?;
+        return Ok(submenu);
+    }
+    let project = project.to_string();
+    submenu.append(&MenuItem::with_id(
+        compose_menu_id(&ComposeAction::StopProject {
+            project: project.clone(),
+        }),
+        "Stop Project",
+        true,
+        None,
+    ))?;
+    submenu.append(&MenuItem::with_id(
+        compose_menu_id(&ComposeAction::DownProject { project }),
+        "Down Project (remove containers)",
+        true,
+        None,
+    ))?;
+    Ok(submenu)
+}
+
+/// Stop, kill, restart and remove actions for one container.
+fn container_submenu(label: String, container: &str, runtime: ContainerRuntime) -> Result<Submenu> {
+    let submenu = Submenu::new(label, true);
+    let item = |action, text| {
+        MenuItem::with_id(
+            container_action_menu_id(container, runtime, action),
+            text,
+            true,
+            None,
+        )
+    };
+    submenu.append(&item(ContainerAction::Stop, "Stop Container"))?;
+    submenu.append(&item(ContainerAction::Kill, "Kill Container"))?;
+    submenu.append(&item(ContainerAction::Restart, "Restart Container"))?;
+    submenu.append(&PredefinedMenuItem::separator())?;
+    submenu.append(&item(
+        ContainerAction::Remove {
+            volumes: false,
+            prune_networks: false,
+        },
+        "Remove Container",
+    ))?;
+    submenu.append(&item(
+        ContainerAction::Remove {
+            volumes: true,
+            prune_networks: false,
+        },
+        "Remove Container and Volumes",
+    ))?;
+    submenu.append(&item(
+        ContainerAction::Remove {
+            volumes: false,
+            prune_networks: true,
+        },
+        "Remove and Prune Networks",
+    ))?;
+    Ok(submenu)
+}
+
+/// `<prefix><runtime>_<name>`: the same name can run under two runtimes.
+fn container_menu_id(prefix: &str, runtime: ContainerRuntime, container: &str) -> String {
+    format!("{}{}_{}", prefix, runtime.binary(), container)
+}
+
+fn parse_container_menu_id(rest: &str) -> Option<(ContainerRuntime, String)> {
+    let (runtime, name) = rest.split_once('_')?;
+    Some((
+        ContainerRuntime::from_binary(runtime)?,
+        sanitize_identifier(name),
+    ))
+}
+
+pub fn container_action_menu_id(
+    container: &str,
+    runtime: ContainerRuntime,
+    action: ContainerAction,
+) -> String {
+    let prefix = match action {
+        ContainerAction::Stop => MENU_ID_DOCKER_STOP_PREFIX,
+        ContainerAction::Kill => MENU_ID_DOCKER_KILL_PREFIX,
+        ContainerAction::Restart => MENU_ID_DOCKER_RESTART_PREFIX,
+        ContainerAction::Remove {
+            prune_networks: true,
+            ..
+        } => MENU_ID_DOCKER_REMOVE_PRUNE_PREFIX,
+        ContainerAction::Remove { volumes: true, .. } => MENU_ID_DOCKER_REMOVE_VOLUMES_PREFIX,
+        ContainerAction::Remove { .. } => MENU_ID_DOCKER_REMOVE_PREFIX,
+    };
+    container_menu_id(prefix, runtime, container)
+}
+
+pub fn compose_menu_id(action: &ComposeAction) -> String {
+    match action {
+        ComposeAction::StopProject { project } => {
+            format!("{}{}", MENU_ID_COMPOSE_STOP_PREFIX, project)
+        }
+        ComposeAction::RestartService { project, service } => {
+            format!("{}{}:{}", MENU_ID_COMPOSE_RESTART_PREFIX, project, service)
+        }
+        ComposeAction::DownProject { project } => {
+            format!("{}{}", MENU_ID_COMPOSE_DOWN_PREFIX, project)
+        }
+    }
+}
+
+fn protected_submenu(label: String, force_id: String, force_label: &str) -> Result<Submenu> {
+    let submenu = Submenu::new(label, true);
+    submenu.append(&MenuItem::new(
+        "🔒 Protected · skipped by Stop All",
+        false,
+        None,
+    ))?;
+    submenu.append(&MenuItem::with_id(force_id, force_label, true, None))?;
+    Ok(submenu)
+}
+
+pub fn process_tree_menu_id(pid: i32, port: u16) -> String {
+    format!("{}{}_{}", MENU_ID_PROCESS_TREE_PREFIX, pid, port)
+}
+
+/// "2 children: esbuild, sh" — names are de-duplicated and capped.
+pub fn format_children_label(children: &[ChildProcess]) -> String {
+    let mut names: Vec<&str> = Vec::new();
+    for child in children {
+        if !names.contains(&child.command.as_str()) {
+            names.push(&child.command);
+        }
+    }
+    let noun = if children.len() == 1 {
+        "child"
+    } else {
+        "children"
+    };
+    let mut label = format!("{} {}: ", children.len(), noun);
+    label.push_str(&names[..names.len().min(MAX_CHILD_NAMES)].join(", "));
+    if names.len() > MAX_CHILD_NAMES {
+        label.push_str(", …");
+    }
+    label
+}
+
 pub fn parse_menu_action(id: &MenuId) -> Option<crate::model::MenuAction> {
     let raw = id.as_ref();
     if raw == MENU_ID_KILL_ALL {
@@ -347,34 +1184,177 @@ pub fn parse_menu_action(id: &MenuId) -> Option<crate::model::MenuAction> {
         Some(crate::model::MenuAction::DownloadUpdate)
     } else if raw == MENU_ID_DISMISS_UPDATE {
         Some(crate::model::MenuAction::DismissUpdate)
+    } else if raw == MENU_ID_DISMISS_RESPAWN {
+        Some(crate::model::MenuAction::DismissRespawn)
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_SUPERVISOR_PREFIX) {
+        Some(crate::model::MenuAction::KillSupervisor {
+            pid: rest.parse().ok()?,
+        })
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_UNDO_CONTAINER_PREFIX) {
+        let (runtime, name) = parse_container_menu_id(rest)?;
+        Some(crate::model::MenuAction::UndoStop(
+            StoppedService::Container { name, runtime },
+        ))
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_UNDO_BREW_PREFIX) {
+        Some(crate::model::MenuAction::UndoStop(
+            StoppedService::BrewService(sanitize_identifier(rest)),
+        ))
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_RESTART_PREFIX) {
+        let (protocol, port) = rest.split_once('_')?;
+        let protocol = match protocol {
+            "tcp" => Protocol::Tcp,
+            "udp" => Protocol::Udp,
+            _ => return None,
+        };
+        Some(crate::model::MenuAction::Restart {
+            port: port.parse().ok()?,
+            protocol,
+        })
     } else if let Some(rest) = raw.strip_prefix(MENU_ID_DOCKER_STOP_PREFIX) {
+        let (runtime, container) = parse_container_menu_id(rest)?;
         Some(crate::model::MenuAction::DockerStop {
-            container: sanitize_identifier(rest),
+            container,
+            runtime,
+            force: false,
         })
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_DOCKER_FORCE_PREFIX) {
+        let (runtime, container) = parse_container_menu_id(rest)?;
+        Some(crate::model::MenuAction::DockerStop {
+            container,
+            runtime,
+            force: true,
+        })
+    } else if let Some((action, rest)) = [
+        (ContainerAction::Kill, MENU_ID_DOCKER_KILL_PREFIX),
+        (ContainerAction::Restart, MENU_ID_DOCKER_RESTART_PREFIX),
+        (
+            ContainerAction::Remove {
+                volumes: false,
+                prune_networks: false,
+            },
+            MENU_ID_DOCKER_REMOVE_PREFIX,
+        ),
+        (
+            ContainerAction::Remove {
+                volumes: true,
+                prune_networks: false,
+            },
+            MENU_ID_DOCKER_REMOVE_VOLUMES_PREFIX,
+        ),
+        (
+            ContainerAction::Remove {
+                volumes: false,
+                prune_networks: true,
+            },
+            MENU_ID_DOCKER_REMOVE_PRUNE_PREFIX,
+        ),
+    ]
+    .into_iter()
+    .find_map(|(action, prefix)| Some((action, raw.strip_prefix(prefix)?)))
+    {
+        let (runtime, container) = parse_container_menu_id(rest)?;
+        Some(crate::model::MenuAction::DockerAction {
+            container,
+            runtime,
+            action,
+        })
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_FORWARD_REESTABLISH_FORCE_PREFIX) {
+        Some(crate::model::MenuAction::ReestablishForward {
+            pid: rest.parse().ok()?,
+            force: true,
+        })
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_FORWARD_REESTABLISH_PREFIX) {
+        Some(crate::model::MenuAction::ReestablishForward {
+            pid: rest.parse().ok()?,
+            force: false,
+        })
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_SSH_CANCEL_FORCE_PREFIX) {
+        let (pid, port) = rest.split_once('_')?;
+        Some(crate::model::MenuAction::CancelSshTunnel {
+            pid: pid.parse().ok()?,
+            port: port.parse().ok()?,
+            force: true,
+        })
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_SSH_CANCEL_PREFIX) {
+        let (pid, port) = rest.split_once('_')?;
+        Some(crate::model::MenuAction::CancelSshTunnel {
+            pid: pid.parse().ok()?,
+            port: port.parse().ok()?,
+            force: false,
+        })
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_COMPOSE_STOP_PREFIX) {
+        Some(crate::model::MenuAction::Compose(
+            ComposeAction::StopProject {
+                project: sanitize_identifier(rest),
+            },
+        ))
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_COMPOSE_DOWN_PREFIX) {
+        Some(crate::model::MenuAction::Compose(
+            ComposeAction::DownProject {
+                project: sanitize_identifier(rest),
+            },
+        ))
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_COMPOSE_RESTART_PREFIX) {
+        let (project, service) = rest.split_once(':')?;
+        Some(crate::model::MenuAction::Compose(
+            ComposeAction::RestartService {
+                project: sanitize_identifier(project),
+                service: sanitize_identifier(service),
+            },
+        ))
     } else if let Some(rest) = raw.strip_prefix(MENU_ID_BREW_STOP_PREFIX) {
         Some(crate::model::MenuAction::BrewStop {
             service: sanitize_identifier(rest),
+            force: false,
+        })
+    } else if let Some(rest) = raw.strip_prefix(MENU_ID_BREW_FORCE_PREFIX) {
+        Some(crate::model::MenuAction::BrewStop {
+            service: sanitize_identifier(rest),
+            force: true,
         })
+    } else if let Some(remainder) = raw.strip_prefix(MENU_ID_PROCESS_TREE_PREFIX) {
+        let mut parts = remainder.split('_');
+        let pid = parts.next()?.parse::<i32>().ok()?;
+        let _port = parts.next()?.parse::<u16>().ok()?;
+        Some(crate::model::MenuAction::KillTree { pid, force: false })
+    } else if let Some(remainder) = raw.strip_prefix(MENU_ID_PROCESS_FORCE_PREFIX) {
+        let mut parts = remainder.split('_');
+        let pid = parts.next()?.parse::<i32>().ok()?;
+        let _port = parts.next()?.parse::<u16>().ok()?;
+        Some(crate::model::MenuAction::KillPid { pid, force: true })
     } else if let Some(remainder) = raw.strip_prefix(MENU_ID_PROCESS_PREFIX) {
         let mut parts = remainder.split('_');
         let pid = parts.next()?.parse::<i32>().ok()?;
         let _port = parts.next()?.parse::<u16>().ok()?;
-        Some(crate::model::MenuAction::KillPid { pid })
+        Some(crate::model::MenuAction::KillPid { pid, force: false })
     } else {
         None
     }
 }
 
-pub fn build_tooltip(processes: &[ProcessInfo], feedback: Option<&KillFeedback>) -> String {
+pub fn build_tooltip(
+    processes: &[ProcessInfo],
+    details: &HashMap<i32, ProcessDetails>,
+    feedback: Option<&KillFeedback>,
+) -> String {
     let mut lines = Vec::new();
     if processes.is_empty() {
         lines.push("No dev port listeners detected.".to_string());
     } else {
         lines.push(format!("Active listeners: {}", processes.len()));
         for process in processes.iter().take(MAX_TOOLTIP_ENTRIES) {
+            let exposure = if process.is_exposed() {
+                " · all interfaces"
+            } else {
+                ""
+            };
             lines.push(format!(
-                "Port {} → {} (PID {})",
-                process.port, process.command, process.pid
+                "{} {} → {} (PID {}){}",
+                process.protocol,
+                process.endpoint(),
+                describe_command(&process.command, details.get(&process.pid)),
+                process.pid,
+                exposure
             ));
         }
         if processes.len() > MAX_TOOLTIP_ENTRIES {
@@ -426,10 +1406,19 @@ pub fn format_command_label(command: &str, ports: &[u16]) -> String {
     label
 }
 
-pub fn collect_targets_for_all(processes: &[ProcessInfo]) -> Vec<crate::model::KillTarget> {
+pub fn collect_targets_for_all(
+    processes: &[ProcessInfo],
+    is_protected: impl Fn(&ProcessInfo) -> bool,
+) -> Vec<crate::model::KillTarget> {
     let mut map: BTreeMap<i32, (String, Vec<u16>)> = BTreeMap::new();
+    // One protected listener shields the whole process
+    let protected: HashSet<i32> = processes
+        .iter()
+        .filter(|p| is_protected(p))
+        .map(|p| p.pid)
+        .collect();
 
-    for process in processes {
+    for process in processes.iter().filter(|p| !protected.contains(&p.pid)) {
         let entry = map
             .entry(process.pid)
             .or_insert_with(|| (process.command.clone(), Vec::new()));
@@ -448,15 +1437,39 @@ pub fn collect_targets_for_all(processes: &[ProcessInfo]) -> Vec<crate::model::K
             }
             ports.sort();
             let label = format_command_label(&command, &ports);
-            Some(crate::model::KillTarget { pid, label })
+            Some(crate::model::KillTarget {
+                pid,
+                label,
+                identity: crate::model::ProcessIdentity::default(),
+                ladder: Vec::new(),
+                children: Vec::new(),
+                tree: false,
+                hooks: crate::model::KillHooks::default(),
+            })
         })
         .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
     use super::*;
     use crate::model::MenuAction;
+    use crate::test_util::listener_on;
+
+    #[test]
+    fn detects_split_family_conflicts() {
+        let v4 = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        let v6 = IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+        // Same PID on both families is a normal dual-stack server
+        assert!(bind_conflicts(&[listener_on(3000, v4, 1), listener_on(3000, v6, 1)]).is_empty());
+        // Different PIDs on different families conflict
+        let conflicts = bind_conflicts(&[listener_on(3000, v4, 1), listener_on(3000, v6, 2)]);
+        assert!(conflicts.contains(&(3000, Protocol::Tcp)));
+        // Different ports never conflict
+        assert!(bind_conflicts(&[listener_on(3000, v4, 1), listener_on(3001, v6, 2)]).is_empty());
+    }
 
     #[test]
     fn parse_simple_actions() {
@@ -474,19 +1487,155 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn container_action_ids_round_trip() {
+        assert!(matches!(
+            parse_menu_action(&MenuId::new(container_action_menu_id(
+                "shop-db-1",
+                ContainerRuntime::Podman,
+                ContainerAction::Stop
+            ))),
+            Some(MenuAction::DockerStop {
+                container,
+                runtime: ContainerRuntime::Podman,
+                force: false
+            }) if container == "shop-db-1"
+        ));
+        for action in [
+            ContainerAction::Kill,
+            ContainerAction::Restart,
+            ContainerAction::Remove {
+                volumes: false,
+                prune_networks: false,
+            },
+            ContainerAction::Remove {
+                volumes: true,
+                prune_networks: false,
+            },
+            ContainerAction::Remove {
+                volumes: false,
+                prune_networks: true,
+            },
+        ] {
+            let id = container_action_menu_id("shop-db-1", ContainerRuntime::Docker, action);
+            assert!(matches!(
+                parse_menu_action(&MenuId::new(&id)),
+                Some(MenuAction::DockerAction {
+                    container,
+                    runtime: ContainerRuntime::Docker,
+                    action: parsed
+                }) if container == "shop-db-1" && parsed == action
+            ));
+        }
+    }
+
+    #[test]
+    fn compose_menu_ids_round_trip() {
+        let actions = [
+            ComposeAction::StopProject {
+                project: "shop".into(),
+            },
+            ComposeAction::RestartService {
+                project: "shop".into(),
+                service: "api-worker".into(),
+            },
+            ComposeAction::DownProject {
+                project: "shop".into(),
+            },
+        ];
+        for action in actions {
+            let id = compose_menu_id(&action);
+            assert!(matches!(
+                parse_menu_action(&MenuId::new(&id)),
+                Some(MenuAction::Compose(parsed)) if parsed == action
+            ));
+        }
+    }
+
     #[test]
     fn parse_targeted_actions() {
         assert!(matches!(
-            parse_menu_action(&MenuId::new("docker_stop_mycontainer")),
-            Some(MenuAction::DockerStop { container }) if container == "mycontainer"
+            parse_menu_action(&MenuId::new("docker_stop_docker_my_container")),
+            Some(MenuAction::DockerStop {
+                container,
+                runtime: ContainerRuntime::Docker,
+                force: false
+            }) if container == "my_container"
+        ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new("docker_force_nerdctl_mycontainer")),
+            Some(MenuAction::DockerStop {
+                container,
+                runtime: ContainerRuntime::Nerdctl,
+                force: true
+            }) if container == "mycontainer"
         ));
+        assert!(parse_menu_action(&MenuId::new("docker_stop_mycontainer")).is_none());
         assert!(matches!(
             parse_menu_action(&MenuId::new("brew_stop_postgresql")),
-            Some(MenuAction::BrewStop { service }) if service == "postgresql"
+            Some(MenuAction::BrewStop { service, force: false }) if service == "postgresql"
         ));
         assert!(matches!(
             parse_menu_action(&MenuId::new("process_1234_3000")),
-            Some(MenuAction::KillPid { pid }) if pid == 1234
+            Some(MenuAction::KillPid { pid, force: false }) if pid == 1234
+        ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new("process_tree_1234_3000")),
+            Some(MenuAction::KillTree { pid, .. }) if pid == 1234
+        ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new(process_force_menu_id(1234, 3000))),
+            Some(MenuAction::KillPid { pid, force: true }) if pid == 1234
+        ));
+        let redis = StoppedService::BrewService("redis".into());
+        assert!(matches!(
+            parse_menu_action(&MenuId::new(undo_menu_id(&redis))),
+            Some(MenuAction::UndoStop(service)) if service == redis
+        ));
+        let db = StoppedService::Container {
+            name: "shop_db_1".into(),
+            runtime: ContainerRuntime::Nerdctl,
+        };
+        assert!(matches!(
+            parse_menu_action(&MenuId::new(undo_menu_id(&db))),
+            Some(MenuAction::UndoStop(service)) if service == db
+        ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new(restart_menu_id(5353, Protocol::Udp))),
+            Some(MenuAction::Restart {
+                port: 5353,
+                protocol: Protocol::Udp
+            })
+        ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new("forward_reestablish_4321")),
+            Some(MenuAction::ReestablishForward {
+                pid: 4321,
+                force: false
+            })
+        ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new("forward_reestablish_force_4321")),
+            Some(MenuAction::ReestablishForward {
+                pid: 4321,
+                force: true
+            })
+        ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new("ssh_cancel_4321_5432")),
+            Some(MenuAction::CancelSshTunnel {
+                pid: 4321,
+                port: 5432,
+                force: false
+            })
+        ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new("ssh_cancel_force_4321_5432")),
+            Some(MenuAction::CancelSshTunnel {
+                pid: 4321,
+                port: 5432,
+                force: true
+            })
         ));
         assert!(matches!(
             parse_menu_action(&MenuId::new("docker_stop_all")),
@@ -496,6 +1645,95 @@ mod tests {
             parse_menu_action(&MenuId::new("brew_stop_all")),
             Some(MenuAction::BrewStopAll)
         ));
+        assert!(matches!(
+            parse_menu_action(&MenuId::new("dismiss_respawn")),
+            Some(MenuAction::DismissRespawn)
+        ));
+    }
+
+    #[test]
+    fn supervisor_items_map_to_stop_actions() {
+        let id = |supervisor: Supervisor| {
+            supervisor_menu_id(&supervisor).and_then(|id| parse_menu_action(&MenuId::new(id)))
+        };
+        assert!(matches!(
+            id(Supervisor::Process {
+                pid: 1200,
+                name: "node nodemon".into(),
+                identity: Default::default(),
+            }),
+            Some(MenuAction::KillSupervisor { pid: 1200 })
+        ));
+        assert!(matches!(
+            id(Supervisor::Container {
+                name: "db".into(),
+                runtime: ContainerRuntime::Podman,
+                restart_policy: Some("always".into())
+            }),
+            Some(MenuAction::DockerStop {
+                container,
+                runtime: ContainerRuntime::Podman,
+                ..
+            }) if container == "db"
+        ));
+        assert!(
+            id(Supervisor::Init {
+                name: "launchd".into()
+            })
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn formats_children_label() {
+        let child = |pid, command: &str| ChildProcess {
+            pid,
+            command: command.into(),
+        };
+        assert_eq!(
+            format_children_label(&[child(2, "esbuild")]),
+            "1 child: esbuild"
+        );
+        let workers = [
+            child(2, "gunicorn"),
+            child(3, "gunicorn"),
+            child(4, "sh"),
+            child(5, "esbuild"),
+            child(6, "node"),
+        ];
+        assert_eq!(
+            format_children_label(&workers),
+            "5 children: gunicorn, sh, esbuild, …"
+        );
+    }
+
+    #[test]
+    fn formats_history_labels() {
+        let record = HistoryRecord {
+            ts: 1_700_000_000,
+            kind: HistoryKind::Stopped,
+            port: 5353,
+            protocol: Protocol::Udp,
+            address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            pid: 42,
+            command: "node".into(),
+            project: Some("web".into()),
+            container: None,
+            duration_secs: Some(125),
+        };
+        let label = format_history_label(&record);
+        assert!(label.ends_with("■ 5353/udp node (PID 42) · web · ran 2m"));
+        assert!(label.starts_with(&format_clock(record.ts)));
+    }
+
+    #[test]
+    fn formats_resource_usage() {
+        assert_eq!(format_rss(512), "512 KB");
+        assert_eq!(format_rss(120_340), "117.5 MB");
+        assert_eq!(format_uptime(42), "42s");
+        assert_eq!(format_uptime(3 * 3600 + 5 * 60), "3h 5m");
+        assert_eq!(format_uptime(2 * 86_400 + 3600), "2d 1h");
+        assert_eq!(truncate_label("abcdef", 4), "abc…");
     }
 
     #[test]
@@ -512,20 +1750,26 @@ mod tests {
     fn collect_targets_groups_by_pid() {
         let p1 = ProcessInfo {
             port: 3000,
+            protocol: Protocol::Tcp,
+            address: IpAddr::V4(Ipv4Addr::LOCALHOST),
             pid: 111,
             command: "node".into(),
         };
         let p2 = ProcessInfo {
             port: 3001,
+            protocol: Protocol::Tcp,
+            address: IpAddr::V4(Ipv4Addr::LOCALHOST),
             pid: 111,
             command: "node".into(),
         };
         let p3 = ProcessInfo {
             port: 5173,
+            protocol: Protocol::Tcp,
+            address: IpAddr::V4(Ipv4Addr::LOCALHOST),
             pid: 222,
             command: "vite".into(),
         };
-        let targets = collect_targets_for_all(&[p1, p2, p3]);
+        let targets = collect_targets_for_all(&[p1, p2, p3], |_| false);
         assert_eq!(targets.len(), 2);
         assert!(
             targets
@@ -538,4 +1782,16 @@ mod tests {
                 .any(|t| t.pid == 222 && t.label.contains("5173"))
         );
     }
+
+    #[test]
+    fn collect_targets_skips_protected_processes() {
+        let listeners = [
+            listener_on(3000, IpAddr::V4(Ipv4Addr::LOCALHOST), 111),
+            listener_on(5432, IpAddr::V4(Ipv4Addr::LOCALHOST), 111),
+            listener_on(5173, IpAddr::V4(Ipv4Addr::LOCALHOST), 222),
+        ];
+        let targets = collect_targets_for_all(&listeners, |p| p.port == 5432);
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].pid, 222);
+    }
 }
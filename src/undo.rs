@@ -0,0 +1,107 @@
+use std::time::{Duration, Instant};
+
+use crate::integrations::brew::run_brew_start;
+use crate::integrations::docker::run_docker_start;
+use crate::integrations::runtime::ContainerRuntime;
+use crate::model::KillFeedback;
+
+/// A container or brew service that PortKiller stopped.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StoppedService {
+    Container {
+        name: String,
+        runtime: ContainerRuntime,
+    },
+    BrewService(String),
+}
+
+impl StoppedService {
+    pub fn name(&self) -> &str {
+        match self {
+            StoppedService::Container { name, .. } | StoppedService::BrewService(name) => name,
+        }
+    }
+
+    /// Start it again with the matching integration.
+    pub fn start(&self) -> KillFeedback {
+        match self {
+            StoppedService::Container { name, runtime } => run_docker_start(*runtime, name),
+            StoppedService::BrewService(name) => run_brew_start(name),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UndoEntry {
+    pub service: StoppedService,
+    pub stopped_at: Instant,
+}
+
+/// Recent stops that can still be undone, newest first.
+#[derive(Clone, Debug, Default)]
+pub struct UndoBuffer {
+    entries: Vec<UndoEntry>,
+}
+
+impl UndoBuffer {
+    pub fn push(&mut self, service: StoppedService, now: Instant) {
+        self.entries.retain(|e| e.service != service);
+        self.entries.insert(
+            0,
+            UndoEntry {
+                service,
+                stopped_at: now,
+            },
+        );
+    }
+
+    /// Remove an entry so it cannot be undone twice.
+    pub fn take(&mut self, service: &StoppedService) -> Option<UndoEntry> {
+        let idx = self.entries.iter().position(|e| &e.service == service)?;
+        Some(self.entries.remove(idx))
+    }
+
+    /// Drop entries older than `window`; true if any were dropped.
+    pub fn expire(&mut self, now: Instant, window: Duration) -> bool {
+        let before = self.entries.len();
+        self.entries
+            .retain(|e| now.duration_since(e.stopped_at) < window);
+        self.entries.len() != before
+    }
+
+    pub fn entries(&self) -> &[UndoEntry] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_stops_for_the_window() {
+        let start = Instant::now();
+        let window = Duration::from_secs(30);
+        let mut buffer = UndoBuffer::default();
+        buffer.push(
+            StoppedService::Container {
+                name: "db".into(),
+                runtime: ContainerRuntime::Podman,
+            },
+            start,
+        );
+        buffer.push(
+            StoppedService::BrewService("redis".into()),
+            start + Duration::from_secs(20),
+        );
+        assert_eq!(buffer.entries()[0].service.name(), "redis");
+
+        assert!(buffer.expire(start + Duration::from_secs(31), window));
+        assert_eq!(buffer.entries().len(), 1);
+        assert!(!buffer.expire(start + Duration::from_secs(31), window));
+
+        let redis = StoppedService::BrewService("redis".into());
+        assert!(buffer.take(&redis).is_some());
+        assert!(buffer.take(&redis).is_none());
+    }
+}
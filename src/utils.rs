@@ -10,6 +10,14 @@ pub fn find_command(name: &str) -> &'static str {
             static DOCKER: OnceLock<&'static str> = OnceLock::new();
             DOCKER.get_or_init(|| find_in_paths(name, HOMEBREW_PATHS))
         }
+        "podman" => {
+            static PODMAN: OnceLock<&'static str> = OnceLock::new();
+            PODMAN.get_or_init(|| find_in_paths(name, HOMEBREW_PATHS))
+        }
+        "nerdctl" => {
+            static NERDCTL: OnceLock<&'static str> = OnceLock::new();
+            NERDCTL.get_or_init(|| find_in_paths(name, HOMEBREW_PATHS))
+        }
         "brew" => {
             static BREW: OnceLock<&'static str> = OnceLock::new();
             BREW.get_or_init(|| find_in_paths(name, HOMEBREW_PATHS))